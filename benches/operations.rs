@@ -0,0 +1,124 @@
+// Copyright 2024 quill-delta-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Benchmarks for the core operations on generated deltas of increasing size.
+//!
+//! Run with: `cargo bench --features test-util`
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use delta::attributes::Attributes;
+use delta::delta::Delta;
+use delta::document::Document;
+use delta::optransform::OpTransform;
+use delta::test_util::{generate_change, generate_document};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const SIZES: [usize; 3] = [100, 1_000, 10_000];
+
+/// System allocator counting the allocations, to report allocations per call.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Deltas the operations run on, generated for one size.
+struct Fixture {
+    size: usize,
+    doc: Delta,
+    a: Delta,
+    b: Delta,
+    edited: Delta,
+    format: Delta,
+    typed: Delta,
+}
+
+impl Fixture {
+    fn new(size: usize) -> Self {
+        let doc = generate_document(size);
+        let len = doc.document_length();
+        let a = generate_change(len, size / 20);
+        let b = generate_change(len, size / 10);
+        let edited = doc.compose(&a).unwrap();
+
+        // formatting the whole document retains every insert without slicing it
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let format = Delta::retain_all(len, bold);
+
+        // a single edit in the middle, as typing gives
+        let mut typing = Delta::default();
+        typing.retain(len / 2);
+        typing.insert("typed");
+        let typed = doc.compose(&typing).unwrap();
+
+        Fixture {
+            size,
+            doc,
+            a,
+            b,
+            edited,
+            format,
+            typed,
+        }
+    }
+}
+
+/// Benchmarks `f` on the fixture of each size, and reports the number of
+/// allocations of a single call, which criterion does not measure.
+fn bench<T>(c: &mut Criterion, name: &str, fixtures: &[Fixture], f: fn(&Fixture) -> T) {
+    let mut group = c.benchmark_group(name);
+    for fixture in fixtures {
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        black_box(f(fixture));
+        let allocs = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+        println!("{name}/{}: {allocs} allocs / iter", fixture.size);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(fixture.size),
+            fixture,
+            |bencher, fixture| {
+                bencher.iter(|| f(black_box(fixture)));
+            },
+        );
+    }
+    group.finish();
+}
+
+fn operations(c: &mut Criterion) {
+    let fixtures: Vec<Fixture> = SIZES.into_iter().map(Fixture::new).collect();
+
+    bench(c, "compose", &fixtures, |x| x.doc.compose(&x.a).unwrap());
+    bench(c, "format", &fixtures, |x| {
+        x.doc.compose(&x.format).unwrap()
+    });
+    bench(c, "transform", &fixtures, |x| {
+        x.a.transform(&x.b, true).unwrap()
+    });
+    bench(c, "diff", &fixtures, |x| x.doc.diff(&x.edited, 0).unwrap());
+    bench(c, "diff local", &fixtures, |x| {
+        x.doc.diff(&x.typed, 0).unwrap()
+    });
+    bench(c, "invert", &fixtures, |x| x.a.invert(&x.doc));
+}
+
+criterion_group!(benches, operations);
+criterion_main!(benches);
@@ -13,6 +13,8 @@
 
 pub mod replace;
 pub use replace::*;
+pub mod no_replace;
+pub use no_replace::*;
 /// Myers' diff algorithm
 pub mod myers;
 /// Patience diff algorithm
@@ -20,6 +22,39 @@ pub mod patience;
 
 pub use myers::diff;
 
+use std::fmt;
+
+/// A general purpose error, for visitors that do not need an error type of
+/// their own. Visitors using `()` as error type keep working, and can be
+/// converted with `From<()>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The visitor failed without further information
+    Unspecified,
+    /// The visitor stopped the diff on purpose, e.g. because a limit was reached
+    Aborted,
+    /// The visitor failed with a message
+    Message(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Unspecified => write!(f, "diff visitor failed"),
+            Error::Aborted => write!(f, "diff aborted by visitor"),
+            Error::Message(msg) => write!(f, "diff visitor failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<()> for Error {
+    fn from(_: ()) -> Self {
+        Error::Unspecified
+    }
+}
+
 // #[cfg(test)]
 // mod test;
 
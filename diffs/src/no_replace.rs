@@ -0,0 +1,57 @@
+use Diff;
+
+/// A "differ" that forwards replacements as a deletion followed by an
+/// insertion, so the wrapped visitor never receives `replace` calls.
+///
+/// Useful after `Replace`, to get blocks of maximal length while still
+/// handling only deletions and insertions.
+pub struct NoReplace<D: Diff> {
+    d: D,
+}
+
+impl<D: Diff> NoReplace<D> {
+    pub fn new(d: D) -> Self {
+        NoReplace { d }
+    }
+    pub fn into_inner(self) -> D {
+        self.d
+    }
+}
+
+impl<D: Diff> AsRef<D> for NoReplace<D> {
+    fn as_ref(&self) -> &D {
+        &self.d
+    }
+}
+
+impl<D: Diff> AsMut<D> for NoReplace<D> {
+    fn as_mut(&mut self) -> &mut D {
+        &mut self.d
+    }
+}
+
+impl<D: Diff> Diff for NoReplace<D> {
+    type Error = D::Error;
+    fn equal(&mut self, old: usize, new: usize, len: usize) -> Result<(), D::Error> {
+        self.d.equal(old, new, len)
+    }
+    fn delete(&mut self, old: usize, len: usize, new: usize) -> Result<(), D::Error> {
+        self.d.delete(old, len, new)
+    }
+    fn insert(&mut self, old: usize, new: usize, new_len: usize) -> Result<(), D::Error> {
+        self.d.insert(old, new, new_len)
+    }
+    fn replace(
+        &mut self,
+        old: usize,
+        old_len: usize,
+        new: usize,
+        new_len: usize,
+    ) -> Result<(), D::Error> {
+        self.d.delete(old, old_len, new)?;
+        self.d.insert(old, new, new_len)
+    }
+    fn finish(&mut self) -> Result<(), D::Error> {
+        self.d.finish()
+    }
+}
@@ -32,7 +32,7 @@ fn unique<A: Hash + Eq, S: Index<usize, Output = A> + ?Sized>(
     p: &S,
     e0: usize,
     e1: usize,
-) -> Vec<I<S>> {
+) -> Vec<I<'_, S>> {
     let mut aa = HashMap::new();
     for i in e0..e1 {
         match aa.entry(&p[i]) {
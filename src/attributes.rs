@@ -6,7 +6,7 @@
 // copied, modified, or distributed except according to those terms.
 
 use serde_derive::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 #[cfg(test)]
 use std::fmt;
 use std::iter::Iterator;
@@ -16,7 +16,7 @@ use crate::types::attr_val::AttrVal;
 
 
 /// Attributes hold a list of properties that determine an insert-value should be formatted.
-/// When creating a delta diff(), or similar, the attribute may also get the value `Attr_val::Null'
+/// When creating a delta `diff()`, or similar, the attribute may also get the value `AttrVal::Null`
 /// indicating that the attribute should be removed when the `diff` is applied.
 #[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct Attributes {
@@ -25,26 +25,156 @@ pub struct Attributes {
 }
 
 impl Attributes {
-    /// # is_equal()
+    /// # `is_equal()`
     ///
     /// Returns tue when 2 attribute structures contain the identical content.
     pub fn is_equal(&self, other: &Attributes) -> bool {
         diff(other, self).is_empty()
     }
 
-    /// # insert()
+    /// # `insert()`
     ///
     /// Insert a value for a given key into the attributes
     pub fn insert<K: Into<String>, V: Into<AttrVal>>(&mut self, key: K, value: V) {
         self.attr.insert(key.into(), value.into());
     }
 
-    /// # is_empty()
+    /// # `from_pairs()`
+    ///
+    /// Builds attributes from key-value pairs, see also the `attrs!` macro.
+    /// ```
+    /// use delta::attributes::Attributes;
+    ///
+    /// let attr = Attributes::from_pairs([("bold", true), ("italic", false)]);
+    /// assert_eq!(attr.len(), 2);
+    /// ```
+    pub fn from_pairs<K, V>(pairs: impl IntoIterator<Item = (K, V)>) -> Attributes
+    where
+        K: Into<String>,
+        V: Into<AttrVal>,
+    {
+        let mut attributes = Attributes::default();
+        for (key, value) in pairs {
+            attributes.insert(key, value);
+        }
+        attributes
+    }
+
+    /// # `is_empty()`
     ///
     /// Returns tue when there is no content in the attributes.
     pub fn is_empty(&self) -> bool {
         self.attr.is_empty()
     }
+
+    /// # `to_sorted_json()`
+    ///
+    /// Serializes the attributes to JSON with the keys in sorted order, also in
+    /// nested maps. Equal attributes give the same string, regardless of the order
+    /// in which they were inserted, so it can be used as a content hash or cache key.
+    /// ```
+    /// use delta::attributes::Attributes;
+    ///
+    /// let mut attr = Attributes::default();
+    /// attr.insert("italic", true);
+    /// attr.insert("bold", true);
+    /// assert_eq!(attr.to_sorted_json(), r#"{"bold":true,"italic":true}"#);
+    /// ```
+    pub fn to_sorted_json(&self) -> String {
+        let sorted: BTreeMap<&str, Sorted> = self
+            .attr
+            .iter()
+            .map(|(key, val)| (key.as_str(), Sorted::from(val)))
+            .collect();
+        // Maps with string keys always serialize
+        serde_json::to_string(&sorted).unwrap_or_default()
+    }
+
+    /// # `coerce_types()`
+    ///
+    /// Converts string values `"true"` and `"false"` to booleans, and strings holding
+    /// an unsigned integer, like `"15"`, to numbers, also in nested maps.
+    /// Useful for data sources that store all values as strings, which otherwise
+    /// never compare equal to the typed values in `diff()` and `compose()`.
+    ///
+    /// Strings that would not round-trip, like `"015"` or `"15px"`, are kept.
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::types::attr_val::AttrVal;
+    ///
+    /// let mut attr = Attributes::default();
+    /// attr.insert("bold", "true");
+    /// attr.coerce_types();
+    /// assert_eq!(attr.get("bold"), Some(&AttrVal::Bool(true)));
+    /// ```
+    pub fn coerce_types(&mut self) -> &mut Attributes {
+        self.attr.values_mut().for_each(coerce_type);
+        self
+    }
+}
+
+/// # attrs!
+///
+/// Builds `Attributes` from `key => value` pairs, where the values may have different
+/// types, as long as they convert in to an `AttrVal`.
+/// ```
+/// use delta::attrs;
+/// use delta::types::attr_val::AttrVal;
+///
+/// let attr = attrs! { "bold" => true, "color" => "red", "size" => 12, "font" => AttrVal::Null };
+/// assert_eq!(attr.get("color"), Some(&AttrVal::from("red")));
+/// assert!(attrs! {}.is_empty());
+/// ```
+#[macro_export]
+macro_rules! attrs {
+    ($($key:expr => $value:expr),* $(,)?) => {
+        $crate::attributes::Attributes::from_pairs::<
+            ::std::string::String,
+            $crate::types::attr_val::AttrVal,
+        >([$((
+            ::std::string::String::from($key),
+            $crate::types::attr_val::AttrVal::from($value),
+        )),*])
+    };
+}
+
+/// Private method
+/// Converts a string value holding a boolean or an unsigned integer, for `coerce_types()`
+fn coerce_type(val: &mut AttrVal) {
+    match val {
+        AttrVal::String(s) if s == "true" || s == "false" => *val = AttrVal::Bool(s == "true"),
+        AttrVal::String(s) => {
+            if let Ok(n) = s.parse::<usize>() {
+                if n.to_string() == *s {
+                    *val = AttrVal::Number(n);
+                }
+            }
+        }
+        AttrVal::Map(map) => map.values_mut().for_each(coerce_type),
+        _ => {}
+    }
+}
+
+/// Private enum
+/// Attribute value with the keys of nested maps in sorted order, for `to_sorted_json()`
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Sorted<'a> {
+    Value(&'a AttrVal),
+    Map(BTreeMap<&'a str, Sorted<'a>>),
+}
+
+impl<'a> From<&'a AttrVal> for Sorted<'a> {
+    fn from(val: &'a AttrVal) -> Self {
+        match val {
+            AttrVal::Map(map) => Sorted::Map(
+                map.iter()
+                    .map(|(key, val)| (key.as_str(), Sorted::from(val)))
+                    .collect(),
+            ),
+            _ => Sorted::Value(val),
+        }
+    }
 }
 
 impl Deref for Attributes {
@@ -61,13 +191,13 @@ impl DerefMut for Attributes {
     }
 }
 
-/// # Compose()
+/// # `Compose()`
 ///
 /// Returns a Delta that is equivalent to applying the operations of
 /// own Delta, followed by another Delta.
 /// 1) if `Attr_val::null` values should be removed, remove them from the base
 /// 2) if the base does NOT contain the key from the delta then we add it to base
-///    regardless if the delta value is "null" or a string, or a Attr_val::MAP
+///    regardless if the delta value is "null" or a string, or an `AttrVal::Map`
 ///
 /// Param:
 ///  - base: base delta
@@ -94,7 +224,7 @@ pub fn compose(attrib: &Attributes, base: &Attributes, keep_null: bool) -> Attri
     ret
 }
 
-/// # transform()
+/// # `transform()`
 ///
 /// Transform given Delta attribute set against another attribute set.
 ///
@@ -113,10 +243,10 @@ pub fn compose(attrib: &Attributes, base: &Attributes, keep_null: bool) -> Attri
 pub fn transform(attrib: &Attributes, base: &Attributes, priority: bool) -> Attributes {
     if attrib.is_empty() {
         return base.clone();
-    };
+    }
     if base.is_empty() {
         return Attributes::default();
-    };
+    }
 
     if !priority {
         // b simply overwrites us without priority
@@ -138,7 +268,7 @@ pub fn transform(attrib: &Attributes, base: &Attributes, priority: bool) -> Attr
     ret
 }
 
-/// # diff()
+/// # `diff()`
 ///
 /// Returns Delta - difference between the two attribute sets
 ///  - base: first quill delta
@@ -160,7 +290,68 @@ pub fn diff(attrib: &Attributes, base: &Attributes) -> Attributes {
     ret
 }
 
-/// # invert()
+/// # `AttrDiff`
+///
+/// The difference between two attribute sets, split by kind, see `diff_categorized()`.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct AttrDiff {
+    /// Keys only in the new attributes, with their new value
+    pub added: Attributes,
+    /// Keys only in the old attributes, with their old value
+    pub removed: Attributes,
+    /// Keys in both attributes with a different value, with their new value
+    pub changed: Attributes,
+}
+
+/// # `diff_categorized()`
+///
+/// Returns the same difference as `diff()`, split in to added, removed and changed
+/// attributes. Where `diff()` marks a removed attribute with `Null`, the removed
+/// map holds its old value.
+///  - base: first quill delta
+///  - attrib: second quill delta
+/// ```
+/// use delta::attributes::{diff_categorized, Attributes};
+///
+/// let mut old = Attributes::default();
+/// old.insert("bold", true);
+/// let mut new = Attributes::default();
+/// new.insert("italic", true);
+///
+/// let res = diff_categorized(&old, &new);
+/// assert_eq!(res.added, new);
+/// assert_eq!(res.removed, old);
+/// assert!(res.changed.is_empty());
+/// ```
+pub fn diff_categorized(attrib: &Attributes, base: &Attributes) -> AttrDiff {
+    let mut ret = AttrDiff::default();
+    for (key, val) in diff(attrib, base).attr {
+        match (attrib.get(&key), base.get(&key)) {
+            (Some(old), None) => ret.removed.insert(key, old.clone()),
+            (None, _) => ret.added.insert(key, val),
+            (Some(_), Some(_)) => ret.changed.insert(key, val),
+        }
+    }
+    ret
+}
+
+/// # `diff_no_clears()`
+///
+/// Like `diff()`, but keys missing in `base` are not cleared with a `Null`:
+/// removing an attribute is treated as no change.
+///  - base: first quill delta
+///  - attrib: second quill delta
+pub fn diff_no_clears(attrib: &Attributes, base: &Attributes) -> Attributes {
+    let mut ret = Attributes::default();
+    for (key, val) in &**base {
+        if attrib.get(key) != Some(val) {
+            ret.insert(key.clone(), val.clone());
+        }
+    }
+    ret
+}
+
+/// # `invert()`
 ///
 /// Returned an inverted quill delta that has the opposite effect of against
 /// a base document quill delta.
@@ -212,17 +403,20 @@ pub(crate) fn display_fmt(attr: &Attributes) -> String {
     let mut at = String::new();
     for (k, v) in attr.iter() {
         if at.is_empty() {
-            at = format!(r#"{k:?}:{v}"#);
+            at = format!(r"{k:?}:{v}");
         } else {
-            at = format!(r#"{at}; {k:?}:{v}"#);
+            at = format!(r"{at}; {k:?}:{v}");
         }
     }
-    format!(r#" Attr[{at}] "#)
+    format!(r" Attr[{at}] ")
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::attributes::{compose, diff, invert, transform, Attributes};
+    use crate::attributes::{
+        compose, diff, diff_categorized, invert, transform, AttrDiff, Attributes,
+    };
+    use crate::types::attr_map::AttrMap;
     use crate::types::attr_val::AttrVal;
 
     #[test]
@@ -588,4 +782,168 @@ mod tests {
 
         assert_eq!(res, right);
     }
+
+    #[test]
+    fn to_sorted_json_passes() {
+        let mut link = AttrMap::default();
+        link.insert("href", "https://quilljs.com");
+        link.insert("target", "_blank");
+        link.insert("rel", AttrVal::Null);
+
+        let mut a = Attributes::default();
+        a.insert("color", "red");
+        a.insert("bold", true);
+        a.insert("link", link);
+        a.insert("size", 12);
+
+        let mut link = AttrMap::default();
+        link.insert("rel", AttrVal::Null);
+        link.insert("target", "_blank");
+        link.insert("href", "https://quilljs.com");
+
+        let mut b = Attributes::default();
+        b.insert("size", 12);
+        b.insert("link", link);
+        b.insert("bold", true);
+        b.insert("color", "red");
+
+        assert_eq!(a.to_sorted_json(), b.to_sorted_json());
+        assert_eq!(
+            a.to_sorted_json(),
+            r#"{"bold":true,"color":"red","link":{"href":"https://quilljs.com","rel":null,"target":"_blank"},"size":12}"#
+        );
+        assert_eq!(Attributes::default().to_sorted_json(), "{}");
+    }
+
+    #[test]
+    fn diff_categorized_passes() {
+        let mut link = AttrMap::default();
+        link.insert("href", "https://quilljs.com");
+
+        let mut old = Attributes::default();
+        old.insert("bold", true);
+        old.insert("color", "red");
+        old.insert("size", 12);
+        old.insert("link", link.clone());
+
+        let mut new_link = AttrMap::default();
+        new_link.insert("href", "https://github.com");
+
+        let mut new = Attributes::default();
+        new.insert("bold", true);
+        new.insert("color", "blue");
+        new.insert("italic", true);
+        new.insert("link", new_link.clone());
+
+        let res = diff_categorized(&old, &new);
+
+        let mut added = Attributes::default();
+        added.insert("italic", true);
+        let mut removed = Attributes::default();
+        removed.insert("size", 12);
+        let mut changed = Attributes::default();
+        changed.insert("color", "blue");
+        changed.insert("link", new_link);
+        assert_eq!(res.added, added);
+        assert_eq!(res.removed, removed);
+        assert_eq!(res.changed, changed);
+
+        // all keys of diff() are classified exactly once
+        let plain = diff(&old, &new);
+        assert_eq!(
+            plain.len(),
+            res.added.len() + res.removed.len() + res.changed.len()
+        );
+        assert_eq!(plain.get("size"), Some(&AttrVal::Null));
+
+        assert_eq!(diff_categorized(&old, &old), AttrDiff::default());
+    }
+
+    #[test]
+    fn from_pairs_passes() {
+        let mut expected = Attributes::default();
+        expected.insert("bold", true);
+        expected.insert("italic", true);
+        assert_eq!(
+            Attributes::from_pairs([("bold", true), ("italic", true)]),
+            expected
+        );
+
+        let keys = vec!["bold".to_string(), "italic".to_string()];
+        assert_eq!(
+            Attributes::from_pairs(keys.into_iter().map(|key| (key, true))),
+            expected
+        );
+        assert!(Attributes::from_pairs(Vec::<(&str, bool)>::new()).is_empty());
+    }
+
+    #[test]
+    fn attrs_macro_passes() {
+        let mut font = AttrMap::default();
+        font.insert("family".to_string(), "Helvetica");
+
+        let mut expected = Attributes::default();
+        expected.insert("bold", true);
+        expected.insert("color", "red");
+        expected.insert("size", 12);
+        expected.insert("italic", AttrVal::Null);
+        expected.insert("font", font.clone());
+
+        let color = String::from("color");
+        let attr = crate::attrs! {
+            "bold" => true,
+            color => "red",
+            "size" => 12,
+            "italic" => AttrVal::Null,
+            "font" => font,
+        };
+        assert_eq!(attr, expected);
+        assert_eq!(crate::attrs! {}, Attributes::default());
+    }
+
+    #[test]
+    fn coerce_types_passes() {
+        let mut font = AttrMap::default();
+        font.insert("size", "15");
+        font.insert("family", "Helvetica");
+
+        let mut a = Attributes::default();
+        a.insert("bold", "true");
+        a.insert("italic", "false");
+        a.insert("indent", "2");
+        a.insert("width", "15px");
+        a.insert("code", "007");
+        a.insert("offset", "-1");
+        a.insert("underline", true);
+        a.insert("color", "red");
+        a.insert("link", AttrVal::Null);
+        a.insert("font", font);
+
+        let mut font = AttrMap::default();
+        font.insert("size", 15);
+        font.insert("family", "Helvetica");
+
+        let mut expected = Attributes::default();
+        expected.insert("bold", true);
+        expected.insert("italic", false);
+        expected.insert("indent", 2);
+        expected.insert("width", "15px");
+        expected.insert("code", "007");
+        expected.insert("offset", "-1");
+        expected.insert("underline", true);
+        expected.insert("color", "red");
+        expected.insert("link", AttrVal::Null);
+        expected.insert("font", font);
+
+        a.coerce_types();
+        assert_eq!(a, expected);
+
+        // coerced attributes compare equal to typed ones
+        let mut typed = Attributes::default();
+        typed.insert("bold", true);
+        let mut text = Attributes::default();
+        text.insert("bold", "true");
+        assert_eq!(diff(&typed, &text).len(), 1);
+        assert!(diff(&typed, text.coerce_types()).is_empty());
+    }
 }
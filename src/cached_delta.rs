@@ -0,0 +1,176 @@
+// Copyright 2024 quill-delta-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::attributes::Attributes;
+use crate::delta::Delta;
+use crate::document::Document;
+use crate::operations::{DeltaOperation, OpsVal};
+use crate::utils::DeltaTransformations;
+use std::cell::Cell;
+
+/// # `CachedDelta`
+///
+/// Wrapper around a `Delta` that memoizes `document_length()` and `delta_length()`.
+///
+/// Both lengths take a scan over all operations. For read heavy workloads the
+/// wrapper computes them once, and invalidates the cached values whenever the
+/// delta is changed through the wrapper.
+///
+/// Read access to the delta is available through `Deref`. Mutable access
+/// is only possible through the methods on the wrapper, or `delta_mut()`,
+/// so the cache can never get out of date. Embeds are counted with the embed
+/// length of the wrapped delta, see `Delta::with_embed_length()`, which is fixed
+/// when the delta is created.
+///
+/// The cache is a `Cell`, so unlike `Delta` the wrapper is `Send` but not `Sync`:
+/// share the inner delta, or wrap it in a `Mutex`, to read it from several threads.
+///
+/// ```
+/// use delta::cached_delta::CachedDelta;
+/// use delta::delta::Delta;
+///
+/// let mut doc = CachedDelta::default();
+/// doc.insert("Hello");
+/// assert_eq!(doc.document_length(), 5);
+/// doc.insert(" World");
+/// assert_eq!(doc.document_length(), 11);
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct CachedDelta {
+    delta: Delta,
+    document_length: Cell<Option<usize>>, //cached Document::document_length()
+    delta_length: Cell<Option<usize>>,    //cached DeltaTransformations::delta_length()
+}
+
+impl CachedDelta {
+    pub fn new(delta: Delta) -> Self {
+        CachedDelta {
+            delta,
+            document_length: Cell::new(None),
+            delta_length: Cell::new(None),
+        }
+    }
+
+    /// # `document_length()`
+    ///
+    /// Cached version of `Document::document_length()`
+    pub fn document_length(&self) -> usize {
+        if let Some(len) = self.document_length.get() {
+            return len;
+        }
+        let len = self.delta.document_length();
+        self.document_length.set(Some(len));
+        len
+    }
+
+    /// # `delta_length()`
+    ///
+    /// Cached version of `DeltaTransformations::delta_length()`
+    pub fn delta_length(&self) -> usize {
+        if let Some(len) = self.delta_length.get() {
+            return len;
+        }
+        let len = self.delta.delta_length();
+        self.delta_length.set(Some(len));
+        len
+    }
+
+    /// # `is_cached()`
+    ///
+    /// Returns true when the document length is currently cached.
+    pub fn is_cached(&self) -> bool {
+        self.document_length.get().is_some()
+    }
+
+    /// # `insert()`
+    ///
+    /// See `Delta::insert()`
+    pub fn insert<S: Into<OpsVal>>(&mut self, value: S) {
+        self.invalidate();
+        self.delta.insert(value);
+    }
+
+    /// # `insert_attr()`
+    ///
+    /// See `Delta::insert_attr()`
+    pub fn insert_attr<S: Into<OpsVal>>(&mut self, value: S, attributes: Attributes) {
+        self.invalidate();
+        self.delta.insert_attr(value, attributes);
+    }
+
+    /// # `retain()`
+    ///
+    /// See `Delta::retain()`
+    pub fn retain(&mut self, length: usize) {
+        self.invalidate();
+        self.delta.retain(length);
+    }
+
+    /// # `retain_attr()`
+    ///
+    /// See `Delta::retain_attr()`
+    pub fn retain_attr(&mut self, length: usize, attributes: Attributes) {
+        self.invalidate();
+        self.delta.retain_attr(length, attributes);
+    }
+
+    /// # `delete()`
+    ///
+    /// See `Delta::delete()`
+    pub fn delete(&mut self, length: usize) {
+        self.invalidate();
+        self.delta.delete(length);
+    }
+
+    /// # `push()`
+    ///
+    /// See `Delta::push()`
+    pub fn push(&mut self, new_op: DeltaOperation) {
+        self.invalidate();
+        self.delta.push(new_op);
+    }
+
+    /// # `delta_mut()`
+    ///
+    /// Returns mutable access to the wrapped delta. The cache is invalidated,
+    /// since any change may be made through the returned reference.
+    pub fn delta_mut(&mut self) -> &mut Delta {
+        self.invalidate();
+        &mut self.delta
+    }
+
+    /// # `into_inner()`
+    ///
+    /// Returns the wrapped delta.
+    pub fn into_inner(self) -> Delta {
+        self.delta
+    }
+
+    fn invalidate(&mut self) {
+        self.document_length.set(None);
+        self.delta_length.set(None);
+    }
+}
+
+impl std::ops::Deref for CachedDelta {
+    type Target = Delta;
+    fn deref(&self) -> &Self::Target {
+        &self.delta
+    }
+}
+
+impl From<Delta> for CachedDelta {
+    fn from(delta: Delta) -> CachedDelta {
+        CachedDelta::new(delta)
+    }
+}
+
+impl PartialEq for CachedDelta {
+    fn eq(&self, other: &Self) -> bool {
+        self.delta == other.delta
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright 2024 quill-delta-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::attributes::Attributes;
+use crate::delta::Delta;
+use crate::error::Error;
+use crate::operations::{DeltaOperation, OpsVal};
+use crate::optransform::OpTransform;
+
+/// # `DeltaCursor`
+///
+/// Builder for change deltas, driven by editor like actions on a cursor.
+///
+/// The cursor position is an index in to the document as it looks after
+/// all actions so far have been applied. So after `type_text("abc")` the cursor
+/// is placed behind the inserted text, just like in an editor.
+///
+/// The cursor may move back and forth freely. Each action is recorded as a
+/// change delta on its own, and `finish()` composes them in to a single change delta.
+///
+/// ```
+/// use delta::cursor::DeltaCursor;
+/// use delta::delta::Delta;
+/// use delta::optransform::OpTransform;
+///
+/// let mut doc = Delta::default();
+/// doc.insert("Hello World\n");
+///
+/// let mut cursor = DeltaCursor::default();
+/// cursor.move_to(11);
+/// cursor.type_text("!!");
+/// cursor.backspace(1);
+/// let change = cursor.finish().unwrap();
+///
+/// let mut expected = Delta::default();
+/// expected.insert("Hello World!\n");
+/// assert_eq!(doc.compose(&change).unwrap(), expected);
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct DeltaCursor {
+    steps: Vec<Delta>, //recorded actions, each relative to the document after the previous one
+    position: usize,   //cursor index in the document after all recorded actions
+}
+
+impl DeltaCursor {
+    /// # `position()`
+    ///
+    /// Returns the current cursor index.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// # `move_to()`
+    ///
+    /// Moves the cursor to `index`, which may be before or after the current position.
+    pub fn move_to(&mut self, index: usize) -> &mut Self {
+        self.position = index;
+        self
+    }
+
+    /// # `move_by()`
+    ///
+    /// Moves the cursor relative to the current position. A negative `offset`
+    /// moves backwards, stopping at the start of the document.
+    pub fn move_by(&mut self, offset: isize) -> &mut Self {
+        self.position = self.position.saturating_add_signed(offset);
+        self
+    }
+
+    /// # `type_text()`
+    ///
+    /// Inserts the text at the cursor, and moves the cursor behind the inserted text.
+    pub fn type_text(&mut self, text: &str) -> &mut Self {
+        self.type_attr(text, Attributes::default())
+    }
+
+    /// # `type_attr()`
+    ///
+    /// Inserts a value with attributes at the cursor, and moves the cursor behind
+    /// the inserted value.
+    pub fn type_attr<S: Into<OpsVal>>(&mut self, value: S, attributes: Attributes) -> &mut Self {
+        let op = DeltaOperation::insert_attr(value, attributes);
+        let length = op.op_len();
+        if length == 0 {
+            return self;
+        }
+        let mut step = Delta::default();
+        step.retain(self.position);
+        step.push(op);
+        self.position += length;
+        self.steps.push(step);
+        self
+    }
+
+    /// # `backspace()`
+    ///
+    /// Deletes `count` characters in front of the cursor, and moves the cursor back.
+    /// Deletion stops at the start of the document.
+    pub fn backspace(&mut self, count: usize) -> &mut Self {
+        let count = usize::min(count, self.position);
+        self.position -= count;
+        let mut step = Delta::default();
+        step.retain(self.position);
+        step.delete(count);
+        self.steps.push(step);
+        self
+    }
+
+    /// # `delete_forward()`
+    ///
+    /// Deletes `count` characters behind the cursor. The cursor does not move.
+    pub fn delete_forward(&mut self, count: usize) -> &mut Self {
+        let mut step = Delta::default();
+        step.retain(self.position);
+        step.delete(count);
+        self.steps.push(step);
+        self
+    }
+
+    /// # `format()`
+    ///
+    /// Applies the attributes to `length` characters starting at the cursor.
+    /// The cursor does not move.
+    pub fn format(&mut self, attributes: Attributes, length: usize) -> &mut Self {
+        let mut step = Delta::default();
+        step.retain(self.position);
+        step.retain_attr(length, attributes);
+        self.steps.push(step);
+        self
+    }
+
+    /// # `finish()`
+    ///
+    /// Composes all recorded actions in to a single change delta.
+    ///
+    /// # Errors
+    /// When composing the recorded actions fails
+    pub fn finish(self) -> Result<Delta, Error> {
+        let mut steps = self.steps.into_iter();
+        let Some(mut change) = steps.next() else {
+            return Ok(Delta::default());
+        };
+        for step in steps {
+            change = change.compose(&step)?;
+        }
+        Ok(change)
+    }
+}
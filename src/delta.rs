@@ -5,13 +5,21 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use crate::attributes::Attributes;
+use crate::attributes::{compose, Attributes};
 pub use crate::document::Document;
-use crate::operations::{DeltaOperation, OpType, OpsVal};
+use crate::error::Error;
+use crate::iterator::DeltaIterator;
+use crate::operations::{DeltaOperation, EmbedLength, OpType, OpsVal};
+use crate::optransform::OpTransform;
+use crate::types::attr_val::AttrVal;
 use crate::types::ops_kind::OpKind;
 use serde_derive::{Deserialize, Serialize};
+#[cfg(feature = "json")]
+use std::collections::HashMap;
 #[cfg(test)]
 use std::fmt::{Display, Formatter};
+#[cfg(feature = "json")]
+use std::io::BufRead;
 
 // https://github.com/maximkornilov/types-quill-delta/blob/master/index.d.ts
 // https://github.com/quilljs/delta#insert-operation
@@ -41,15 +49,328 @@ use std::fmt::{Display, Formatter};
 ///         DeltaOperation::insert("Hallo World")
 ///     ].into();
 /// ```
-#[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+///
+/// With the feature `compact-on-load` deserialized deltas are compacted
+/// as if they were built with `push()`, see `compact()`.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct Delta {
     //#[serde(flatten)]
+    #[cfg_attr(
+        feature = "compact-on-load",
+        serde(deserialize_with = "deserialize_compacted")
+    )]
     ops: Vec<DeltaOperation>,
+    #[serde(skip)]
+    order: InsertDeleteOrder,
+    #[serde(skip)]
+    line_separator: Option<char>,
+    #[serde(skip)]
+    embed_length: Option<EmbedLength>,
+}
+
+/// # `InsertDeleteOrder`
+///
+/// The order in which `push()` puts an insert and a delete at the same position.
+/// Both orders give the same result when applied, but some interop targets expect
+/// one of them.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum InsertDeleteOrder {
+    /// Insert before delete, as Quill does
+    #[default]
+    InsertFirst,
+    /// Delete before insert
+    DeleteFirst,
 }
 
 impl Delta {
     pub fn new(ops: Vec<DeltaOperation>) -> Self {
-        Delta { ops }
+        Delta {
+            ops,
+            order: InsertDeleteOrder::default(),
+            line_separator: None,
+            embed_length: None,
+        }
+    }
+
+    /// # `replay()`
+    ///
+    /// Replays a change log: composes the changes one by one on to `base`, and
+    /// returns the document after each change, so the result has the same length
+    /// as `changes`.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut base = Delta::default();
+    /// base.insert("Hello");
+    /// let mut change = Delta::default();
+    /// change.retain(5);
+    /// change.insert("!");
+    ///
+    /// let states = Delta::replay(&base, &[change.clone(), change]).unwrap();
+    /// assert_eq!(states.len(), 2);
+    /// assert_eq!(states[1].document_length(), 7);
+    /// ```
+    ///
+    /// # Errors
+    /// The errors of `compose()`
+    pub fn replay(base: &Delta, changes: &[Delta]) -> Result<Vec<Delta>, Error> {
+        let mut states: Vec<Delta> = Vec::with_capacity(changes.len());
+        for change in changes {
+            let doc = states.last().unwrap_or(base).compose(change)?;
+            states.push(doc);
+        }
+        Ok(states)
+    }
+
+    /// # `with_ordering()`
+    ///
+    /// Creates an empty delta, for which `push()` orders adjacent inserts and
+    /// deletes as given. `Delta::default()` uses `InsertDeleteOrder::InsertFirst`.
+    ///
+    /// The order is a property of how the delta is built, it is not serialized and
+    /// it is ignored when comparing deltas.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, InsertDeleteOrder};
+    /// use delta::operations::DeltaOperation;
+    ///
+    /// let mut delta = Delta::with_ordering(InsertDeleteOrder::DeleteFirst);
+    /// delta.insert("A");
+    /// delta.delete(1);
+    /// assert_eq!(delta[0], DeltaOperation::delete(1));
+    /// ```
+    pub fn with_ordering(order: InsertDeleteOrder) -> Self {
+        Delta {
+            ops: Vec::new(),
+            order,
+            line_separator: None,
+            embed_length: None,
+        }
+    }
+
+    /// # `with_line_separator()`
+    ///
+    /// Creates an empty document, that separates its lines by `separator` in stead
+    /// of `'\n'`, as some legacy formats use `'\u{2028}'`. The line based methods of
+    /// `Document`, such as `each_line()`, `to_lines()`, `block_formats()` and
+    /// `set_block_format()`, use the separator when no other character is given.
+    ///
+    /// Like the ordering of `with_ordering()`, the separator is a property of this
+    /// delta only: it is not serialized, it is ignored when comparing deltas, and
+    /// deltas computed from this one, e.g. by `compose()`, use `'\n'` again.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut doc = Delta::with_line_separator('\u{2028}');
+    /// doc.insert("One\u{2028}Two\u{2028}");
+    /// assert_eq!(doc.to_lines().unwrap().len(), 2);
+    /// ```
+    pub fn with_line_separator(separator: char) -> Self {
+        Delta {
+            ops: Vec::new(),
+            order: InsertDeleteOrder::default(),
+            line_separator: Some(separator),
+            embed_length: None,
+        }
+    }
+
+    /// # `line_separator()`
+    ///
+    /// Returns the character separating the lines of this document, `'\n'` unless
+    /// the delta was created by `with_line_separator()`.
+    pub fn line_separator(&self) -> char {
+        self.line_separator.unwrap_or('\n')
+    }
+
+    /// # `with_embed_length()`
+    ///
+    /// Creates an empty document, that measures its embeds with `embed_length` in stead
+    /// of giving them all length 1, see `EmbedLength`. The deltas returned by its methods,
+    /// like `compose()` and `slice()`, measure their embeds the same way. An operation
+    /// on two deltas uses the function of `self`, or else the one of the other delta.
+    ///
+    /// The function is not serialized, and it is ignored when comparing deltas.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    /// use delta::operations::OpsMap;
+    /// use delta::optransform::OpTransform;
+    ///
+    /// let mut doc = Delta::with_embed_length(|embed| {
+    ///     embed
+    ///         .map_val()
+    ///         .ok()
+    ///         .and_then(|m| m.get("cells"))
+    ///         .and_then(|c| c.number_val().ok())
+    ///         .unwrap_or(1)
+    /// });
+    /// let mut table = OpsMap::default();
+    /// table.insert("cells", 3);
+    /// doc.insert(table);
+    /// doc.insert("!");
+    /// assert_eq!(doc.document_length(), 4);
+    ///
+    /// let mut change = Delta::default();
+    /// change.delete(3);
+    /// assert_eq!(doc.compose(&change).unwrap().document_length(), 1);
+    ///
+    /// // a delete may not split the table
+    /// let mut change = Delta::default();
+    /// change.delete(1);
+    /// assert!(doc.compose(&change).is_err());
+    /// ```
+    pub fn with_embed_length<F>(embed_length: F) -> Self
+    where
+        F: Fn(&OpsVal) -> usize + Send + Sync + 'static,
+    {
+        Delta {
+            ops: Vec::new(),
+            order: InsertDeleteOrder::default(),
+            line_separator: None,
+            embed_length: Some(EmbedLength::new(embed_length)),
+        }
+    }
+
+    /// # `embed_length()`
+    ///
+    /// Returns the function measuring the embeds of this delta, `None` when all embeds
+    /// have length 1, see `with_embed_length()`.
+    pub fn embed_length(&self) -> Option<&EmbedLength> {
+        self.embed_length.as_ref()
+    }
+
+    /// # `len_of()`
+    ///
+    /// Returns the length of `op` as measured by this delta, see `with_embed_length()`.
+    pub fn len_of(&self, op: &DeltaOperation) -> usize {
+        op.op_len_with(self.embed_length())
+    }
+
+    /// Private method
+    /// Returns an empty delta measuring its embeds like this one, for results of its methods.
+    pub(crate) fn empty_like(&self) -> Delta {
+        Delta {
+            embed_length: self.embed_length.clone(),
+            ..Delta::default()
+        }
+    }
+
+    /// Private method
+    /// Returns an empty delta for the result of an operation on `self` and `other`,
+    /// measuring its embeds like `self`, or else like `other`.
+    pub(crate) fn empty_like_either(&self, other: &Delta) -> Delta {
+        Delta {
+            embed_length: self.embed_length.clone().or(other.embed_length.clone()),
+            ..Delta::default()
+        }
+    }
+
+    /// # `retain_all()`
+    ///
+    /// Creates a change delta retaining a whole document of length `doc_len`,
+    /// applying the attributes to all of it.
+    ///
+    /// With empty attributes the result is a single plain retain. Such a retain
+    /// changes nothing, and is removed by `chop()` when it ends a delta, so e.g.
+    /// composing it returns the original document.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::{Delta, Document};
+    /// use delta::optransform::OpTransform;
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Hello");
+    ///
+    /// let mut bold = Attributes::default();
+    /// bold.insert("bold", true);
+    /// let change = Delta::retain_all(doc.document_length(), bold.clone());
+    ///
+    /// let mut expected = Delta::default();
+    /// expected.insert_attr("Hello", bold);
+    /// assert_eq!(doc.compose(&change).unwrap(), expected);
+    /// ```
+    pub fn retain_all(doc_len: usize, attributes: Attributes) -> Delta {
+        let mut delta = Delta::default();
+        delta.retain_attr(doc_len, attributes);
+        delta
+    }
+
+    /// # `identity_change()`
+    ///
+    /// Creates the change delta that changes nothing in a document of length `doc_len`:
+    /// a single `retain(doc_len)`, for protocols that expect an explicit change.
+    ///
+    /// Composing it on to the document returns the document. Note that a trailing
+    /// retain without attributes is removed when a delta is chopped, as the results
+    /// of `compose()` and `diff()` are, so this change does not survive those.
+    /// For a `doc_len` of 0 the change is empty.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    /// use delta::operations::DeltaOperation;
+    /// use delta::optransform::OpTransform;
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Hello");
+    ///
+    /// let change = Delta::identity_change(doc.document_length());
+    /// assert_eq!(change[0], DeltaOperation::retain(5));
+    /// assert_eq!(doc.compose(&change).unwrap(), doc);
+    /// ```
+    pub fn identity_change(doc_len: usize) -> Delta {
+        Delta::retain_all(doc_len, Attributes::default())
+    }
+
+    /// # `retain_clear_all()`
+    ///
+    /// Creates a change delta removing all formatting from the range `[start, end)`
+    /// of the document `doc`. It retains the range with a `Null` for every attribute
+    /// key present in it, so the keys need not be known up front.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::Delta;
+    /// use delta::optransform::OpTransform;
+    ///
+    /// let mut bold = Attributes::default();
+    /// bold.insert("bold", true);
+    /// let mut doc = Delta::default();
+    /// doc.insert_attr("Hello", bold);
+    ///
+    /// let change = Delta::retain_clear_all(&doc, 0, 5).unwrap();
+    /// let mut expected = Delta::default();
+    /// expected.insert("Hello");
+    /// assert_eq!(doc.compose(&change).unwrap(), expected);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `Error::NotADocument` if `doc` contains other operations than Insert, and
+    /// `Error::IndexOutOfRange` if the range does not fit in the document
+    pub fn retain_clear_all(doc: &Delta, start: usize, end: usize) -> Result<Delta, Error> {
+        if doc.iter().any(|op| op.op_type() != OpType::Insert) {
+            return Err(Error::NotADocument);
+        }
+        let length = doc.document_length();
+        if start > end || end > length {
+            return Err(Error::IndexOutOfRange { index: end, length });
+        }
+        let mut clear = Attributes::default();
+        for (offset, op) in doc.ops_with_offsets() {
+            if offset < end && offset + doc.len_of(op) > start {
+                for key in op.attributes.keys() {
+                    clear.insert(key.as_str(), AttrVal::Null);
+                }
+            }
+        }
+        let mut delta = Delta::default();
+        delta.retain(start);
+        delta.retain_attr(end - start, clear);
+        delta.chop();
+        Ok(delta)
     }
 
     pub(crate) fn chop(&mut self) -> &mut Delta {
@@ -66,7 +387,7 @@ impl Delta {
         self
     }
 
-    /// # insert()
+    /// # `insert()`
     ///
     /// Insert operation to insert only a value without attributes.
     pub fn insert<S: Into<OpsVal>>(&mut self, value: S) {
@@ -77,7 +398,7 @@ impl Delta {
         self.push(op);
     }
 
-    /// # insert_attr()
+    /// # `insert_attr()`
     ///
     /// Insert operation to insert a value with attributes.
     pub fn insert_attr<S: Into<OpsVal>>(&mut self, value: S, attributes: Attributes) {
@@ -89,7 +410,25 @@ impl Delta {
         self.push(op);
     }
 
-    /// # retain()
+    /// # `try_insert()`
+    ///
+    /// Strict version of `insert()`.
+    ///
+    /// Where `insert()` silently ignores a value of zero length, such as an empty
+    /// string, this method returns an error, to catch bugs producing empty inserts.
+    ///
+    /// # Errors
+    /// `EmptyInsert` when the value has zero length
+    pub fn try_insert<S: Into<OpsVal>>(&mut self, value: S) -> Result<(), Error> {
+        let op = DeltaOperation::insert(value);
+        if op.op_len() == 0 {
+            return Err(Error::EmptyInsert);
+        }
+        self.push(op);
+        Ok(())
+    }
+
+    /// # `retain()`
     ///
     /// Insert operation to retain only a retain length without attributes.
     pub fn retain(&mut self, length: usize) {
@@ -99,7 +438,7 @@ impl Delta {
         self.push(DeltaOperation::retain(length));
     }
 
-    /// # retain_attr()
+    /// # `retain_attr()`
     ///
     /// Insert operation to retain a retain length with attributes.
     pub fn retain_attr(&mut self, length: usize, attributes: Attributes) {
@@ -111,7 +450,7 @@ impl Delta {
         self.push(op);
     }
 
-    /// # delete()
+    /// # `delete()`
     ///
     /// Insert operation to delete a delete length.
     pub fn delete(&mut self, length: usize) {
@@ -121,7 +460,7 @@ impl Delta {
         self.push(DeltaOperation::delete(length));
     }
 
-    /// # push()
+    /// # `push()`
     ///
     /// Private function to add one operation to the end of the operations vector
     ///
@@ -144,7 +483,7 @@ impl Delta {
         // Merge new operations to the existing operations on the stack if possible
         match &new_op.kind {
             OpKind::Insert(_insert) => match last_op.op_type() {
-                OpType::Delete => {
+                OpType::Delete if self.order == InsertDeleteOrder::InsertFirst => {
                     // order of insert and delete may be swapped without giving the same delta result
                     // we always insert before delete
                     // as a result, repeated insert / delete are nicely collected into 1 operation if possible
@@ -155,11 +494,12 @@ impl Delta {
                     };
                     if let Ok(new_s) = new_op.string_val() {
                         if let Ok(last_s) = tmp.string_val() {
-                            if new_op.attributes.is_equal(&tmp.attributes) {
-                                let op = DeltaOperation::insert_attr(
+                            if new_op.attributes.is_equal(&tmp.attributes) && new_op.id == tmp.id {
+                                let mut op = DeltaOperation::insert_attr(
                                     [last_s, new_s].concat(),
                                     new_op.attributes,
                                 );
+                                op.id = new_op.id;
                                 self.ops.push(op);
                                 self.ops.push(last_op);
                                 return;
@@ -174,33 +514,62 @@ impl Delta {
                 OpType::Insert => {
                     if let Ok(last_s) = last_op.string_val() {
                         if let Ok(new_s) = new_op.string_val() {
-                            if last_op.attributes.is_equal(&new_op.attributes) {
-                                let op = DeltaOperation::insert_attr(
+                            if last_op.attributes.is_equal(&new_op.attributes)
+                                && last_op.id == new_op.id
+                            {
+                                let mut op = DeltaOperation::insert_attr(
                                     [last_s, new_s].concat(),
                                     last_op.attributes,
                                 );
+                                op.id = last_op.id;
                                 self.ops.push(op);
                                 return;
                             }
                         }
                     }
                 }
-                OpType::Retain => {}
+                OpType::Retain | OpType::Delete => {}
             },
             OpKind::Retain(retain) => {
-                if last_op.op_type() == OpType::Retain && last_op.attributes == new_op.attributes {
+                if last_op.op_type() == OpType::Retain
+                    && last_op.attributes == new_op.attributes
+                    && last_op.id == new_op.id
+                {
                     let op =
-                        DeltaOperation::retain_attr(last_op.op_len() + retain, new_op.attributes);
+                        DeltaOperation::retain_attr(last_op.op_len() + retain, new_op.attributes)
+                            .with_id_of(&last_op);
                     self.ops.push(op);
                     return;
                 }
             }
             OpKind::Delete(delete) => {
-                if last_op.op_type() == OpType::Delete {
-                    let op = DeltaOperation::delete(last_op.op_len() + delete);
+                if last_op.op_type() == OpType::Delete && last_op.id == new_op.id {
+                    let op = DeltaOperation::delete(last_op.op_len() + delete).with_id_of(&last_op);
                     self.ops.push(op);
                     return;
                 }
+                if last_op.op_type() == OpType::Insert
+                    && self.order == InsertDeleteOrder::DeleteFirst
+                {
+                    // mirror of the insert before delete case: the delete moves in front
+                    // of the whole run of trailing inserts, and is merged with a delete
+                    // in front of that run
+                    self.ops.push(last_op);
+                    let start = self
+                        .ops
+                        .iter()
+                        .rposition(|op| op.op_type() != OpType::Insert)
+                        .map_or(0, |i| i + 1);
+                    let inserts = self.ops.split_off(start);
+                    match self.ops.last_mut() {
+                        Some(prev) if prev.op_type() == OpType::Delete && prev.id == new_op.id => {
+                            prev.kind = OpKind::Delete(prev.op_len() + delete);
+                        }
+                        _ => self.ops.push(new_op),
+                    }
+                    self.ops.extend(inserts);
+                    return;
+                }
             }
         }
 
@@ -208,7 +577,366 @@ impl Delta {
         self.ops.push(new_op);
     }
 
-    /// # append()
+    /// # `push_compose_retain()`
+    ///
+    /// Adds a retain operation that applies to the same range as the retain at the
+    /// end of this delta.
+    ///
+    /// Where `push()` only merges retains with equal attributes, and keeps
+    /// differently formatted retains as separate operations covering consecutive
+    /// ranges, this method composes the attributes of both retains in to one
+    /// operation. The attributes of `new_op` take precedence, and `null` values are
+    /// kept so that they still remove formatting when the delta is applied.
+    ///
+    /// Use this when building a change that applies several attribute sets to one range.
+    /// When `new_op` is not a retain, or the tail is not a retain of the same length,
+    /// this behaves like `push()`.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::Delta;
+    /// use delta::operations::DeltaOperation;
+    ///
+    /// let mut bold = Attributes::default();
+    /// bold.insert("bold", true);
+    /// let mut italic = Attributes::default();
+    /// italic.insert("italic", true);
+    ///
+    /// let mut delta = Delta::default();
+    /// delta.retain_attr(5, bold);
+    /// delta.push_compose_retain(DeltaOperation::retain_attr(5, italic));
+    /// assert_eq!(delta.len(), 1);
+    /// assert_eq!(delta[0].get_attributes().len(), 2);
+    /// ```
+    pub fn push_compose_retain(&mut self, new_op: DeltaOperation) {
+        if let OpKind::Retain(len) = new_op.kind {
+            if let Some(last_op) = self.ops.last_mut() {
+                if last_op.kind == OpKind::Retain(len) {
+                    last_op.attributes = compose(&last_op.attributes, &new_op.attributes, true);
+                    return;
+                }
+            }
+        }
+        self.push(new_op);
+    }
+
+    /// # `compact()`
+    ///
+    /// Rebuilds the delta by pushing all operations again, such that mergeable
+    /// neighbours are merged, and zero length operations are dropped.
+    ///
+    /// A delta created from a vector of operations, or by deserialization, is taken
+    /// as is. Compacting it gives the same operations as building it with `push()`.
+    pub fn compact(&mut self) -> &mut Delta {
+        let ops = std::mem::take(&mut self.ops);
+        for op in ops {
+            if !op.is_empty() {
+                self.push(op);
+            }
+        }
+        self
+    }
+
+    /// # `merge_deletes()`
+    ///
+    /// Drops all zero length operations, and merges the deletes that become adjacent.
+    /// Unlike `compact()`, other operations are left as they are,
+    /// so only the deletes of a change delta are tightened, e.g. before transmission.
+    /// Deletes with different ids are not merged.
+    ///
+    /// ```
+    /// use delta::delta::Delta;
+    /// use delta::operations::DeltaOperation;
+    ///
+    /// let mut delta = Delta::new(vec![
+    ///     DeltaOperation::retain(2),
+    ///     DeltaOperation::delete(1),
+    ///     DeltaOperation::retain(0),
+    ///     DeltaOperation::delete(2),
+    /// ]);
+    /// delta.merge_deletes();
+    /// assert_eq!(
+    ///     delta,
+    ///     Delta::new(vec![DeltaOperation::retain(2), DeltaOperation::delete(3)])
+    /// );
+    /// ```
+    pub fn merge_deletes(&mut self) -> &mut Delta {
+        let mut ops: Vec<DeltaOperation> = Vec::with_capacity(self.ops.len());
+        for op in std::mem::take(&mut self.ops) {
+            if op.is_empty() {
+                continue;
+            }
+            if let (OpKind::Delete(len), Some(prev)) = (&op.kind, ops.last_mut()) {
+                if let OpKind::Delete(prev_len) = prev.kind {
+                    if prev.id == op.id {
+                        prev.kind = OpKind::Delete(prev_len + len);
+                        continue;
+                    }
+                }
+            }
+            ops.push(op);
+        }
+        self.ops = ops;
+        self
+    }
+
+    /// # `clamp_inserts()`
+    ///
+    /// Truncates the inserts of a change delta once `max_inserted` characters have been
+    /// inserted, and drops the inserts after that. Retains and deletes are kept, such that
+    /// the change still applies to the same document, e.g. to rate-limit input.
+    /// An embed counts as one character, and is either kept or dropped as a whole.
+    ///
+    /// ```
+    /// use delta::delta::Delta;
+    ///
+    /// let mut change = Delta::default();
+    /// change.retain(2);
+    /// change.insert("Hello");
+    /// change.delete(1);
+    /// change.clamp_inserts(2);
+    ///
+    /// let mut expected = Delta::default();
+    /// expected.retain(2);
+    /// expected.insert("He");
+    /// expected.delete(1);
+    /// assert_eq!(change, expected);
+    /// ```
+    pub fn clamp_inserts(&mut self, max_inserted: usize) -> &mut Delta {
+        let mut remaining = max_inserted;
+        for mut op in std::mem::take(&mut self.ops) {
+            if op.op_type() == OpType::Insert {
+                if remaining == 0 {
+                    continue;
+                }
+                if let OpKind::Insert(OpsVal::String(text)) = &mut op.kind {
+                    match text.char_indices().nth(remaining) {
+                        Some((i, _)) => {
+                            text.truncate(i);
+                            remaining = 0;
+                        }
+                        None => remaining -= text.chars().count(),
+                    }
+                } else {
+                    remaining -= 1;
+                }
+            }
+            self.push(op);
+        }
+        self
+    }
+
+    /// # `hunks()`
+    ///
+    /// Splits a change delta in to its contiguous change regions, which are separated
+    /// by retains without attributes. Each hunk is a change of its own against the same
+    /// document as this change, starting with a retain up to its region, e.g. for a UI
+    /// to accept or reject changes one by one.
+    ///
+    /// ```
+    /// use delta::delta::Delta;
+    ///
+    /// let mut change = Delta::default();
+    /// change.insert("A");
+    /// change.retain(5);
+    /// change.delete(1);
+    ///
+    /// let hunks = change.hunks();
+    /// assert_eq!(hunks.len(), 2);
+    /// let mut expected = Delta::default();
+    /// expected.retain(5);
+    /// expected.delete(1);
+    /// assert_eq!(hunks[1], expected);
+    /// ```
+    pub fn hunks(&self) -> Vec<Delta> {
+        let mut hunks = Vec::new();
+        let mut hunk: Option<Delta> = None;
+        let mut base_index = 0;
+        for op in self.ops.iter().filter(|op| !op.is_empty()) {
+            if op.op_type() == OpType::Retain && op.attributes.is_empty() {
+                hunks.extend(hunk.take());
+                base_index += op.op_len();
+                continue;
+            }
+            hunk.get_or_insert_with(|| {
+                let mut delta = self.empty_like();
+                delta.retain(base_index);
+                delta
+            })
+            .push(op.clone());
+            if op.op_type() != OpType::Insert {
+                base_index += op.op_len();
+            }
+        }
+        hunks.extend(hunk);
+        hunks
+    }
+
+    /// # `apply_hunks()`
+    ///
+    /// Applies only the hunks of this change with the given indices, see `hunks()`,
+    /// to `base`. Each accepted hunk is transformed against the hunks accepted before
+    /// it, so the positions stay correct when hunks are skipped, e.g. to accept
+    /// changes selectively. Indices may be given in any order, and repeated.
+    ///
+    /// ```
+    /// use delta::delta::Delta;
+    ///
+    /// let mut base = Delta::default();
+    /// base.insert("Hello World");
+    /// let mut change = Delta::default();
+    /// change.insert("Oh, ");
+    /// change.retain(6);
+    /// change.delete(5);
+    /// change.insert("Earth");
+    ///
+    /// let mut expected = Delta::default();
+    /// expected.insert("Hello Earth");
+    /// assert_eq!(change.apply_hunks(&base, &[1]).unwrap(), expected);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `Error::IndexOutOfRange` if an index is not the index of a hunk, and the
+    /// errors of `compose()` and `transform()`
+    pub fn apply_hunks(&self, base: &Delta, hunk_indices: &[usize]) -> Result<Delta, Error> {
+        let hunks = self.hunks();
+        if let Some(&index) = hunk_indices.iter().find(|&&i| i >= hunks.len()) {
+            return Err(Error::IndexOutOfRange {
+                index,
+                length: hunks.len(),
+            });
+        }
+        let mut accepted = Delta::default();
+        for (i, hunk) in hunks.iter().enumerate() {
+            if hunk_indices.contains(&i) {
+                let hunk = accepted.transform(hunk, true)?;
+                accepted = accepted.compose(&hunk)?;
+            }
+        }
+        base.compose(&accepted)
+    }
+
+    /// # `is_normalized()`
+    ///
+    /// Returns true when the delta is minimal: it contains no zero length operations,
+    /// no neighbours that `push()` would merge or reorder given the delta's
+    /// `InsertDeleteOrder`, and no trailing retain
+    /// without attributes. That is, `compact()` and chopping would not change it.
+    ///
+    /// ```
+    /// use delta::delta::Delta;
+    /// use delta::operations::DeltaOperation;
+    ///
+    /// let mut delta = Delta::default();
+    /// delta.insert("Hello");
+    /// delta.retain(5);
+    /// assert!(!delta.is_normalized());
+    ///
+    /// let delta = Delta::new(vec![DeltaOperation::insert("Hel"), DeltaOperation::insert("lo")]);
+    /// assert!(!delta.is_normalized());
+    /// ```
+    pub fn is_normalized(&self) -> bool {
+        if self.ops.iter().any(DeltaOperation::is_empty) {
+            return false;
+        }
+        if let Some(last) = self.ops.last() {
+            if last.op_type() == OpType::Retain && last.attributes.is_empty() {
+                return false;
+            }
+        }
+        !self.ops.windows(2).any(|pair| {
+            let (prev, next) = (&pair[0], &pair[1]);
+            match (prev.op_type(), next.op_type()) {
+                (OpType::Delete, OpType::Delete) => prev.id == next.id,
+                (OpType::Delete, OpType::Insert) => self.order == InsertDeleteOrder::InsertFirst,
+                (OpType::Insert, OpType::Delete) => self.order == InsertDeleteOrder::DeleteFirst,
+                (OpType::Retain, OpType::Retain) => {
+                    prev.attributes == next.attributes && prev.id == next.id
+                }
+                (OpType::Insert, OpType::Insert) => {
+                    prev.is_string()
+                        && next.is_string()
+                        && prev.attributes.is_equal(&next.attributes)
+                        && prev.id == next.id
+                }
+                _ => false,
+            }
+        })
+    }
+
+    /// # `common_prefix()`
+    ///
+    /// Returns the length over which `self` and `other` start with identical operations,
+    /// regardless of how those operations are split. Inserted text is compared char by
+    /// char, so "Hello" and "Help" share a prefix of 3. The shared prefix can be factored
+    /// out before a transform or compose.
+    ///
+    /// ```
+    /// use delta::delta::Delta;
+    ///
+    /// let mut a = Delta::default();
+    /// a.retain(5);
+    /// a.delete(2);
+    /// let mut b = Delta::default();
+    /// b.retain(3);
+    /// b.insert("X");
+    /// assert_eq!(a.common_prefix(&b), 3);
+    /// ```
+    pub fn common_prefix(&self, other: &Delta) -> usize {
+        let embed_length = self.embed_length().or(other.embed_length());
+        let this_iter = DeltaIterator::new(&self.ops).with_embed_length(embed_length);
+        let other_iter = DeltaIterator::new(&other.ops).with_embed_length(embed_length);
+        let mut length = 0;
+        while let (Some(this_op), Some(other_op)) = (this_iter.peek(), other_iter.peek()) {
+            if this_op.op_type() != other_op.op_type() {
+                break;
+            }
+            let len = this_iter.peek_len().min(other_iter.peek_len());
+            let (this_text, other_text) = (this_op.string_val(), other_op.string_val());
+            if this_text.is_ok() || other_text.is_ok() {
+                // Text is compared char by char, so a split never lands inside a char
+                let (Ok(this_text), Ok(other_text)) = (this_text, other_text) else {
+                    break;
+                };
+                if this_op.attributes != other_op.attributes || this_op.id != other_op.id {
+                    break;
+                }
+                let this_text = &this_text[this_iter.position().1..];
+                let other_text = &other_text[other_iter.position().1..];
+                let common: usize = this_text
+                    .chars()
+                    .zip(other_text.chars())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a.len_utf8())
+                    .sum();
+                if common < len {
+                    length += common;
+                    break;
+                }
+                this_iter.next_len(len);
+                other_iter.next_len(len);
+                length += len;
+            } else if this_op.op_type() == OpType::Insert {
+                // Embeds are compared as a whole
+                if this_op != other_op {
+                    break;
+                }
+                length += this_iter.peek_len();
+                this_iter.next();
+                other_iter.next();
+            } else {
+                let this_op = this_iter.next_len(len);
+                if this_op != other_iter.next_len(len) {
+                    break;
+                }
+                length += len;
+            }
+        }
+        length
+    }
+
+    /// # `append()`
     ///
     /// Appends a delta to the current delta document.
     pub fn append(&mut self, mut delta: Delta) {
@@ -217,7 +945,7 @@ impl Delta {
         self.ops.append(&mut delta.ops);
     }
 
-    /// # append_delta_operation()
+    /// # `append_delta_operation()`
     ///
     /// Appends a single delta operation to the current delta document.
     pub(crate) fn append_delta_operation(&mut self, mut other: Vec<DeltaOperation>) -> &mut Delta {
@@ -228,19 +956,345 @@ impl Delta {
         self
     }
 
-    /// # get_ops()
+    /// # `get_ops()`
     ///
     /// Converts a Delta document in to a vector of Delta operations
     pub fn get_ops(self) -> Vec<DeltaOperation> {
         self.ops
     }
 
-    /// # get_ops_ref()
+    /// # `get_ops_ref()`
     ///
     /// Returns a reference to a vector of Delta operations for the Delta document
     pub fn get_ops_ref(&self) -> &Vec<DeltaOperation> {
         &self.ops
     }
+
+    /// # `iter_rev()`
+    ///
+    /// Iterates over the operations of the delta from last to first.
+    /// Use `ReverseDeltaIterator` to walk backward inside the operations.
+    pub fn iter_rev(&self) -> impl Iterator<Item = &DeltaOperation> {
+        self.ops.iter().rev()
+    }
+
+    /// # `ops_with_offsets()`
+    ///
+    /// Iterates the operations paired with their start offset, being the sum of the
+    /// lengths of all preceding operations, see `len_of()`.
+    ///
+    /// ```
+    /// use delta::delta::Delta;
+    ///
+    /// let mut delta = Delta::default();
+    /// delta.insert("Hello");
+    /// delta.retain(3);
+    /// delta.delete(2);
+    ///
+    /// let offsets: Vec<usize> = delta.ops_with_offsets().map(|(offset, _)| offset).collect();
+    /// assert_eq!(offsets, vec![0, 5, 8]);
+    /// ```
+    pub fn ops_with_offsets(&self) -> impl Iterator<Item = (usize, &DeltaOperation)> {
+        self.ops.iter().scan(0, move |offset, op| {
+            let start = *offset;
+            *offset += self.len_of(op);
+            Some((start, op))
+        })
+    }
+
+    /// # `contains_embed()`
+    ///
+    /// Returns true when the delta inserts at least one embed, i.e. a non string insert.
+    pub fn contains_embed(&self) -> bool {
+        self.ops
+            .iter()
+            .any(|op| op.op_type() == OpType::Insert && op.is_object())
+    }
+
+    /// # `is_formatting_only()`
+    ///
+    /// Returns true when the delta only changes formatting: it contains retains, but
+    /// no inserts or deletes, so the content and its layout stay the same.
+    /// A delta without operations changes nothing, and also returns true.
+    pub fn is_formatting_only(&self) -> bool {
+        self.ops.iter().all(|op| op.op_type() == OpType::Retain)
+    }
+
+    /// # `embeds()`
+    ///
+    /// Iterates the embeds inserted by the delta, yielding the offset of each embed
+    /// together with its value and attributes.
+    ///
+    /// ```
+    /// use delta::delta::Delta;
+    /// use delta::operations::OpsMap;
+    ///
+    /// let mut img = OpsMap::default();
+    /// img.insert("image", "octocat.png");
+    ///
+    /// let mut delta = Delta::default();
+    /// delta.insert("Hello");
+    /// delta.insert(img);
+    ///
+    /// let offsets: Vec<usize> = delta.embeds().map(|(offset, _, _)| offset).collect();
+    /// assert_eq!(offsets, vec![5]);
+    /// ```
+    pub fn embeds(&self) -> impl Iterator<Item = (usize, &OpsVal, &Attributes)> {
+        self.ops_with_offsets()
+            .filter(|(_, op)| op.op_type() == OpType::Insert && op.is_object())
+            .map(|(offset, op)| (offset, op.insert_value(), op.get_attributes()))
+    }
+
+    /// # `inserted_content()`
+    ///
+    /// Returns the document made of just the inserts of this change delta, in order
+    /// and with their attributes, ignoring its retains and deletes.
+    /// Useful to preview what a change adds, without knowing its base document.
+    /// Same as `Document::to_document()`.
+    ///
+    /// ```
+    /// use delta::delta::Delta;
+    ///
+    /// let mut change = Delta::default();
+    /// change.retain(5);
+    /// change.insert("Hello");
+    /// change.delete(2);
+    /// change.insert(" World");
+    ///
+    /// let mut expected = Delta::default();
+    /// expected.insert("Hello World");
+    /// assert_eq!(change.inserted_content(), expected);
+    /// ```
+    #[must_use]
+    pub fn inserted_content(&self) -> Delta {
+        self.to_document()
+    }
+
+    /// # `normalize_newlines()`
+    ///
+    /// Rewrites the string inserts to use `\n` as the only new line character,
+    /// replacing `\r\n` and lone `\r`, also when a `\r\n` pair is split over two
+    /// inserts. The line attributes of such a pair are taken from the `\n`.
+    /// Afterwards the delta is compacted, since inserts may have become empty.
+    ///
+    /// Meant for documents: the lengths of retains and deletes are not adjusted.
+    ///
+    /// ```
+    /// use delta::delta::Delta;
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Hello\r\nWorld\r");
+    /// doc.normalize_newlines();
+    ///
+    /// let mut expected = Delta::default();
+    /// expected.insert("Hello\nWorld\n");
+    /// assert_eq!(doc, expected);
+    /// ```
+    pub fn normalize_newlines(&mut self) -> &mut Delta {
+        let ends_with_cr = |op: &DeltaOperation| op.string_val().is_ok_and(|s| s.ends_with('\r'));
+        let starts_with_lf =
+            |op: &DeltaOperation| op.string_val().is_ok_and(|s| s.starts_with('\n'));
+        for i in 0..self.ops.len() {
+            let split_crlf =
+                ends_with_cr(&self.ops[i]) && self.ops.get(i + 1).is_some_and(starts_with_lf);
+            if let OpKind::Insert(OpsVal::String(s)) = &mut self.ops[i].kind {
+                if split_crlf {
+                    s.pop();
+                }
+                if s.contains('\r') {
+                    *s = s.replace("\r\n", "\n").replace('\r', "\n");
+                }
+            }
+        }
+        self.compact()
+    }
+
+    /// # `remap_embeds()`
+    ///
+    /// Replaces the value and attributes of each embed by the result of the closure,
+    /// e.g. to renumber embed ids that collide after merging two documents.
+    /// Text inserts, retains and deletes are left untouched.
+    /// Afterwards the delta is compacted, since remapped operations may be merged
+    /// with their neighbours.
+    pub fn remap_embeds<F>(&mut self, f: F) -> &mut Delta
+    where
+        F: Fn(&OpsVal, &Attributes) -> (OpsVal, Attributes),
+    {
+        for op in &mut self.ops {
+            if op.op_type() == OpType::Insert && op.is_object() {
+                let (value, attributes) = f(op.insert_value(), op.get_attributes());
+                op.set_op_kind(OpKind::Insert(value));
+                op.set_attributes(attributes);
+            }
+        }
+        self.compact()
+    }
+}
+
+#[cfg(feature = "compact-on-load")]
+fn deserialize_compacted<'de, D>(deserializer: D) -> Result<Vec<DeltaOperation>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let ops = <Vec<DeltaOperation> as serde::Deserialize>::deserialize(deserializer)?;
+    let mut delta = Delta::new(ops);
+    delta.compact();
+    Ok(delta.ops)
+}
+
+#[cfg(feature = "json")]
+impl Delta {
+    /// # `from_jsonl()`
+    ///
+    /// Reads JSON Lines, as used by operation logs, and yields a delta per line.
+    /// A line holds either a delta `{"ops":[...]}`, or a single operation, which is
+    /// yielded as a delta of that operation. Empty lines are skipped.
+    ///
+    /// Lines are read as the iterator advances, so a log can be replayed without
+    /// loading it completely.
+    /// ```
+    /// use delta::delta::Delta;
+    /// use delta::optransform::OpTransform;
+    ///
+    /// let log = r#"{"ops":[{"insert":"Hello"}]}
+    /// {"retain":5}
+    /// {"ops":[{"retain":5},{"insert":" World"}]}"#;
+    ///
+    /// let mut doc = Delta::default();
+    /// for change in Delta::from_jsonl(log.as_bytes()) {
+    ///     doc = doc.compose(&change.unwrap()).unwrap();
+    /// }
+    /// assert_eq!(doc.len(), 1);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Yields `Error::Io` when reading fails, and `Error::JsonLine` with the
+    /// line number when a line is neither a delta nor an operation.
+    pub fn from_jsonl<R: BufRead>(reader: R) -> impl Iterator<Item = Result<Delta, Error>> {
+        reader
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| match line {
+                Err(err) => Some(Err(Error::Io(err))),
+                Ok(line) if line.trim().is_empty() => None,
+                Ok(line) => Some(
+                    serde_json::from_str::<Delta>(&line)
+                        .or_else(|err| {
+                            serde_json::from_str::<DeltaOperation>(&line)
+                                .map(|op| Delta::new(vec![op]))
+                                .map_err(|_| err)
+                        })
+                        .map_err(|source| Error::JsonLine {
+                            line: index + 1,
+                            source,
+                        }),
+                ),
+            })
+    }
+
+    /// # `from_str_strict()`
+    ///
+    /// Reads a delta from JSON, like `serde_json::from_str()`, for schemas that forbid
+    /// nesting: an attribute value or embed may be a map, but a map inside a map is
+    /// rejected.
+    /// ```
+    /// use delta::delta::Delta;
+    ///
+    /// let flat = r#"{"ops":[{"insert":{"image":"octocat.png"},"attributes":{"font":{"size":"15px"}}}]}"#;
+    /// assert!(Delta::from_str_strict(flat).is_ok());
+    ///
+    /// let nested = r#"{"ops":[{"insert":"A","attributes":{"font":{"size":{"px":15}}}}]}"#;
+    /// assert!(serde_json::from_str::<Delta>(nested).is_ok());
+    /// assert!(Delta::from_str_strict(nested).is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `Error::SerdeNestedMap` for a map inside a map, and `Error::Json` when
+    /// the input is no valid delta.
+    pub fn from_str_strict(json: &str) -> Result<Delta, Error> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let Some(ops) = value.get("ops").and_then(serde_json::Value::as_array) else {
+            return Ok(serde_json::from_value(value)?);
+        };
+        for op in ops.iter().filter_map(serde_json::Value::as_object) {
+            if let Some(insert) = op.get("insert") {
+                AttrVal::from_json_value(insert.clone(), 1)?;
+            }
+            let attributes = op.get("attributes").and_then(serde_json::Value::as_object);
+            for attr in attributes.into_iter().flat_map(serde_json::Map::values) {
+                AttrVal::from_json_value(attr.clone(), 1)?;
+            }
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// # `from_slice()`
+    ///
+    /// Reads a delta from JSON bytes, like `serde_json::from_slice()`, e.g. as received
+    /// from the network, without converting them to a `String` first.
+    /// ```
+    /// use delta::delta::Delta;
+    ///
+    /// let bytes = br#"{"ops":[{"insert":"Hello"}]}"#;
+    /// let delta = Delta::from_slice(bytes).unwrap();
+    /// assert_eq!(delta.len(), 1);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `Error::Json` when the input is no valid delta.
+    pub fn from_slice(bytes: &[u8]) -> Result<Delta, Error> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// # `DeltaEnvelope`
+///
+/// A delta stored together with metadata, like `{"ops":[...],"version":5}`.
+/// Deserializing a `Delta` drops fields other than `ops`, the envelope keeps them
+/// in `meta`, and writes them back next to the operations when serialized.
+/// ```
+/// use delta::delta::DeltaEnvelope;
+///
+/// let json = r#"{"ops":[{"insert":"Hello"}],"version":5}"#;
+/// let envelope: DeltaEnvelope = serde_json::from_str(json).unwrap();
+/// assert_eq!(envelope.meta["version"], 5);
+///
+/// let delta = envelope.into_delta();
+/// assert_eq!(delta.len(), 1);
+/// ```
+#[cfg(feature = "json")]
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeltaEnvelope {
+    #[cfg_attr(
+        feature = "compact-on-load",
+        serde(deserialize_with = "deserialize_compacted")
+    )]
+    ops: Vec<DeltaOperation>,
+    #[serde(flatten)]
+    pub meta: HashMap<String, serde_json::Value>,
+}
+
+#[cfg(feature = "json")]
+impl DeltaEnvelope {
+    /// # `from_delta_with_meta()`
+    ///
+    /// Wraps a delta and its metadata in an envelope.
+    pub fn from_delta_with_meta(delta: Delta, meta: HashMap<String, serde_json::Value>) -> Self {
+        DeltaEnvelope {
+            ops: delta.ops,
+            meta,
+        }
+    }
+
+    /// # `into_delta()`
+    ///
+    /// Returns the delta of the envelope, dropping the metadata.
+    pub fn into_delta(self) -> Delta {
+        Delta::new(self.ops)
+    }
 }
 
 impl std::ops::Deref for Delta {
@@ -258,7 +1312,14 @@ impl std::ops::DerefMut for Delta {
 
 impl From<Vec<DeltaOperation>> for Delta {
     fn from(ops: Vec<DeltaOperation>) -> Delta {
-        Delta { ops }
+        Delta::new(ops)
+    }
+}
+
+/// Deltas are equal when their operations are, regardless of their `InsertDeleteOrder`
+impl PartialEq for Delta {
+    fn eq(&self, other: &Self) -> bool {
+        self.ops == other.ops
     }
 }
 
@@ -5,20 +5,81 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use crate::attributes::{diff, invert, Attributes};
+use crate::attributes::{compose, diff, diff_no_clears, invert, Attributes};
 use crate::delta::Delta;
 use crate::error::Error;
 use crate::iterator::DeltaIterator;
-use crate::operations::{DeltaOperation, OpType, OpsVal};
+use crate::operations::{merge_embed, DeltaOperation, OpType, OpsVal, EMBED_MERGE_KEY};
+use crate::optransform::OpTransform;
+use crate::types::attr_val::AttrVal;
 use crate::types::ops_kind::OpKind;
 use crate::utils::DeltaTransformations;
 use anyhow::Result;
 use diffs::{myers, Diff, Replace};
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+/// # `DiffGranularity`
+///
+/// Unit of text that `diff_granularity()` compares.
+///  - `Char`: single characters, identical to `diff()`
+///  - `Word`: runs of alphanumeric characters; whitespace and punctuation are compared per character
+///  - `Token`: tokens produced by the given tokenizer, e.g. sentences
+///
+/// The tokenizer returns slices of its input in order. Text that is skipped by
+/// the tokenizer is compared as a token of its own, so no content gets lost.
+pub enum DiffGranularity<'a> {
+    Char,
+    Word,
+    Token(&'a dyn Fn(&str) -> Vec<&str>),
+}
+
+/// # `TriState`
+///
+/// Result of `range_has_attribute()`:
+///  - `All`: all text in the range has the attribute value
+///  - `None`: no text in the range has the attribute value, also for an empty range
+///  - `Mixed`: only part of the text has the attribute value
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TriState {
+    All,
+    None,
+    Mixed,
+}
+
+/// # `EachLineOptions`
+///
+/// Options of `each_line_opts()`, for the embeds in a line:
+///  - `embed_placeholder`: text inserted in the line in place of each embed, with the
+///    attributes of the embed; `None` keeps the embed itself
+///  - `count_embeds`: when `false` embeds are left out of the line content altogether
+///
+/// and for the end of the document:
+///  - `include_empty_final_line`: when `true` the empty line after the last line break,
+///    or the single line of an empty document, is passed to the predicate as well
+///
+/// The default keeps embeds as they are and skips the empty final line, like `each_line()`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EachLineOptions {
+    pub embed_placeholder: Option<String>,
+    pub count_embeds: bool,
+    pub include_empty_final_line: bool,
+}
+
+impl Default for EachLineOptions {
+    fn default() -> Self {
+        EachLineOptions {
+            embed_placeholder: None,
+            count_embeds: true,
+            include_empty_final_line: false,
+        }
+    }
+}
 
 /// These methods called on or with non-document Deltas will result in undefined behavior.
 pub trait Document {
 
-    /// # concat()
+    /// # `concat()`
     ///
     /// Returns a Delta representing the concatenation of
     /// this and another document Delta's operations.
@@ -46,7 +107,42 @@ pub trait Document {
     /// ```
     fn concat(&mut self, other: Delta) -> &mut Delta;
 
-    /// # diff()
+    /// # `try_concat()`
+    ///
+    /// Concatenates like `concat()`, but fails when the last operation of this
+    /// document and the first operation of `other` have different attributes.
+    ///
+    /// `concat()` silently merges equally formatted inserts at the boundary, and keeps
+    /// differently formatted inserts apart. Applications that expect the formatting to
+    /// continue across the boundary may use this method to detect the difference.
+    /// When an error is returned, this document is left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::ConcatBoundaryConflict`: if the attributes at the boundary differ
+    fn try_concat(&mut self, other: Delta) -> Result<&mut Delta, Error>;
+
+    /// # `append_block()`
+    ///
+    /// Concatenates like `concat()`, but first ends this document with its line
+    /// separator when it does not, so the last line of this document and the first
+    /// line of `other` stay separate blocks. An empty document gets no new line.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Hello");
+    /// let mut other = Delta::default();
+    /// other.insert("World\n");
+    ///
+    /// let mut expected = Delta::default();
+    /// expected.insert("Hello\nWorld\n");
+    /// assert_eq!(doc.append_block(other), &expected);
+    /// ```
+    fn append_block(&mut self, other: Delta) -> &mut Delta;
+
+    /// # `diff()`
     ///
     /// Returns a Delta representing the difference between two documents.
     /// Optionally, accepts a suggested index where change took place, often
@@ -71,10 +167,377 @@ pub trait Document {
     /// `ErrorDelta::NotADocument`: if `other` is not a document (i.e. contains other operations than Insert)
     fn diff(&self, other: &Delta, _cursor: usize) -> Result<Delta, Error>;
 
-    /// # each_line()
+    /// # `diff_attribute_aware()`
+    ///
+    /// Returns the difference between two documents like `diff()`, but content is only
+    /// equal when both the text and the attributes are. Text that is formatted differently
+    /// is deleted and inserted again, so all retains are without attributes.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::Delta;
+    /// use delta::document::Document;
+    ///
+    /// let mut bold = Attributes::default();
+    /// bold.insert("bold", true);
+    ///
+    /// let mut a = Delta::default();
+    /// a.insert("Hello");
+    /// let mut b = Delta::default();
+    /// b.insert_attr("Hello", bold.clone());
+    ///
+    /// let mut expected = Delta::default();
+    /// expected.insert_attr("Hello", bold);
+    /// expected.delete(5);
+    /// assert_eq!(a.diff_attribute_aware(&b).unwrap(), expected);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` or `other` is not a document (i.e. contains other operations than Insert)
+    fn diff_attribute_aware(&self, other: &Delta) -> Result<Delta, Error>;
+
+    /// # `diff_text()`
+    ///
+    /// Returns a change Delta that turns this document in to the plain text `new_text`,
+    /// e.g. after editing the document in a plain text buffer.
+    ///
+    /// Unchanged text is retained, keeping its formatting, and new text is inserted
+    /// without attributes. Embeds can not be represented in plain text, and are deleted.
+    ///
+    /// `cursor` is the position of the cursor in `new_text` after the edit, in bytes like
+    /// all document lengths. When an edit in repeated text can be made at several places,
+    /// e.g. typing an "a" in "aa", it is made at the cursor if that is one of them.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut bold = Attributes::default();
+    /// bold.insert("bold", true);
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert_attr("Hello", bold);
+    ///
+    /// let change = doc.diff_text("Hello!", 0).unwrap();
+    /// // result = { ops: [{ retain: 5 }, { insert: '!' }] }
+    /// assert_eq!(change.len(), 2);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` is not a document (i.e. contains other operations than Insert)
+    fn diff_text(&self, new_text: &str, cursor: usize) -> Result<Delta, Error>;
+
+    /// # `diff_min_equal()`
+    ///
+    /// Returns a Delta representing the difference between two documents,
+    /// like `diff()`, but equal runs shorter than `min_equal` characters that are
+    /// bordered by changes on both sides are folded in to the surrounding change.
+    ///
+    /// This avoids change previews that are fragmented by tiny retains.
+    ///
+    /// ```
+    /// extern crate delta;
+    /// use delta::delta::Delta;
+    /// use delta::document::{Document};
+    ///
+    /// let  mut a = Delta::default();
+    ///  a.insert("aXYb");
+    /// let  mut b = Delta::default();
+    ///  b.insert("cXYd");
+    ///
+    /// let diff = a.diff_min_equal(&b, 3).unwrap();
+    /// // result = { ops: [{ insert: 'cXYd' }, { delete: 4 }] }
+    /// assert_eq!(diff.len(), 2);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `other` is not a document (i.e. contains other operations than Insert)
+    fn diff_min_equal(&self, other: &Delta, min_equal: usize) -> Result<Delta, Error>;
+
+    /// # `diff_explicit_embeds()`
+    ///
+    /// Returns a Delta representing the difference between two documents, like
+    /// `diff()`, but every embed that is equal in both documents gets a retain of
+    /// its own, also at the end of the change. `diff()` merges these retains with
+    /// the surrounding retains, or drops them at the end.
+    ///
+    /// Useful for rendering a structural diff, where retained embeds are marked.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    /// use delta::operations::OpsMap;
+    ///
+    /// let mut img = OpsMap::default();
+    /// img.insert("image", "octocat.png");
+    ///
+    /// let mut a = Delta::default();
+    /// a.insert("Hello");
+    /// a.insert(img.clone());
+    ///
+    /// let mut b = Delta::default();
+    /// b.insert("Hello!");
+    /// b.insert(img);
+    ///
+    /// let diff = a.diff_explicit_embeds(&b).unwrap();
+    /// // result = { ops: [{ retain: 5 }, { insert: '!' }, { retain: 1 }] }
+    /// assert_eq!(diff.len(), 3);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `other` is not a document (i.e. contains other operations than Insert)
+    fn diff_explicit_embeds(&self, other: &Delta) -> Result<Delta, Error>;
+
+    /// # `diff_no_clears()`
+    ///
+    /// Returns a Delta representing the difference between two documents, like
+    /// `diff()`, but attributes that are only present in `self` are not cleared.
+    /// Removing formatting is treated as no change, added and changed formatting is kept.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut bold = Attributes::default();
+    /// bold.insert("bold", true);
+    ///
+    /// let mut a = Delta::default();
+    /// a.insert_attr("Hello", bold);
+    /// let mut b = Delta::default();
+    /// b.insert("Hello");
+    ///
+    /// // diff() gives { ops: [{ retain: 5, attributes: { bold: null } }] }
+    /// assert_eq!(a.diff(&b, 0).unwrap().len(), 1);
+    /// assert!(a.diff_no_clears(&b).unwrap().is_empty());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `other` is not a document (i.e. contains other operations than Insert)
+    fn diff_no_clears(&self, other: &Delta) -> Result<Delta, Error>;
+
+    /// # `diff_capped()`
+    ///
+    /// Returns a Delta representing the difference between two documents, like
+    /// `diff()`, as long as it has at most `max_ops` operations. A longer diff is
+    /// replaced by a coarse change that inserts all of `other` and deletes all of
+    /// `self`: two operations for unformatted text, which keeps rendering cheap.
+    ///
+    /// The coarse change keeps the formatting and embeds of `other`, so it has one
+    /// operation more than `other`. When `other` has more than `max_ops - 1` operations
+    /// the result can therefore exceed `max_ops`, and the shorter of the diff and the
+    /// coarse change is returned.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut a = Delta::default();
+    /// a.insert("a-b-c");
+    /// let mut b = Delta::default();
+    /// b.insert("a+b+c");
+    ///
+    /// assert_eq!(a.diff_capped(&b, 10).unwrap(), a.diff(&b, 0).unwrap());
+    ///
+    /// let mut coarse = Delta::default();
+    /// coarse.insert("a+b+c");
+    /// coarse.delete(5);
+    /// assert_eq!(a.diff_capped(&b, 2).unwrap(), coarse);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `other` is not a document (i.e. contains other operations than Insert)
+    fn diff_capped(&self, other: &Delta, max_ops: usize) -> Result<Delta, Error>;
+
+    /// # `diff_normalized()`
+    ///
+    /// Returns a Delta representing the difference between two documents, like
+    /// `diff()`, after bringing the text of both documents in Unicode normalization
+    /// form C (NFC). Visually identical text, such as a precomposed `é` and an `e`
+    /// followed by a combining accent, then gives no difference.
+    ///
+    /// Note that the offsets of the result are offsets in the normalized text of
+    /// `self`, which may differ from the offsets in `self` itself. The result
+    /// applies to `self.normalized()`.
+    ///
+    /// Requires the feature `unicode`.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut nfc = Delta::default();
+    /// nfc.insert("caf\u{e9}");
+    /// let mut nfd = Delta::default();
+    /// nfd.insert("cafe\u{301}");
+    ///
+    /// assert!(!nfc.diff(&nfd, 0).unwrap().is_empty());
+    /// assert!(nfc.diff_normalized(&nfd, 0).unwrap().is_empty());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `other` is not a document (i.e. contains other operations than Insert)
+    #[cfg(feature = "unicode")]
+    fn diff_normalized(&self, other: &Delta, cursor: usize) -> Result<Delta, Error>;
+
+    /// # `normalized()`
+    ///
+    /// Returns the document with the text of its string inserts in Unicode
+    /// normalization form C (NFC), as used by `diff_normalized()`.
+    ///
+    /// Requires the feature `unicode`.
+    #[cfg(feature = "unicode")]
+    fn normalized(&self) -> Delta;
+
+    /// # `morph_into()`
+    ///
+    /// Returns the change delta that transforms this document in to `target`.
+    /// The same as `diff()`, named for intent.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    /// use delta::optransform::OpTransform;
+    ///
+    /// let mut a = Delta::default();
+    /// a.insert("Hello");
+    /// let mut b = Delta::default();
+    /// b.insert("Hello World");
+    ///
+    /// let change = a.morph_into(&b, 0).unwrap();
+    /// assert_eq!(a.compose(&change).unwrap(), b);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `other` is not a document (i.e. contains other operations than Insert)
+    fn morph_into(&self, target: &Delta, cursor: usize) -> Result<Delta, Error>;
+
+    /// # `morph()`
+    ///
+    /// Changes this document in to `target`, by composing it with the change
+    /// from `morph_into()`.
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` or `target` is not a document (i.e. contains other operations than Insert)
+    fn morph(&mut self, target: &Delta) -> Result<&mut Delta, Error>;
+
+    /// # `edit_distance()`
+    ///
+    /// Returns the number of inserted plus deleted characters of the shortest edit
+    /// script between two documents, as found by the diff. Embeds count as characters,
+    /// attributes are ignored.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut a = Delta::default();
+    /// a.insert("Hello");
+    /// let mut b = Delta::default();
+    /// b.insert("Hallo");
+    ///
+    /// // delete 'e', insert 'a'
+    /// assert_eq!(a.edit_distance(&b).unwrap(), 2);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` or `other` is not a document (i.e. contains other operations than Insert)
+    fn edit_distance(&self, other: &Delta) -> Result<usize, Error>;
+
+    /// # `bidirectional_diff()`
+    ///
+    /// Returns `(forward, backward)`: the change from this document to `other`, as
+    /// `diff()` gives it, and the change back. The diff runs once, the backward change
+    /// is the inverse of the forward change, e.g. for storing undo history.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    /// use delta::optransform::OpTransform;
+    ///
+    /// let mut a = Delta::default();
+    /// a.insert("Hello");
+    /// let mut b = Delta::default();
+    /// b.insert("Hallo!");
+    ///
+    /// let (forward, backward) = a.bidirectional_diff(&b).unwrap();
+    /// assert_eq!(a.compose(&forward).unwrap(), b);
+    /// assert_eq!(b.compose(&backward).unwrap(), a);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` or `other` is not a document (i.e. contains other operations than Insert)
+    fn bidirectional_diff(&self, other: &Delta) -> Result<(Delta, Delta), Error>;
+
+    /// # `patch_string()`
+    ///
+    /// Renders the difference between two documents as text, like a unified diff,
+    /// e.g. for logging. Lines are compared including their formatting, changed lines
+    /// are marked with `-` (this document) and `+` (`other`), and surrounded by up to
+    /// 3 unchanged context lines. Each hunk starts with a `@@ -line,count +line,count @@`
+    /// header, line numbers start at 1. Embeds are rendered as `\u{fffc}`.
+    ///
+    /// A formatting change shows as the same text removed and added again.
+    /// Equal documents give an empty string.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut a = Delta::default();
+    /// a.insert("Hello\nWorld\n");
+    /// let mut b = Delta::default();
+    /// b.insert("Hello\nBig world\n");
+    ///
+    /// let patch = a.patch_string(&b).unwrap();
+    /// assert_eq!(patch, "@@ -1,2 +1,2 @@\n Hello\n-World\n+Big world\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` or `other` is not a document (i.e. contains other operations than Insert)
+    fn patch_string(&self, other: &Delta) -> Result<String, Error>;
+
+    /// # `diff_granularity()`
+    ///
+    /// Returns a Delta representing the difference between two documents,
+    /// comparing words or custom tokens in stead of characters.
+    /// Changed tokens are replaced as a whole, which gives more readable
+    /// results for prose than a character diff.
+    ///
+    /// ```
+    /// extern crate delta;
+    /// use delta::delta::Delta;
+    /// use delta::document::{DiffGranularity, Document};
+    ///
+    /// let  mut a = Delta::default();
+    ///  a.insert("The cat sat. The dog ran. ");
+    /// let  mut b = Delta::default();
+    ///  b.insert("The cat sat. A dog ran. ");
+    ///
+    /// let sentences: &dyn Fn(&str) -> Vec<&str> = &|s| s.split_inclusive(". ").collect();
+    /// let diff = a.diff_granularity(&b, &DiffGranularity::Token(sentences)).unwrap();
+    /// // result = { ops: [{ retain: 13 }, { insert: 'A dog ran. ' }, { delete: 13 }] }
+    /// assert_eq!(diff.len(), 3);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `other` is not a document (i.e. contains other operations than Insert)
+    fn diff_granularity(
+        &self,
+        other: &Delta,
+        granularity: &DiffGranularity,
+    ) -> Result<Delta, Error>;
+
+    /// # `each_line()`
     ///
     /// run for each line in the text a method. A line is defined by
-    /// line brake character: '\n'
+    /// line brake character: `new_line_char`, or else the line separator of the
+    /// document, which is '\n' unless set by `Delta::with_line_separator()`
     ///
     /// Lines are processed until the predicate returns false as output.
     ///
@@ -83,14 +546,63 @@ pub trait Document {
     ///
     /// With closure Fn(&Delta, Attributes, usize) -> bool
     ///  - Delta: document to apply
-    ///  - Attribute: at the end of line character (might be a separate DeltaOperation)
+    ///  - Attribute: at the end of line character (might be a separate `DeltaOperation`)
     ///  - integer with the line number
+    ///
+    /// Text after the last line break is passed as a line without attributes.
+    /// When the document is empty or ends on a line break, there is no such text,
+    /// and the predicate is not called for it: an empty document has no lines.
+    /// Use `each_line_opts()` with `include_empty_final_line` to get the empty line.
     /// # Errors
     fn each_line<F>(&self, predicate: F, new_line_char: Option<char>) -> Result<(), Error>
     where
         F: Fn(&Delta, &Attributes, usize) -> bool;
 
-    /// # invert()
+    /// # `each_line_opts()`
+    ///
+    /// Runs the predicate for each line like `each_line()`, with `options` determining
+    /// how the embeds in a line are passed to the predicate, see `EachLineOptions`.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::{Delta, Document};
+    /// use delta::document::EachLineOptions;
+    /// use delta::operations::OpsMap;
+    ///
+    /// let mut img = OpsMap::default();
+    /// img.insert("image", "octocat.png");
+    /// let mut doc = Delta::default();
+    /// doc.insert("See ");
+    /// doc.insert(img);
+    /// doc.insert("\n");
+    ///
+    /// let options = EachLineOptions {
+    ///     embed_placeholder: Some("[image]".to_string()),
+    ///     ..EachLineOptions::default()
+    /// };
+    /// doc.each_line_opts(
+    ///     |line: &Delta, _: &Attributes, _| {
+    ///         let mut expected = Delta::default();
+    ///         expected.insert("See [image]");
+    ///         assert_eq!(*line, expected);
+    ///         true
+    ///     },
+    ///     None,
+    ///     &options,
+    /// )
+    /// .unwrap();
+    /// ```
+    /// # Errors
+    fn each_line_opts<F>(
+        &self,
+        predicate: F,
+        new_line_char: Option<char>,
+        options: &EachLineOptions,
+    ) -> Result<(), Error>
+    where
+        F: Fn(&Delta, &Attributes, usize) -> bool;
+
+    /// # `invert()`
     ///
     /// Returns an inverted delta that has the opposite effect of against a base document delta.
     /// That is:<br>
@@ -125,39 +637,763 @@ pub trait Document {
     ///  base.compose(delta).compose(inverted) === base
     fn invert(&self, base: &Delta) -> Delta;
 
-    /// # document_length()
-    ///
-    /// Length of all insert values in this delta document.
-    fn document_length(&self) -> usize;
-}
+    /// # `invert_range()`
+    ///
+    /// Inverts only the part of this change that affects the positions `[start, end)`
+    /// of the base document, e.g. to undo the formatting of one paragraph while keeping
+    /// the rest of a change. Like the result of `invert()`, the result applies to
+    /// `base.compose(change)`; it reverts the deletes, formatting and inserts at
+    /// positions in the range, and keeps everything outside of it.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::{Delta, Document};
+    /// use delta::optransform::OpTransform;
+    ///
+    /// let mut bold = Attributes::default();
+    /// bold.insert("bold", true);
+    ///
+    /// let mut base = Delta::default();
+    /// base.insert("Hello World");
+    /// let mut change = Delta::default();
+    /// change.retain_attr(11, bold.clone());
+    ///
+    /// let undo = change.invert_range(&base, 0, 6).unwrap();
+    /// let mut expected = Delta::default();
+    /// expected.insert("Hello ");
+    /// expected.insert_attr("World", bold);
+    /// assert_eq!(base.compose(&change).unwrap().compose(&undo).unwrap(), expected);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `base` is not a document (i.e. contains other operations than Insert)<br>
+    /// `ErrorDelta::IndexOutOfRange`: if the range does not fit in the base document
+    fn invert_range(&self, base: &Delta, start: usize, end: usize) -> Result<Delta, Error>;
+
+    /// # `clear_formatting_change()`
+    ///
+    /// Returns the change that removes all attributes from this document, i.e.
+    /// retains with the attributes of the document set to `Null`.
+    /// Composing the change on to the document strips its formatting, the inverse
+    /// of the change restores it.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::{Delta, Document};
+    /// use delta::optransform::OpTransform;
+    ///
+    /// let mut bold = Attributes::default();
+    /// bold.insert("bold", true);
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Hello ");
+    /// doc.insert_attr("World", bold);
+    ///
+    /// let change = doc.clear_formatting_change().unwrap();
+    /// let mut expected = Delta::default();
+    /// expected.insert("Hello World");
+    /// assert_eq!(doc.compose(&change).unwrap(), expected);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` is not a document (i.e. contains other operations than Insert)
+    fn clear_formatting_change(&self) -> Result<Delta, Error>;
+
+    /// # `document_length()`
+    ///
+    /// Length of all insert values in this delta document.
+    fn document_length(&self) -> usize;
+
+    /// # `to_document()`
+    ///
+    /// Returns a document delta holding only the inserts of this delta, retains and
+    /// deletes are dropped. Inserts that become neighbours are merged where possible.
+    ///
+    /// Salvages a document view from a malformed delta, or from a change delta.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut change = Delta::default();
+    /// change.retain(3);
+    /// change.insert("Hello");
+    /// change.delete(2);
+    /// change.insert(" World");
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Hello World");
+    /// assert_eq!(change.to_document(), doc);
+    /// ```
+    fn to_document(&self) -> Delta;
+
+    /// # `chunk()`
+    ///
+    /// Splits the document in to sequential sub-documents with a length of at most
+    /// `chunk_len` each. String inserts are split between characters when needed,
+    /// embeds count with the embed length of the delta and are never split.
+    /// A character or embed longer than `chunk_len` gets a chunk of its own.
+    ///
+    /// Concatenating the chunks reproduces the original document.
+    /// A `chunk_len` of 0 returns the whole document as a single chunk.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Hello World");
+    ///
+    /// let chunks = doc.chunk(5);
+    /// assert_eq!(chunks.len(), 3);
+    /// ```
+    fn chunk(&self, chunk_len: usize) -> Vec<Delta>;
+
+    /// # `split_at()`
+    ///
+    /// Splits the document in to the part before `index`, and the part from `index`
+    /// on. A string insert at the split point is split, an embed at the split point
+    /// goes to the right side.
+    ///
+    /// Concatenating both parts reproduces the original document.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Hello World");
+    ///
+    /// let (left, right) = doc.split_at(5).unwrap();
+    /// assert_eq!(left.document_length(), 5);
+    /// assert_eq!(right.document_length(), 6);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` is not a document (i.e. contains other operations than Insert)<br>
+    /// `ErrorDelta::IndexOutOfRange`: if `index` is larger than the document length
+    fn split_at(&self, index: usize) -> Result<(Delta, Delta), Error>;
+
+    /// # `range_has_attribute()`
+    ///
+    /// Tells whether the text in the range from `start` up to `end` has the attribute
+    /// `key` with `value`, e.g. to decide whether a toolbar button shows as active.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::{Delta, Document};
+    /// use delta::document::TriState;
+    /// use delta::types::attr_val::AttrVal;
+    ///
+    /// let mut bold = Attributes::default();
+    /// bold.insert("bold", true);
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Hello ");
+    /// doc.insert_attr("World", bold);
+    ///
+    /// let state = doc.range_has_attribute(4, 8, "bold", &AttrVal::Bool(true)).unwrap();
+    /// assert_eq!(state, TriState::Mixed);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` is not a document (i.e. contains other operations than Insert)<br>
+    /// `ErrorDelta::IndexOutOfRange`: if `end` is larger than the document length, or smaller than `start`
+    fn range_has_attribute(
+        &self,
+        start: usize,
+        end: usize,
+        key: &str,
+        value: &AttrVal,
+    ) -> Result<TriState, Error>;
+
+    /// # `to_search_text()`
+    ///
+    /// Renders the document as text, for example for search indexing.
+    /// String inserts are copied as is, embeds are rendered by the `embed_render`
+    /// closure, which gets the embed value and its attributes.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::{Delta, Document};
+    /// use delta::operations::OpsMap;
+    ///
+    /// let mut img = OpsMap::default();
+    /// img.insert("image", "octocat.png");
+    /// let mut alt = Attributes::default();
+    /// alt.insert("alt", "Octocat");
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("See ");
+    /// doc.insert_attr(img, alt);
+    ///
+    /// let text = doc
+    ///     .to_search_text(&|_, attr| {
+    ///         attr.get("alt").and_then(|a| a.str_val().ok()).unwrap_or("").to_string()
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(text, "See Octocat");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` is not a document (i.e. contains other operations than Insert)
+    fn to_search_text(
+        &self,
+        embed_render: &dyn Fn(&OpsVal, &Attributes) -> String,
+    ) -> Result<String, Error>;
+
+    /// # `word_count()`
+    ///
+    /// Returns the number of whitespace delimited words in the text of the document.
+    /// Embeds separate words, and do not count as words themselves.
+    /// `word_count()` equals `word_count_with_flags(false)`.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Hello, big\nworld!\n");
+    /// assert_eq!(doc.word_count().unwrap(), 3);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` is not a document (i.e. contains other operations than Insert)
+    fn word_count(&self) -> Result<usize, Error>;
+
+    /// # `word_count_with_flags()`
+    ///
+    /// Counts words like `word_count()`, with `count_embeds` each embed counts as one word.
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` is not a document (i.e. contains other operations than Insert)
+    fn word_count_with_flags(&self, count_embeds: bool) -> Result<usize, Error>;
+
+    /// # `apply_with_inverse()`
+    ///
+    /// Applies a change to this document, and returns both the resulting document
+    /// and the inverse of the change, same as:<br>
+    /// `(self.compose(change), change.invert(self))`
+    ///
+    /// Useful for undo: `result.compose(inverse) === self`.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    /// use delta::optransform::OpTransform;
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Hello");
+    ///
+    /// let mut change = Delta::default();
+    /// change.retain(5);
+    /// change.insert("!");
+    ///
+    /// let (result, inverse) = doc.apply_with_inverse(&change).unwrap();
+    /// assert_eq!(result.compose(&inverse).unwrap(), doc);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` is not a document (i.e. contains other operations than Insert)<br>
+    /// any error returned by `compose()`, e.g. `ErrorDelta::SplitEmbed` if `change` ends within an embed
+    fn apply_with_inverse(&self, change: &Delta) -> Result<(Delta, Delta), Error>;
+
+    /// # `apply_undoable()`
+    ///
+    /// Applies a change to this document in place, like `apply_with_inverse()`, and
+    /// returns the inverse of the change to push on an undo stack.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Hello");
+    ///
+    /// let mut change = Delta::default();
+    /// change.retain(5);
+    /// change.insert("!");
+    ///
+    /// let undo = doc.apply_undoable(&change).unwrap();
+    /// assert_eq!(doc.len(), 1);
+    /// doc.apply_undoable(&undo).unwrap();
+    /// assert_eq!(doc.document_length(), 5);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` is not a document (i.e. contains other operations than Insert),
+    /// in which case `self` is left unchanged
+    fn apply_undoable(&mut self, change: &Delta) -> Result<Delta, Error>;
+
+    /// # `format_inserted()`
+    ///
+    /// Applies the attributes `attr` to `len` characters of this document, starting
+    /// at `index`, without going through `compose()`. The result is the same as
+    /// composing a change `retain(index)`, `retain_attr(len, attr)`: an attribute with
+    /// a `Null` value removes the attribute from the text.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut bold = Attributes::default();
+    /// bold.insert("bold", true);
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Hello World");
+    /// doc.format_inserted(6, 5, bold.clone()).unwrap();
+    ///
+    /// let mut expected = Delta::default();
+    /// expected.insert("Hello ");
+    /// expected.insert_attr("World", bold);
+    /// assert_eq!(doc, expected);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` is not a document (i.e. contains other operations than Insert)
+    fn format_inserted(
+        &mut self,
+        index: usize,
+        len: usize,
+        attr: Attributes,
+    ) -> Result<&mut Delta, Error>;
+
+    /// # `find_text()`
+    ///
+    /// Returns the position of the first occurrence of `needle` in the text of this
+    /// document, at or after position `from`, or `None` when it is not found.
+    /// The text may span multiple operations, also with different attributes.
+    ///
+    /// Positions are document positions, as used by `retain()`. An embed is treated
+    /// as `'\0'` characters, one for each position it spans.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut bold = Attributes::default();
+    /// bold.insert("bold", true);
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Hello ");
+    /// doc.insert_attr("World", bold.clone());
+    ///
+    /// // format the found text
+    /// let index = doc.find_text("o W", 0).unwrap().unwrap();
+    /// let mut change = Delta::default();
+    /// change.retain(index);
+    /// change.retain_attr(3, bold);
+    /// assert_eq!(index, 4);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` is not a document (i.e. contains other operations than Insert)
+    fn find_text(&self, needle: &str, from: usize) -> Result<Option<usize>, Error>;
+
+    /// # `to_lines()`
+    ///
+    /// Splits the document in to lines, as done by `each_line()` with the default
+    /// new line character. Each line is returned with the attributes of its new line
+    /// character; the line itself does not contain the new line character.
+    ///
+    /// A last line without new line character gets empty attributes.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut header = Attributes::default();
+    /// header.insert("header", 1);
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Title");
+    /// doc.insert_attr("\n", header.clone());
+    /// doc.insert("Text\n");
+    ///
+    /// let lines = doc.to_lines().unwrap();
+    /// assert_eq!(lines.len(), 2);
+    /// assert_eq!(lines[0].1, header);
+    /// assert_eq!(Delta::from_lines(lines), doc);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` is not a document (i.e. contains other operations than Insert)
+    fn to_lines(&self) -> Result<Vec<(Delta, Attributes)>, Error>;
+
+    /// # `changed_lines()`
+    ///
+    /// Returns the indices of the lines that differ between this document and `other`,
+    /// e.g. to render only the changed paragraphs. Lines are detected as in `to_lines()`,
+    /// and compared by index on their content and block attributes. So after a line is
+    /// inserted or removed, all following lines count as changed, and the lines that
+    /// only one of the documents has are changed too.
+    ///
+    /// ```
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut a = Delta::default();
+    /// a.insert("One\nTwo\nThree\n");
+    /// let mut b = Delta::default();
+    /// b.insert("One\nTwo!\nThree\n");
+    ///
+    /// assert_eq!(a.changed_lines(&b).unwrap(), vec![1]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` or `other` is not a document (i.e. contains other operations than Insert)
+    fn changed_lines(&self, other: &Delta) -> Result<Vec<usize>, Error>;
+
+    /// # `block_formats()`
+    ///
+    /// Returns the block formats of the document, i.e. the attributes of the new line
+    /// character of each line, such as headers, lists and alignment. Lines are
+    /// detected as in `to_lines()`, so there is one entry per line.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::{Delta, Document};
+    ///
+    /// let mut header = Attributes::default();
+    /// header.insert("header", 1);
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Title");
+    /// doc.insert_attr("\n", header.clone());
+    /// doc.insert("Text\n");
+    ///
+    /// assert_eq!(doc.block_formats().unwrap(), vec![header, Attributes::default()]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` is not a document (i.e. contains other operations than Insert)
+    fn block_formats(&self) -> Result<Vec<Attributes>, Error>;
+
+    /// # `set_block_format()`
+    ///
+    /// Returns the change delta that applies `attr` to the new line character ending
+    /// line `line_index` of the document, e.g. to make a paragraph a header.
+    /// The attributes are composed with the existing block format; use `AttrVal::Null`
+    /// values to remove formats.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::{Delta, Document};
+    /// use delta::optransform::OpTransform;
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Title\nText\n");
+    ///
+    /// let mut header = Attributes::default();
+    /// header.insert("header", 1);
+    /// let change = doc.set_block_format(0, header.clone()).unwrap();
+    /// // result = { ops: [{ retain: 5 }, { retain: 1, attributes: { header: 1 } }] }
+    /// assert_eq!(change.len(), 2);
+    /// assert_eq!(doc.compose(&change).unwrap().block_formats().unwrap()[0], header);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// `ErrorDelta::NotADocument`: if `self` is not a document (i.e. contains other operations than Insert)<br>
+    /// `ErrorDelta::IndexOutOfRange`: if the document has no line `line_index` that ends with a new line character
+    fn set_block_format(&self, line_index: usize, attr: Attributes) -> Result<Delta, Error>;
+
+    /// # `from_lines()`
+    ///
+    /// Joins lines in to a document, the reverse of `to_lines()`. Each line is
+    /// terminated by a new line character that carries the attributes of the line.
+    ///
+    /// Round-tripping is lossless for documents that end with a new line character,
+    /// as Quill documents do.
+    fn from_lines(lines: Vec<(Delta, Attributes)>) -> Delta;
+}
+
+impl Document for Delta {
+    fn concat(&mut self, mut other: Delta) -> &mut Delta {
+        if !other.is_empty() {
+            self.push(other.remove(0)); //merges repeated retain, delete, insert
+            self.extend(other.to_vec()); //should have no repetitions
+        }
+        self
+    }
+
+    fn append_block(&mut self, other: Delta) -> &mut Delta {
+        let separator = self.line_separator();
+        if let Some(last) = self.last() {
+            if !last.string_val().is_ok_and(|s| s.ends_with(separator)) {
+                self.insert(separator.to_string());
+            }
+        }
+        self.concat(other)
+    }
+
+    fn try_concat(&mut self, other: Delta) -> Result<&mut Delta, Error> {
+        if let (Some(left), Some(right)) = (self.last(), other.first()) {
+            if !left.get_attributes().is_equal(right.get_attributes()) {
+                return Err(Error::ConcatBoundaryConflict {
+                    left: format!("{:?}", left.get_attributes()),
+                    right: format!("{:?}", right.get_attributes()),
+                });
+            }
+        }
+        Ok(self.concat(other))
+    }
+
+    fn diff(&self, other: &Delta, _cursor: usize) -> Result<Delta, Error> {
+        let mut delta = diff_unchopped(self, other, diff)?;
+        delta.chop();
+        Ok(delta)
+    }
+
+    fn diff_attribute_aware(&self, other: &Delta) -> Result<Delta, Error> {
+        let mut tokens = EmbedTokens::new(&[self, other], "");
+        let a = to_diff_units(self, &mut tokens)?;
+        let b = to_diff_units(other, &mut tokens)?;
+        let mut delta = diff_sequences(self, other, &a, &b, diff)?;
+        delta.chop();
+        Ok(delta)
+    }
+
+    fn diff_text(&self, new_text: &str, cursor: usize) -> Result<Delta, Error> {
+        let mut tokens = EmbedTokens::new(&[self], new_text);
+        let aa = to_diff_string(self, &mut tokens)?;
+        let a: Vec<char> = aa.chars().collect();
+        let b: Vec<char> = new_text.chars().collect();
+
+        let mut script = EditScript::default();
+        myers::diff(&mut script, &a, 0, a.len(), &b, 0, b.len())?;
+        let cursor_chars = new_text
+            .char_indices()
+            .take_while(|(i, _)| *i < cursor)
+            .count();
+        let edits = slide_to_cursor(&script.edits, &a, &b, cursor_chars);
+
+        let units: Vec<usize> = char_lengths(self).collect();
+        let a_len = |from: usize, len: usize| -> usize { units[from..from + len].iter().sum() };
+
+        let mut delta = self.empty_like();
+        for edit in edits {
+            match edit {
+                Edit::Equal(o, _, len) => delta.retain(a_len(o, len)),
+                Edit::Delete(o, len, _) => delta.delete(a_len(o, len)),
+                Edit::Insert(_, n, len) => delta.insert(b[n..n + len].iter().collect::<String>()),
+            }
+        }
+        delta.chop();
+        Ok(delta)
+    }
+
+    fn diff_explicit_embeds(&self, other: &Delta) -> Result<Delta, Error> {
+        let delta = diff_unchopped(self, other, diff)?;
+        Ok(explicit_embed_retains(&delta, self))
+    }
+
+    fn morph_into(&self, target: &Delta, cursor: usize) -> Result<Delta, Error> {
+        self.diff(target, cursor)
+    }
+
+    fn morph(&mut self, target: &Delta) -> Result<&mut Delta, Error> {
+        let change = self.morph_into(target, 0)?;
+        *self = self.compose(&change)?;
+        Ok(self)
+    }
+
+    #[cfg(feature = "unicode")]
+    fn diff_normalized(&self, other: &Delta, cursor: usize) -> Result<Delta, Error> {
+        self.normalized().diff(&other.normalized(), cursor)
+    }
 
-impl Document for Delta {
-    fn concat(&mut self, mut other: Delta) -> &mut Delta {
-        if !other.is_empty() {
-            self.push(other.remove(0)); //merges repeated retain, delete, insert
-            self.extend(other.to_vec()); //should have no repetitions
+    #[cfg(feature = "unicode")]
+    fn normalized(&self) -> Delta {
+        use unicode_normalization::UnicodeNormalization;
+        let mut doc = self.clone();
+        for op in doc.iter_mut() {
+            if let OpKind::Insert(OpsVal::String(text)) = &mut op.kind {
+                *text = text.nfc().collect();
+            }
         }
-        self
+        doc
+    }
+
+    fn diff_no_clears(&self, other: &Delta) -> Result<Delta, Error> {
+        let mut delta = diff_unchopped(self, other, diff_no_clears)?;
+        delta.chop();
+        Ok(delta)
+    }
+
+    fn diff_capped(&self, other: &Delta, max_ops: usize) -> Result<Delta, Error> {
+        let delta = self.diff(other, 0)?;
+        if delta.len() <= max_ops {
+            return Ok(delta);
+        }
+        let mut coarse = self.empty_like_either(other);
+        for op in other.iter() {
+            coarse.push(op.clone());
+        }
+        coarse.delete(self.document_length());
+        if coarse.len() < delta.len() {
+            Ok(coarse)
+        } else {
+            Ok(delta)
+        }
+    }
+
+    fn bidirectional_diff(&self, other: &Delta) -> Result<(Delta, Delta), Error> {
+        let forward = self.diff(other, 0)?;
+        let backward = forward.invert(self);
+        Ok((forward, backward))
+    }
+
+    fn patch_string(&self, other: &Delta) -> Result<String, Error> {
+        let a = self.to_lines()?;
+        let b = other.to_lines()?;
+        let mut script = EditScript::default();
+        myers::diff(&mut script, &a, 0, a.len(), &b, 0, b.len())?;
+
+        //(marker, line number in self, line number in other, line)
+        let mut rows: Vec<(char, usize, usize, &Delta)> = Vec::new();
+        for edit in script.edits {
+            match edit {
+                Edit::Equal(o, n, len) => {
+                    rows.extend((0..len).map(|i| (' ', o + i, n + i, &a[o + i].0)));
+                }
+                Edit::Delete(o, len, n) => {
+                    rows.extend((0..len).map(|i| ('-', o + i, n, &a[o + i].0)));
+                }
+                Edit::Insert(o, n, len) => {
+                    rows.extend((0..len).map(|i| ('+', o, n + i, &b[n + i].0)));
+                }
+            }
+        }
+
+        let mut res = String::new();
+        let mut next = 0;
+        while let Some(first) = (next..rows.len()).find(|&i| rows[i].0 != ' ') {
+            //extend the hunk while the next change is within reach of the context
+            let mut last = first;
+            let reach = |last: usize| last + 1..rows.len().min(last + 2 * PATCH_CONTEXT + 2);
+            while let Some(i) = reach(last).find(|&i| rows[i].0 != ' ') {
+                last = i;
+            }
+            let end = rows.len().min(last + PATCH_CONTEXT + 1);
+            let hunk = &rows[first.saturating_sub(PATCH_CONTEXT)..end];
+            let old_len = hunk.iter().filter(|row| row.0 != '+').count();
+            let new_len = hunk.iter().filter(|row| row.0 != '-').count();
+            let old_start = hunk[0].1 + usize::from(old_len > 0);
+            let new_start = hunk[0].2 + usize::from(new_len > 0);
+            let header = format!("@@ -{old_start},{old_len} +{new_start},{new_len} @@\n");
+            res.push_str(&header);
+            for (marker, _, _, line) in hunk {
+                res.push(*marker);
+                res.push_str(&line.to_search_text(&|_, _| "\u{fffc}".to_string())?);
+                res.push('\n');
+            }
+            next = last + PATCH_CONTEXT + 1;
+        }
+        Ok(res)
+    }
+
+    fn edit_distance(&self, other: &Delta) -> Result<usize, Error> {
+        let mut tokens = EmbedTokens::new(&[self, other], "");
+        let aa = to_diff_string(self, &mut tokens)?;
+        let bb = to_diff_string(other, &mut tokens)?;
+        let a: Vec<char> = aa.chars().collect();
+        let b: Vec<char> = bb.chars().collect();
+
+        let mut script = EditScript::default();
+        myers::diff(&mut script, &a, 0, a.len(), &b, 0, b.len())?;
+        Ok(script
+            .edits
+            .iter()
+            .map(|edit| match *edit {
+                Edit::Equal(..) => 0,
+                Edit::Delete(_, len, _) | Edit::Insert(_, _, len) => len,
+            })
+            .sum())
     }
 
-    fn diff<'a>(&self, other: &Delta, _cursor: usize) -> Result<Delta, Error> {
-        //Collect all inserts in to 1 long string
-        let aa = to_diff_string(self)?;
-        let bb = to_diff_string(other)?;
-        //Split strings in characters to diff over
+    fn diff_min_equal(&self, other: &Delta, min_equal: usize) -> Result<Delta, Error> {
+        let mut tokens = EmbedTokens::new(&[self, other], "");
+        let aa = to_diff_string(self, &mut tokens)?;
+        let bb = to_diff_string(other, &mut tokens)?;
         let a: Vec<char> = aa.chars().collect();
         let b: Vec<char> = bb.chars().collect();
-        //result document
-        let mut delta = Delta::default();
 
+        //record the edit script first, so short equal runs can be merged afterwards
+        let mut script = EditScript::default();
+        myers::diff(&mut script, &a, 0, a.len(), &b, 0, b.len())?;
+        let edits = merge_short_equals(&script.edits, min_equal);
+
+        let embed_length = self.embed_length().or(other.embed_length());
+        let mut delta = self.empty_like_either(other);
         let mut ddd: D = D {
-            res: &mut delta,                       //delta to be returned
-            other: &mut DeltaIterator::new(other), //iterator other delta from input
-            me: &mut DeltaIterator::new(self),     //self delta ...
+            res: &mut delta,
+            other: &mut DeltaIterator::new(other).with_embed_length(embed_length),
+            me: &mut DeltaIterator::new(self).with_embed_length(embed_length),
+            attr_diff: diff,
+        };
+        for edit in edits {
+            match edit {
+                Edit::Equal(o, n, len) => ddd.equal(o, n, len)?,
+                Edit::Delete(o, len, n) => ddd.delete(o, len, n)?,
+                Edit::Insert(o, n, len) => ddd.insert(o, n, len)?,
+            }
+        }
+
+        delta.chop();
+        Ok(delta)
+    }
+
+    fn diff_granularity(
+        &self,
+        other: &Delta,
+        granularity: &DiffGranularity,
+    ) -> Result<Delta, Error> {
+        let tokenizer: &dyn Fn(&str) -> Vec<&str> = match granularity {
+            DiffGranularity::Char => return self.diff(other, 0),
+            DiffGranularity::Word => &word_tokens,
+            DiffGranularity::Token(t) => t,
+        };
+
+        let mut tokens = EmbedTokens::new(&[self, other], "");
+        let aa = to_diff_string(self, &mut tokens)?;
+        let bb = to_diff_string(other, &mut tokens)?;
+        let a = tokenize(&aa, tokenizer);
+        let b = tokenize(&bb, tokenizer);
+
+        let mut script = EditScript::default();
+        myers::diff(&mut script, &a, 0, a.len(), &b, 0, b.len())?;
+
+        //The delta operations are measured in characters, not in tokens
+        let a_len = |from: usize, len: usize| -> usize {
+            a[from..from + len].iter().map(|t| t.chars().count()).sum()
+        };
+        let b_len = |from: usize, len: usize| -> usize {
+            b[from..from + len].iter().map(|t| t.chars().count()).sum()
         };
 
-        let mut diff = Replace::new(&mut ddd);
-        myers::diff(&mut diff, &a, 0, a.len(), &b, 0, b.len()).unwrap();
+        let embed_length = self.embed_length().or(other.embed_length());
+        let mut delta = self.empty_like_either(other);
+        let mut ddd: D = D {
+            res: &mut delta,
+            other: &mut DeltaIterator::new(other).with_embed_length(embed_length),
+            me: &mut DeltaIterator::new(self).with_embed_length(embed_length),
+            attr_diff: diff,
+        };
+        for edit in script.edits {
+            match edit {
+                Edit::Equal(o, n, len) => ddd.equal(o, n, a_len(o, len))?,
+                Edit::Delete(o, len, n) => ddd.delete(o, a_len(o, len), n)?,
+                Edit::Insert(o, n, len) => ddd.insert(o, n, b_len(n, len))?,
+            }
+        }
 
         delta.chop();
         Ok(delta)
@@ -167,15 +1403,24 @@ impl Document for Delta {
     where
         F: Fn(&Delta, &Attributes, usize) -> bool,
     {
-        //Standard, or prescribed new line character?
-        let mut new_line = '\n';
-        if let Option::Some(nl) = new_line_char {
-            new_line = nl;
-        }
+        self.each_line_opts(predicate, new_line_char, &EachLineOptions::default())
+    }
+
+    fn each_line_opts<F>(
+        &self,
+        predicate: F,
+        new_line_char: Option<char>,
+        options: &EachLineOptions,
+    ) -> Result<(), Error>
+    where
+        F: Fn(&Delta, &Attributes, usize) -> bool,
+    {
+        //Prescribed, or the document's new line character?
+        let new_line = new_line_char.unwrap_or(self.line_separator());
 
         //collect a line ... repeatedly
-        let iter = DeltaIterator::new(self);
-        let mut line = Delta::default();
+        let iter = DeltaIterator::new(self).with_embed_length(self.embed_length());
+        let mut line = self.empty_like();
         let mut i = 0;
         while iter.has_next() {
             if iter.peek_type() != OpType::Insert {
@@ -184,46 +1429,55 @@ impl Document for Delta {
             let Some(this_op) = iter.peek() else {
                 return Err(Error::IteratorIsEmpty);
             };
-            let start = this_op.op_len() - iter.peek_len();
+            let start = self.len_of(this_op) - iter.peek_len();
             if this_op.is_object() {
-                line.push(iter.next_len(0));
+                let embed = iter.try_next_len(0)?;
+                match &options.embed_placeholder {
+                    _ if !options.count_embeds => {}
+                    Some(text) => {
+                        line.push(DeltaOperation::insert_attr(text.as_str(), embed.attributes));
+                    }
+                    None => line.push(embed),
+                }
             } else {
                 //no more options, it must be a string, or and object ...
                 let newline_found = this_op.string_val()?[start..].find(new_line);
                 match newline_found {
                     None => {
-                        line.push(iter.next_len(0));
+                        line.push(iter.try_next_len(0)?);
                     }
                     Some(t) => {
                         let len = t;
                         if len > 0 {
-                            line.push(iter.next_len(len));
+                            line.push(iter.try_next_len(len)?);
                         } else {
                             //len=0 --> we are ON the next line marker
-                            let go_on = predicate(&line, &iter.next_len(1).attributes, i);
+                            let new_line_op = iter.try_next_len(new_line.len_utf8())?;
+                            let go_on = predicate(&line, &new_line_op.attributes, i);
                             if !go_on {
                                 return Ok(());
                             }
                             i += 1;
-                            line = Delta::default();
+                            line = self.empty_like();
                         }
                     }
                 }
             }
         }
         //run the predicate on the remaining line (last char need not be a line break)
-        if line.delta_length() > 0 {
+        if line.delta_length() > 0 || options.include_empty_final_line {
             predicate(&line, &Attributes::default(), i);
         }
         Ok(())
     }
 
     fn invert(&self, base: &Delta) -> Delta {
-        let mut inverted = Delta::default();
+        let embed_length = self.embed_length().or(base.embed_length());
+        let mut inverted = self.empty_like_either(base);
 
         let predicate = |base_index: usize, op: &DeltaOperation| -> usize {
             if op.op_type() == OpType::Insert {
-                inverted.delete(op.op_len());
+                inverted.delete(op.op_len_with(embed_length));
             } else if op.op_type() == OpType::Retain && op.attributes.is_empty() {
                 inverted.retain(op.op_len());
                 return base_index + op.op_len();
@@ -236,10 +1490,7 @@ impl Document for Delta {
                     if op.op_type() == OpType::Delete {
                         inverted.push(base_op.clone());
                     } else if op.op_type() == OpType::Retain && !op.attributes.is_empty() {
-                        inverted.retain_attr(
-                            base_op.op_len(),
-                            invert(&op.attributes, &base_op.attributes),
-                        );
+                        invert_retain(&mut inverted, base, op, base_op);
                     }
                 });
                 return base_index + length;
@@ -247,98 +1498,793 @@ impl Document for Delta {
             base_index
         };
         self.iter().fold(0, predicate);
-        return inverted.chop().to_owned();
+        inverted.chop().to_owned()
+    }
+
+    fn invert_range(&self, base: &Delta, start: usize, end: usize) -> Result<Delta, Error> {
+        if base.iter().any(|op| op.op_type() != OpType::Insert) {
+            return Err(Error::NotADocument);
+        }
+        let length = base.document_length();
+        if start > end || end > length {
+            return Err(Error::IndexOutOfRange { index: end, length });
+        }
+        let embed_length = self.embed_length().or(base.embed_length());
+        let mut inverted = self.empty_like_either(base);
+        let iter = DeltaIterator::new(self).with_embed_length(embed_length);
+        let mut base_index = 0;
+        while iter.has_next() {
+            //split the operations at the boundaries of the range, inserts do not advance
+            let in_range = (start..end).contains(&base_index);
+            let boundary = match base_index {
+                _ if iter.peek_type() == OpType::Insert => usize::MAX,
+                i if i < start => start,
+                i if i < end => end,
+                _ => usize::MAX,
+            };
+            let op = iter.next_len(boundary - base_index);
+            let len = op.op_len_with(embed_length);
+            match op.op_type() {
+                OpType::Insert if in_range => inverted.delete(len),
+                OpType::Insert => inverted.retain(len),
+                OpType::Retain if !in_range || op.attributes.is_empty() => {
+                    inverted.retain(len);
+                    base_index += len;
+                }
+                OpType::Delete if !in_range => base_index += len,
+                _ => {
+                    for base_op in base.slice(base_index, base_index + len).iter() {
+                        if op.op_type() == OpType::Delete {
+                            inverted.push(base_op.clone());
+                        } else {
+                            invert_retain(&mut inverted, base, &op, base_op);
+                        }
+                    }
+                    base_index += len;
+                }
+            }
+        }
+        Ok(inverted.chop().to_owned())
+    }
+
+    fn clear_formatting_change(&self) -> Result<Delta, Error> {
+        let mut change = self.empty_like();
+        for op in self.iter() {
+            if op.op_type() != OpType::Insert {
+                return Err(Error::NotADocument);
+            }
+            let mut clear = Attributes::default();
+            for key in op.attributes.keys() {
+                clear.insert(key.as_str(), AttrVal::Null);
+            }
+            change.retain_attr(self.len_of(op), clear);
+        }
+        change.chop();
+        Ok(change)
     }
 
     fn document_length(&self) -> usize {
         let mut len: usize = 0;
         for d in self.iter() {
             match d.op_type() {
-                OpType::Insert => len += d.op_len(),
+                OpType::Insert => len += self.len_of(d),
                 OpType::Delete => len -= d.op_len(),
                 OpType::Retain => {}
             }
         }
         len
     }
+
+    fn to_document(&self) -> Delta {
+        let mut doc = self.empty_like();
+        for op in self.iter() {
+            if op.op_type() == OpType::Insert && !op.is_empty() {
+                doc.push(op.clone());
+            }
+        }
+        doc
+    }
+
+    fn chunk(&self, chunk_len: usize) -> Vec<Delta> {
+        let length = self.document_length();
+        if length == 0 {
+            return Vec::new();
+        }
+        if chunk_len == 0 {
+            return vec![self.clone()];
+        }
+
+        //cut between characters, and around embeds
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        let mut end = 0;
+        for len in char_lengths(self) {
+            if end > start && end + len > start + chunk_len {
+                chunks.push(self.slice(start, end));
+                start = end;
+            }
+            end += len;
+        }
+        chunks.push(self.slice(start, end));
+        chunks
+    }
+
+    fn split_at(&self, index: usize) -> Result<(Delta, Delta), Error> {
+        if self.iter().any(|op| op.op_type() != OpType::Insert) {
+            return Err(Error::NotADocument);
+        }
+        let length = self.document_length();
+        if index > length {
+            return Err(Error::IndexOutOfRange { index, length });
+        }
+        let iter = DeltaIterator::new(self).with_embed_length(self.embed_length());
+        let mut left = self.empty_like();
+        let mut position = 0;
+        while position < index && iter.has_next() {
+            iter.check_embed_split(index - position)?;
+            let op = iter.next_len(index - position);
+            position += self.len_of(&op);
+            left.push(op);
+        }
+        let mut right = self.empty_like();
+        right.extend(iter.rest());
+        Ok((left, right))
+    }
+
+    fn range_has_attribute(
+        &self,
+        start: usize,
+        end: usize,
+        key: &str,
+        value: &AttrVal,
+    ) -> Result<TriState, Error> {
+        if self.iter().any(|op| op.op_type() != OpType::Insert) {
+            return Err(Error::NotADocument);
+        }
+        let length = self.document_length();
+        if end > length || start > end {
+            return Err(Error::IndexOutOfRange { index: end, length });
+        }
+        if start == end {
+            return Ok(TriState::None);
+        }
+        let range = self.slice(start, end);
+        let with = range
+            .iter()
+            .filter(|op| op.get_attributes().get(key) == Some(value))
+            .count();
+        Ok(match with {
+            0 => TriState::None,
+            n if n == range.len() => TriState::All,
+            _ => TriState::Mixed,
+        })
+    }
+
+    fn to_search_text(
+        &self,
+        embed_render: &dyn Fn(&OpsVal, &Attributes) -> String,
+    ) -> Result<String, Error> {
+        let mut res = String::new();
+        for op in self.iter() {
+            match &op.kind {
+                OpKind::Insert(OpsVal::String(val)) => res.push_str(val),
+                OpKind::Insert(val) => res.push_str(&embed_render(val, &op.attributes)),
+                _ => return Err(Error::NotADocument),
+            }
+        }
+        Ok(res)
+    }
+
+    fn word_count(&self) -> Result<usize, Error> {
+        self.word_count_with_flags(false)
+    }
+
+    fn word_count_with_flags(&self, count_embeds: bool) -> Result<usize, Error> {
+        let embed = if count_embeds { " \u{fffc} " } else { " " };
+        let text = self.to_search_text(&|_, _| embed.to_string())?;
+        Ok(text.split_whitespace().count())
+    }
+
+    fn apply_with_inverse(&self, change: &Delta) -> Result<(Delta, Delta), Error> {
+        if self.iter().any(|op| op.op_type() != OpType::Insert) {
+            return Err(Error::NotADocument);
+        }
+        let result = self.compose(change)?;
+        Ok((result, change.invert(self)))
+    }
+
+    fn apply_undoable(&mut self, change: &Delta) -> Result<Delta, Error> {
+        let (result, inverse) = self.apply_with_inverse(change)?;
+        *self = result;
+        Ok(inverse)
+    }
+
+    fn format_inserted(
+        &mut self,
+        index: usize,
+        len: usize,
+        attr: Attributes,
+    ) -> Result<&mut Delta, Error> {
+        if self.iter().any(|op| op.op_type() != OpType::Insert) {
+            return Err(Error::NotADocument);
+        }
+        let formatted = {
+            let iter = DeltaIterator::new(self).with_embed_length(self.embed_length());
+            let mut formatted = self.empty_like();
+            let mut position = 0;
+            while iter.has_next() {
+                let op = if position < index {
+                    iter.check_embed_split(index - position)?;
+                    iter.next_len(index - position)
+                } else if position < index + len {
+                    iter.check_embed_split(index + len - position)?;
+                    let mut op = iter.next_len(index + len - position);
+                    let attributes = compose(&op.attributes, &attr, false);
+                    op.set_attributes(attributes);
+                    op
+                } else {
+                    iter.next_len(usize::MAX)
+                };
+                position += self.len_of(&op);
+                formatted.push(op);
+            }
+            formatted
+        };
+        *self = formatted;
+        Ok(self)
+    }
+
+    fn find_text(&self, needle: &str, from: usize) -> Result<Option<usize>, Error> {
+        let mut text = String::new();
+        for op in self.iter() {
+            match &op.kind {
+                OpKind::Insert(OpsVal::String(val)) => text.push_str(val),
+                OpKind::Insert(_) => {
+                    for _ in 0..self.len_of(op) {
+                        text.push(NULL_CHARACTER);
+                    }
+                }
+                _ => return Err(Error::NotADocument),
+            }
+        }
+        let Some(rest) = text.get(from..) else {
+            return Ok(None);
+        };
+        Ok(rest.find(needle).map(|index| from + index))
+    }
+
+    fn block_formats(&self) -> Result<Vec<Attributes>, Error> {
+        Ok(self
+            .to_lines()?
+            .into_iter()
+            .map(|(_, attributes)| attributes)
+            .collect())
+    }
+
+    fn set_block_format(&self, line_index: usize, attr: Attributes) -> Result<Delta, Error> {
+        if self.iter().any(|op| op.op_type() != OpType::Insert) {
+            return Err(Error::NotADocument);
+        }
+        let separator = self.line_separator();
+        let newlines: Vec<usize> = self
+            .ops_with_offsets()
+            .flat_map(|(offset, op)| {
+                let text = op.string_val().unwrap_or_default();
+                text.match_indices(separator).map(move |(i, _)| offset + i)
+            })
+            .collect();
+        let Some(position) = newlines.get(line_index) else {
+            return Err(Error::IndexOutOfRange {
+                index: line_index,
+                length: newlines.len(),
+            });
+        };
+        let mut change = Delta::default();
+        change.retain(*position);
+        change.retain_attr(separator.len_utf8(), attr);
+        Ok(change)
+    }
+
+    fn to_lines(&self) -> Result<Vec<(Delta, Attributes)>, Error> {
+        if self.iter().any(|op| op.op_type() != OpType::Insert) {
+            return Err(Error::NotADocument);
+        }
+        let lines = RefCell::new(Vec::new());
+        self.each_line(
+            |line, attributes, _| {
+                lines.borrow_mut().push((line.clone(), attributes.clone()));
+                true
+            },
+            None,
+        )?;
+        Ok(lines.into_inner())
+    }
+
+    fn changed_lines(&self, other: &Delta) -> Result<Vec<usize>, Error> {
+        let a = self.to_lines()?;
+        let b = other.to_lines()?;
+        Ok((0..a.len().max(b.len()))
+            .filter(|&i| a.get(i) != b.get(i))
+            .collect())
+    }
+
+    fn from_lines(lines: Vec<(Delta, Attributes)>) -> Delta {
+        let mut doc = Delta::default();
+        for (line, attributes) in lines {
+            doc.concat(line);
+            doc.insert_attr("\n", attributes);
+        }
+        doc
+    }
+}
+
+/// Private method
+/// Pushes the inverse of the retain `op` over `base_op` to `inverted`.
+/// An embed merged by the `EMBED_MERGE_KEY` instruction of `op` is replaced
+/// by the original embed, otherwise only the attributes are inverted.
+fn invert_retain(
+    inverted: &mut Delta,
+    base: &Delta,
+    op: &DeltaOperation,
+    base_op: &DeltaOperation,
+) {
+    let merged = match op.attributes.get(EMBED_MERGE_KEY) {
+        Some(instruction) if !base_op.is_string() => {
+            merge_embed(base_op.insert_value(), instruction).is_some()
+        }
+        _ => false,
+    };
+    if merged {
+        inverted.delete(base.len_of(base_op));
+        inverted.push(base_op.clone());
+    } else {
+        inverted.retain_attr(
+            base.len_of(base_op),
+            invert(&op.attributes, &base_op.attributes),
+        );
+    }
+}
+
+/// Private method
+/// Implements `diff()`, without removing the trailing retain.
+/// `attr_diff` calculates the attributes of the retains for equal content.
+fn diff_unchopped(this: &Delta, other: &Delta, attr_diff: AttrDiffFn) -> Result<Delta, Error> {
+    //Collect all inserts in to 1 long string
+    let mut tokens = EmbedTokens::new(&[this, other], "");
+    let aa = to_diff_string(this, &mut tokens)?;
+    let bb = to_diff_string(other, &mut tokens)?;
+    //Split strings in characters to diff over
+    let a: Vec<char> = aa.chars().collect();
+    let b: Vec<char> = bb.chars().collect();
+    diff_sequences(this, other, &a, &b, attr_diff)
+}
+
+/// Private method
+/// Diffs two documents, comparing the characters of `a` and `b`, which hold
+/// one item for each character of `this` and `other`.
+/// The common prefix and suffix are trimmed first, so Myers only runs on the
+/// changed middle, which is usually small compared to the document.
+fn diff_sequences<T: PartialEq>(
+    this: &Delta,
+    other: &Delta,
+    a: &[T],
+    b: &[T],
+    attr_diff: AttrDiffFn,
+) -> Result<Delta, Error> {
+    //result document
+    let embed_length = this.embed_length().or(other.embed_length());
+    let mut delta = this.empty_like_either(other);
+
+    let other_iter = DeltaIterator::new(other).with_embed_length(embed_length);
+    let this_iter = DeltaIterator::new(this).with_embed_length(embed_length);
+
+    let mut ddd: D = D {
+        res: &mut delta,    //delta to be returned
+        other: &other_iter, //iterator other delta from input
+        me: &this_iter,     //this delta ...
+        attr_diff,          //attribute changes for equal content
+    };
+
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_end, b_end) = (a.len() - suffix, b.len() - suffix);
+
+    let mut diff = Replace::new(&mut ddd);
+    if prefix > 0 {
+        diff.equal(0, 0, prefix)?;
+    }
+    myers::diff(&mut diff, a, prefix, a_end, b, prefix, b_end)?;
+    if suffix > 0 {
+        diff.equal(a_end, b_end, suffix)?;
+        diff.finish()?;
+    }
+    Ok(delta)
+}
+
+/// Private method
+/// Splits the retains of a change against `base` such that each embed of `base`
+/// gets a retain of its own, for `diff_explicit_embeds()`.
+/// The trailing retain is removed, unless it retains an embed.
+fn explicit_embed_retains(change: &Delta, base: &Delta) -> Delta {
+    let base_iter = DeltaIterator::new(base).with_embed_length(base.embed_length());
+    let mut ops: Vec<DeltaOperation> = Vec::new();
+    let mut last_embed = false;
+    for op in change.iter() {
+        match op.op_type() {
+            OpType::Insert => {
+                ops.push(op.clone());
+                last_embed = false;
+            }
+            OpType::Delete => {
+                let mut remaining = op.op_len();
+                while remaining > 0 && base_iter.has_next() {
+                    let piece = base_iter.next_len(remaining);
+                    remaining = remaining.saturating_sub(base.len_of(&piece));
+                }
+                ops.push(op.clone());
+                last_embed = false;
+            }
+            OpType::Retain => {
+                let mut remaining = op.op_len();
+                let mut text_len = 0;
+                while remaining > 0 && base_iter.has_next() {
+                    let piece = base_iter.next_len(remaining);
+                    remaining = remaining.saturating_sub(base.len_of(&piece));
+                    if piece.is_string() {
+                        text_len += piece.op_len();
+                        last_embed = false;
+                        continue;
+                    }
+                    if text_len > 0 {
+                        ops.push(DeltaOperation::retain_attr(text_len, op.attributes.clone()));
+                        text_len = 0;
+                    }
+                    ops.push(DeltaOperation::retain_attr(
+                        base.len_of(&piece),
+                        op.attributes.clone(),
+                    ));
+                    last_embed = true;
+                }
+                if text_len > 0 {
+                    ops.push(DeltaOperation::retain_attr(text_len, op.attributes.clone()));
+                }
+            }
+        }
+    }
+    if !last_embed {
+        if let Some(last) = ops.last() {
+            if last.op_type() == OpType::Retain && last.attributes.is_empty() {
+                ops.pop();
+            }
+        }
+    }
+    let mut delta = change.empty_like();
+    delta.extend(ops);
+    delta
 }
 
-/// placeholder char to embed in diff()
+/// placeholder char to embed in `diff()`, when `EmbedTokens` runs out of placeholders
 const NULL_CHARACTER: char = '\0';
 
+/// Unicode private use ranges, from which the embed placeholders are taken
+const PRIVATE_USE: [(u32, u32); 3] = [
+    (0xE000, 0xF8FF),
+    (0xF_0000, 0xF_FFFD),
+    (0x10_0000, 0x10_FFFD),
+];
+
+/// Private struct
+/// Assigns each distinct embed value a placeholder char for diffing, that does not
+/// occur in the text of the documents. Equal embeds get the same placeholder, so
+/// the diff can match them, and text never matches an embed.
+struct EmbedTokens {
+    used: HashSet<char>,
+    tokens: Vec<(OpsVal, char)>,
+    candidates: Box<dyn Iterator<Item = char>>,
+}
+
+impl EmbedTokens {
+    fn new(docs: &[&Delta], text: &str) -> Self {
+        let mut used: HashSet<char> = text.chars().collect();
+        for doc in docs {
+            for op in doc.iter() {
+                if let OpKind::Insert(OpsVal::String(val)) = &op.kind {
+                    used.extend(val.chars());
+                }
+            }
+        }
+        EmbedTokens {
+            used,
+            tokens: Vec::new(),
+            candidates: Box::new(
+                PRIVATE_USE
+                    .into_iter()
+                    .flat_map(|(start, end)| (start..=end).filter_map(char::from_u32)),
+            ),
+        }
+    }
+
+    fn token(&mut self, embed: &OpsVal) -> char {
+        if let Some((_, token)) = self.tokens.iter().find(|(val, _)| val == embed) {
+            return *token;
+        }
+        let used = &self.used;
+        // Out of placeholders, the diff still compares the embeds themselves
+        let token = self
+            .candidates
+            .find(|c| !used.contains(c))
+            .unwrap_or(NULL_CHARACTER);
+        self.tokens.push((embed.clone(), token));
+        token
+    }
+}
+
+/// Calculates the attribute changes between two equal pieces of content
+type AttrDiffFn = fn(&Attributes, &Attributes) -> Attributes;
+
 struct D<'a> {
     pub res: &'a mut Delta,
     pub other: &'a DeltaIterator<'a>,
     pub me: &'a DeltaIterator<'a>,
+    pub attr_diff: AttrDiffFn,
 }
 
-impl<'a> Diff for D<'a> {
-    type Error = ();
-    fn equal(&mut self, _o: usize, _new: usize, len: usize) -> Result<(), ()> {
+impl Diff for D<'_> {
+    type Error = diffs::Error;
+    fn equal(&mut self, _o: usize, _new: usize, len: usize) -> Result<(), diffs::Error> {
         let mut l = len;
         while l > 0 {
             //dbg!( "diff --> Equal ");
-            let v = [self.me.peek_len(), self.other.peek_len(), len];
-            let op_len = *v.iter().min().unwrap();
-            let this_op = self.me.next_len(op_len);
-            let other_op = self.other.next_len(op_len);
+            let (_, me_chars) = next_units(self.me, l);
+            let (_, other_chars) = next_units(self.other, l);
+            let chars = me_chars.min(other_chars);
+            let (me_len, _) = next_units(self.me, chars);
+            let (other_len, _) = next_units(self.other, chars);
+            let this_op = self.me.next_len(me_len);
+            let other_op = self.other.next_len(other_len);
             if this_op.op_type() == OpType::Insert
                 && other_op.op_type() == OpType::Insert
                 && this_op.is_same_operation(&other_op)
             {
-                let mut delta = DeltaOperation::retain(op_len);
-                delta.set_attributes(diff(&this_op.attributes, &other_op.attributes));
+                let mut delta = DeltaOperation::retain(me_len);
+                delta.set_attributes((self.attr_diff)(&this_op.attributes, &other_op.attributes));
                 self.res.push(delta);
             } else {
                 // dbg!(&other_op);
                 self.res.push(other_op.clone());
-                self.res.delete(op_len);
+                self.res.delete(me_len);
             }
-            l -= op_len;
+            l -= chars;
         }
         Ok(())
     }
-    fn delete(&mut self, _o: usize, len: usize, _new: usize) -> Result<(), ()> {
+    fn delete(&mut self, _o: usize, len: usize, _new: usize) -> Result<(), diffs::Error> {
         let mut l = len;
         while l > 0 {
             //dbg!( "diff --> Delete ");
-            let v = [self.me.peek_len(), len];
-            let op_len = *v.iter().min().unwrap();
+            let (op_len, chars) = next_units(self.me, l);
             self.me.next_len(op_len);
             let op = DeltaOperation::delete(op_len);
             // dbg!(&op);
             self.res.push(op);
-            l -= op_len;
+            l -= chars;
         }
         Ok(())
     }
-    fn insert(&mut self, _o: usize, _n: usize, len: usize) -> Result<(), ()> {
+    fn insert(&mut self, _o: usize, _n: usize, len: usize) -> Result<(), diffs::Error> {
         let mut l = len;
         while l > 0 {
             //dbg!( "diff --> Insert ");
-            // dbg!(_len);
-            // dbg!(self.other.peek_len());
-            let v = [self.other.peek_len(), len];
-            let op_len = *v.iter().min().unwrap();
-            // dbg!(op_len);
+            let (op_len, chars) = next_units(self.other, l);
             let op = self.other.next_len(op_len).clone();
             // dbg!(&op);
             self.res.push(op);
-            l -= op_len;
+            l -= chars;
         }
-        // dbg!( self.me.debug_index());
-        // dbg!( self.other.debug_index());
         Ok(())
     }
 }
 
 /// Private method
-/// To convert a list of DeltaOperation in to 1 single string
-/// Regardless of the attributes in each DeltaOperation
+/// The diff counts characters, while the length of a string insert counts bytes.
+/// Returns the length in operation units, and in characters, of at most `chars`
+/// characters of the next operation of `iter`.
+/// An embed is a single character in the diff, spanning the length of the embed.
+fn next_units(iter: &DeltaIterator, chars: usize) -> (usize, usize) {
+    let Some(op) = iter.peek() else {
+        return (chars, chars);
+    };
+    match op.string_val() {
+        Ok(text) => {
+            let rest = &text[iter.position().1..];
+            match rest.char_indices().nth(chars) {
+                Some((i, _)) => (i, chars),
+                None => (rest.len(), rest.chars().count()),
+            }
+        }
+        Err(_) if op.op_type() == OpType::Insert => (iter.peek_len(), 1),
+        Err(_) => {
+            let len = iter.peek_len().min(chars);
+            (len, len)
+        }
+    }
+}
+
+/// number of unchanged lines shown around a change by `patch_string()`
+const PATCH_CONTEXT: usize = 3;
+
+/// A single step in an edit script, using the same arguments as the `Diff` trait
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Edit {
+    Equal(usize, usize, usize),
+    Delete(usize, usize, usize),
+    Insert(usize, usize, usize),
+}
+
+/// Records the edit script produced by a diff algorithm, for post processing
+#[derive(Default)]
+struct EditScript {
+    edits: Vec<Edit>,
+}
+
+impl Diff for EditScript {
+    type Error = diffs::Error;
+    fn equal(&mut self, old: usize, new: usize, len: usize) -> Result<(), diffs::Error> {
+        self.edits.push(Edit::Equal(old, new, len));
+        Ok(())
+    }
+    fn delete(&mut self, old: usize, len: usize, new: usize) -> Result<(), diffs::Error> {
+        self.edits.push(Edit::Delete(old, len, new));
+        Ok(())
+    }
+    fn insert(&mut self, old: usize, new: usize, len: usize) -> Result<(), diffs::Error> {
+        self.edits.push(Edit::Insert(old, new, len));
+        Ok(())
+    }
+}
+
+/// Private method
+/// Converts equal runs shorter than `min_equal`, with a change on both sides,
+/// in to a delete followed by an insert of the same length.
+fn merge_short_equals(edits: &[Edit], min_equal: usize) -> Vec<Edit> {
+    let mut res = Vec::with_capacity(edits.len());
+    for (i, edit) in edits.iter().enumerate() {
+        match *edit {
+            Edit::Equal(o, n, len)
+                if len < min_equal
+                    && i > 0
+                    && !matches!(edits.get(i - 1), Some(Edit::Equal(..)) | None)
+                    && !matches!(edits.get(i + 1), Some(Edit::Equal(..)) | None) =>
+            {
+                res.push(Edit::Delete(o, len, n));
+                res.push(Edit::Insert(o, n, len));
+            }
+            _ => res.push(*edit),
+        }
+    }
+    res
+}
+
+/// Private method
+/// Returns the length of each character of the document: a character of a string
+/// insert spans its UTF-8 length, an embed is a single character spanning the
+/// length of the embed.
+fn char_lengths(delta: &Delta) -> impl Iterator<Item = usize> + '_ {
+    delta.iter().flat_map(|op| match op.string_val() {
+        Ok(text) => text.chars().map(char::len_utf8).collect(),
+        Err(_) => vec![delta.len_of(op)],
+    })
+}
+
+/// Private method
+/// Moves an insert or delete with equal runs on both sides along repeated text,
+/// when it can be made at `cursor`, a character index in the new text: an insert
+/// then ends at the cursor, a delete starts at it. Other edits keep their place.
+/// The result of the edits does not change.
+fn slide_to_cursor(edits: &[Edit], a: &[char], b: &[char], cursor: usize) -> Vec<Edit> {
+    //join consecutive edits of the same kind, and surround them with empty equal
+    //runs, so each change at the ends has neighbours
+    let mut res = vec![Edit::Equal(0, 0, 0)];
+    for edit in edits {
+        match (res.last_mut(), *edit) {
+            (Some(Edit::Equal(_, _, len)), Edit::Equal(_, _, more))
+            | (Some(Edit::Delete(_, len, _)), Edit::Delete(_, more, _))
+            | (Some(Edit::Insert(_, _, len)), Edit::Insert(_, _, more)) => *len += more,
+            _ => res.push(*edit),
+        }
+    }
+    res.push(Edit::Equal(a.len(), b.len(), 0));
+    for i in 1..res.len() - 1 {
+        let (Edit::Equal(po, pn, pl), Edit::Equal(no, nn, nl)) = (res[i - 1], res[i + 1]) else {
+            continue;
+        };
+        let (seq, start, len, pos) = match res[i] {
+            Edit::Insert(_, n, len) => (b, n, len, n + len),
+            Edit::Delete(o, len, n) => (a, o, len, n),
+            Edit::Equal(..) => continue,
+        };
+        let left = (0..pl)
+            .take_while(|j| seq[start - 1 - j] == seq[start + len - 1 - j])
+            .count();
+        let right = (0..nl)
+            .take_while(|j| seq[start + j] == seq[start + len + j])
+            .count();
+        //only a cursor within the repeated text picks between the places
+        if cursor + left < pos || cursor > pos + right {
+            continue;
+        }
+        let (back, ahead) = (pos.saturating_sub(cursor), cursor.saturating_sub(pos));
+        res[i - 1] = Edit::Equal(po, pn, pl + ahead - back);
+        res[i] = match res[i] {
+            Edit::Insert(o, n, len) => Edit::Insert(o + ahead - back, n + ahead - back, len),
+            Edit::Delete(o, len, n) => Edit::Delete(o + ahead - back, len, n + ahead - back),
+            equal @ Edit::Equal(..) => equal,
+        };
+        res[i + 1] = Edit::Equal(no + ahead - back, nn + ahead - back, nl + back - ahead);
+    }
+    res.retain(|edit| !matches!(edit, Edit::Equal(_, _, 0)));
+    res
+}
+
+/// Private method
+/// Splits the text with the tokenizer, and adds the text between the tokens as
+/// separate tokens, such that the tokens concatenate to the original text again.
+fn tokenize<'t>(text: &'t str, tokenizer: &dyn Fn(&str) -> Vec<&str>) -> Vec<&'t str> {
+    let mut res = Vec::new();
+    let mut pos = 0;
+    for token in tokenizer(text) {
+        if token.is_empty() {
+            continue;
+        }
+        let Some(start) = text[pos..].find(token) else {
+            continue;
+        };
+        if start > 0 {
+            res.push(&text[pos..pos + start]);
+        }
+        res.push(&text[pos + start..pos + start + token.len()]);
+        pos += start + token.len();
+    }
+    if pos < text.len() {
+        res.push(&text[pos..]);
+    }
+    res
+}
+
+/// Private method
+/// Word tokenizer: runs of alphanumeric characters are one token,
+/// any other character is a token of its own.
+fn word_tokens(text: &str) -> Vec<&str> {
+    let mut res = Vec::new();
+    let mut word_start: Option<usize> = None;
+    for (i, c) in text.char_indices() {
+        if c.is_alphanumeric() {
+            word_start.get_or_insert(i);
+            continue;
+        }
+        if let Some(start) = word_start.take() {
+            res.push(&text[start..i]);
+        }
+        res.push(&text[i..i + c.len_utf8()]);
+    }
+    if let Some(start) = word_start {
+        res.push(&text[start..]);
+    }
+    res
+}
+
+/// Private method
+/// To convert a list of `DeltaOperation` in to 1 single string
+/// Regardless of the attributes in each `DeltaOperation`
 ///
 /// Generate a string with all insert concatenated
-/// and non string things "Insert(Hasmap)"  represented by `NULL_CHARACTER`.
-fn to_diff_string(delta: &Delta) -> Result<String, Error> {
+/// and non string things "Insert(Hasmap)" represented by a placeholder from `tokens`.
+fn to_diff_string(delta: &Delta, tokens: &mut EmbedTokens) -> Result<String, Error> {
     let mut res = String::new();
     for op in delta.iter() {
         match op {
@@ -349,10 +2295,10 @@ fn to_diff_string(delta: &Delta) -> Result<String, Error> {
                 res.push_str(&val[..]);
             }
             DeltaOperation {
-                kind: OpKind::Insert(_),
+                kind: OpKind::Insert(embed),
                 ..
             } => {
-                res.push(NULL_CHARACTER);
+                res.push(tokens.token(embed));
             }
             //A document is valid when all delta in the document are "insert" operations
             _ => return Err(Error::NotADocument),
@@ -361,6 +2307,26 @@ fn to_diff_string(delta: &Delta) -> Result<String, Error> {
     Ok(res)
 }
 
+/// Private method
+/// Like `to_diff_string()`, but pairs each character with the attributes of its operation,
+/// such that content only compares equal when its formatting is equal too.
+fn to_diff_units<'a>(
+    delta: &'a Delta,
+    tokens: &mut EmbedTokens,
+) -> Result<Vec<(char, &'a Attributes)>, Error> {
+    let mut res = Vec::new();
+    for op in delta.iter() {
+        match &op.kind {
+            OpKind::Insert(OpsVal::String(val)) => {
+                res.extend(val.chars().map(|c| (c, &op.attributes)));
+            }
+            OpKind::Insert(embed) => res.push((tokens.token(embed), &op.attributes)),
+            _ => return Err(Error::NotADocument),
+        }
+    }
+    Ok(res)
+}
+
 #[cfg(test)]
 mod test {
     use crate::delta::Delta;
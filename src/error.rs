@@ -27,4 +27,27 @@ pub enum Error {
     EmptyVectorLastOp,
     #[error("Iterator has no next element")]
     IteratorIsEmpty,
+    #[error("Malformed delta: found an operation with zero length (index {index:?})")]
+    ZeroLengthOperation { index: usize },
+    #[error("Inserting a value of zero length")]
+    EmptyInsert,
+    #[error("A retain or delete of length {len:?} splits the embed of length {embed_len:?}")]
+    SplitEmbed { len: usize, embed_len: usize },
+    #[error("Concatenation conflict: operations at the boundary have different attributes ({left:?} and {right:?})")]
+    ConcatBoundaryConflict { left: String, right: String },
+    #[error("Index {index:?} is out of range for a document of length {length:?}")]
+    IndexOutOfRange { index: usize, length: usize },
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[cfg(feature = "json")]
+    #[error("Deserialization error on line {line:?}: {source}")]
+    JsonLine {
+        line: usize,
+        source: serde_json::Error,
+    },
+    #[cfg(feature = "json")]
+    #[error("Deserialization error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Diff algorithm failed: {0}")]
+    Diff(#[from] diffs::Error),
 }
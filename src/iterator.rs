@@ -6,28 +6,33 @@
 // copied, modified, or distributed except according to those terms.
 
 
-use crate::operations::{DeltaOperation, OpType};
+use crate::attributes::Attributes;
+use crate::error::Error;
+use crate::operations::{DeltaOperation, EmbedLength, OpType, OpsVal};
+use crate::types::ops_kind::OpKind;
+use std::borrow::Cow;
 use std::cell::Cell;
 use std::option::Option;
 
-/// # DeltaIterator
+/// # `DeltaIterator`
 ///
-/// Iterator iterating over the content IN the DeltaOperations.
+/// Iterator iterating over the content IN the `DeltaOperation`s.
 ///
-/// Hence we do not only iterate over the Objects of type DeltaOperation,
+/// Hence we do not only iterate over the Objects of type `DeltaOperation`,
 /// but also inside. There are 2 indexes:
-///  - index pointing to a DeltaOperation;
-///  - offset pointing to a position inside the DeltaOperation.
+///  - index pointing to a `DeltaOperation`;
+///  - offset pointing to a position inside the `DeltaOperation`.
 ///
 /// For input values, the index may have values
 ///  - 0 --> first
 ///  - any usize --> some value in the array
-///  - usize::MAX --> end of the list reached
+///  - `usize::MAX` --> end of the list reached
 #[allow(clippy::module_name_repetitions)]
 pub struct DeltaIterator<'a> {
     ops: &'a Vec<DeltaOperation>, //private list of elements to iterate over
     index: Cell<usize>,           //private index in the vector
     offset: Cell<usize>, //private Position in the string in the DeltaOperation (in case operation == "Insert")
+    embed_length: Option<&'a EmbedLength>, //private length function for embeds, None gives length 1
 }
 
 impl<'a> DeltaIterator<'a> {
@@ -36,14 +41,25 @@ impl<'a> DeltaIterator<'a> {
             ops,
             index: Cell::new(0),
             offset: Cell::new(0),
+            embed_length: None,
         }
     }
 
+    /// # `with_embed_length()`
+    ///
+    /// Measures the embeds with `embed_length`, in stead of giving them length 1.
+    /// See `Delta::with_embed_length()`.
+    #[must_use]
+    pub fn with_embed_length(mut self, embed_length: Option<&'a EmbedLength>) -> Self {
+        self.embed_length = embed_length;
+        self
+    }
+
     pub fn has_next(&self) -> bool {
         self.peek_len() < usize::MAX
     }
 
-    /// # peek()
+    /// # `peek()`
     ///
     /// Returns the delta operation that is next in line to be processed.
     /// But does NOT advance to the next operation.
@@ -58,7 +74,7 @@ impl<'a> DeltaIterator<'a> {
         None
     }
 
-    /// # next()
+    /// # `next()`
     ///
     /// Returns the next operation, and advances the index to the
     /// next operation.
@@ -76,9 +92,9 @@ impl<'a> DeltaIterator<'a> {
         }
     }
 
-    /// # peek_len()
+    /// # `peek_len()`
     ///
-    /// Assuming we are on an offset o in an DeltaOperation on Delta operation index i.<br>
+    /// Assuming we are on an offset o in an `DeltaOperation` on Delta operation index i.<br>
     /// We return the remaining length of the Delta operation we point to:
     ///     `op[i].len - offset`
     ///
@@ -87,14 +103,14 @@ impl<'a> DeltaIterator<'a> {
     /// # Panics
     /// when internal index offset or index values are wrong
     pub fn peek_len(&self) -> usize {
-        if self.ops.len() > self.index.get() {
-            self.ops.get(self.index.get()).unwrap().op_len() - self.offset.get()
+        if let Some(op) = self.ops.get(self.index.get()) {
+            op.op_len_with(self.embed_length) - self.offset.get()
         } else {
             usize::MAX
         }
     }
 
-    /// # peek_type()
+    /// # `peek_type()`
     ///
     /// Returns the `OpType` of the next operation without advancing the index.
     /// # Panics
@@ -109,17 +125,18 @@ impl<'a> DeltaIterator<'a> {
         }
     }
 
-    /// # next_len()
+    /// # `next_len()`
     ///
-    /// Returns the next DeltaOperation or a slice thereof
+    /// Returns the next `DeltaOperation` or a slice thereof
     /// depending on the length of the input parameter len:
     ///
     ///  - If len == 0 the next operation is returned
     ///  - If len > 0 the next operation is returned, or a slice
-    ///  - If len takes us past the current DeltaOperation Length, we get the remainder of the DeltaOperation
+    ///  - If len takes us past the current `DeltaOperation` Length, we get the remainder of the `DeltaOperation`
     ///
     /// # Panics
-    /// when internal index offset or index values are wrong
+    /// when internal index offset or index values are wrong, or when `len` splits an embed,
+    /// see `try_next_len()`
     pub fn next_len(&self, len: usize) -> DeltaOperation {
         let mut length = len;
         if length == 0 {
@@ -132,7 +149,7 @@ impl<'a> DeltaIterator<'a> {
             let next_op = self.ops.get(index).unwrap();
 
             //Determining the slice we need to take
-            let op_length = next_op.op_len();
+            let op_length = next_op.op_len_with(self.embed_length);
             let mut act_len = op_length - offset;
 
             //Updating index for next step
@@ -147,15 +164,16 @@ impl<'a> DeltaIterator<'a> {
             }
 
             //returning resulting operation: delete, retain, insert
+            //slices keep the id of the operation
             match next_op.op_type() {
                 OpType::Delete => {
                     let op = DeltaOperation::delete(act_len);
-                    return op;
+                    return op.with_id_of(next_op);
                 }
                 OpType::Retain => {
                     let mut op = DeltaOperation::retain(act_len);
                     op.set_attributes(next_op.attributes.clone());
-                    return op;
+                    return op.with_id_of(next_op);
                 }
                 OpType::Insert => {
                     if next_op.is_string() {
@@ -163,10 +181,11 @@ impl<'a> DeltaIterator<'a> {
                         let mut op =
                             DeltaOperation::insert(s[offset..offset + act_len].to_string());
                         op.set_attributes(next_op.attributes.clone());
-                        return op;
+                        return op.with_id_of(next_op);
                     }
+                    // Embeds are atomic, also when they span multiple positions
                     assert_eq!(offset, 0);
-                    assert_eq!(act_len, 1);
+                    assert_eq!(act_len, op_length, "a length of {len} splits an embed");
                     return next_op.clone();
                 }
             }
@@ -174,7 +193,99 @@ impl<'a> DeltaIterator<'a> {
         DeltaOperation::retain(usize::MAX)
     }
 
-    /// # rest()
+    /// # `next_borrowed()`
+    ///
+    /// Advances like `next_len()`, but returns a view on the operation instead of a copy.
+    /// The attributes are borrowed, and so is the insert value, unless a string has to
+    /// be sliced. Returns `None` when the iterator is exhausted, where `next_len()`
+    /// returns a `retain(usize::MAX)`.
+    ///
+    /// # Panics
+    /// when internal index offset or index values are wrong, or when `len` splits an embed
+    pub(crate) fn next_borrowed(&self, len: usize) -> Option<OpView<'a>> {
+        let length = if len == 0 { usize::MAX } else { len };
+        let index = self.index.get();
+        let op = self.ops.get(index)?;
+        let offset = self.offset.get();
+        let remaining = op.op_len_with(self.embed_length) - offset;
+
+        let (act_len, value) = match &op.kind {
+            OpKind::Insert(OpsVal::String(s)) if length < remaining || offset > 0 => {
+                let act_len = length.min(remaining);
+                let slice = s[offset..offset + act_len].to_string();
+                (act_len, Some(Cow::Owned(OpsVal::String(slice))))
+            }
+            // Embeds are atomic, also when they span multiple positions
+            OpKind::Insert(value) => {
+                assert_eq!(offset, 0);
+                assert!(length >= remaining, "a length of {len} splits an embed");
+                (remaining, Some(Cow::Borrowed(value)))
+            }
+            OpKind::Retain(_) | OpKind::Delete(_) => (length.min(remaining), None),
+        };
+
+        //Updating index for next step
+        if act_len == remaining {
+            self.index.set(index + 1);
+            self.offset.set(0);
+        } else {
+            self.offset.set(offset + act_len);
+        }
+        Some(OpView {
+            op,
+            len: act_len,
+            value,
+        })
+    }
+
+    /// # `try_next_len()`
+    ///
+    /// Bound checked version of `next_len()`.
+    ///
+    /// Where `next_len()` returns a `retain(usize::MAX)` when the iterator is exhausted,
+    /// this method returns an error. It also returns an error when the next operation
+    /// has zero length, which a well-formed delta never contains, but which would
+    /// otherwise never advance the offset inside the operation.
+    ///
+    /// # Errors
+    ///  - `IteratorIsEmpty` when there are no more operations
+    ///  - `ZeroLengthOperation` when the next operation has length 0
+    ///  - `SplitEmbed` when `len` covers only part of an embed
+    pub fn try_next_len(&self, len: usize) -> Result<DeltaOperation, Error> {
+        let Some(next_op) = self.peek() else {
+            return Err(Error::IteratorIsEmpty);
+        };
+        if next_op.op_len() == 0 {
+            return Err(Error::ZeroLengthOperation {
+                index: self.index.get(),
+            });
+        }
+        self.check_embed_split(len)?;
+        Ok(self.next_len(len))
+    }
+
+    /// Private method
+    /// Returns `SplitEmbed` when taking `len` from the next operation would take
+    /// only part of an embed. A `len` of 0 takes the whole operation.
+    pub(crate) fn check_embed_split(&self, len: usize) -> Result<(), Error> {
+        if let Some(op) = self.peek() {
+            let embed_len = self.peek_len();
+            if op.op_type() == OpType::Insert && !op.is_string() && len != 0 && len < embed_len {
+                return Err(Error::SplitEmbed { len, embed_len });
+            }
+        }
+        Ok(())
+    }
+
+    /// # `position()`
+    ///
+    /// Returns the current `(index, offset)` of the iterator: the index of the
+    /// operation, and the offset inside that operation.
+    pub fn position(&self) -> (usize, usize) {
+        (self.index.get(), self.offset.get())
+    }
+
+    /// # `rest()`
     ///
     /// Returns the remainder of the operations stack
     /// to which the iterator points
@@ -197,6 +308,190 @@ impl<'a> DeltaIterator<'a> {
     }
 }
 
+/// # `OpView`
+///
+/// Borrowed view on an operation, or a slice thereof, as returned by `next_borrowed()`.
+/// Only a sliced string insert owns its value.
+pub(crate) struct OpView<'a> {
+    pub(crate) op: &'a DeltaOperation, //operation the view is taken from
+    pub(crate) len: usize,
+    pub(crate) value: Option<Cow<'a, OpsVal>>, //insert value, None for retain and delete
+}
+
+impl<'a> OpView<'a> {
+    pub(crate) fn op_type(&self) -> OpType {
+        self.op.op_type()
+    }
+
+    pub(crate) fn attributes(&self) -> &'a Attributes {
+        &self.op.attributes
+    }
+
+    /// Returns the viewed operation, as `next_len()` would have.
+    pub(crate) fn into_op(self) -> DeltaOperation {
+        let op = match self.value {
+            Some(value) => {
+                DeltaOperation::insert_attr(value.into_owned(), self.op.attributes.clone())
+            }
+            None if self.op_type() == OpType::Delete => DeltaOperation::delete(self.len),
+            None => DeltaOperation::retain_attr(self.len, self.op.attributes.clone()),
+        };
+        op.with_id_of(self.op)
+    }
+}
+
+/// # `ReverseDeltaIterator`
+///
+/// Iterator iterating over the content IN the `DeltaOperation`s, starting from the end.
+///
+/// It mirrors `DeltaIterator`, with both indexes counted from the back:
+///  - index pointing to a `DeltaOperation`, 0 being the last one;
+///  - offset pointing to a position inside the `DeltaOperation`, counted from its end.
+///
+/// Slices taken with `next_len()` are the trailing part of the remaining operation.
+#[allow(clippy::module_name_repetitions)]
+pub struct ReverseDeltaIterator<'a> {
+    ops: &'a Vec<DeltaOperation>, //private list of elements to iterate over
+    index: Cell<usize>,           //private index in the vector, counted from the end
+    offset: Cell<usize>,          //private position in the DeltaOperation, counted from its end
+    embed_length: Option<&'a EmbedLength>, //private length function for embeds, None gives length 1
+}
+
+impl<'a> ReverseDeltaIterator<'a> {
+    pub fn new(ops: &'a Vec<DeltaOperation>) -> Self {
+        ReverseDeltaIterator {
+            ops,
+            index: Cell::new(0),
+            offset: Cell::new(0),
+            embed_length: None,
+        }
+    }
+
+    /// # `with_embed_length()`
+    ///
+    /// Measures the embeds with `embed_length`, in stead of giving them length 1.
+    /// See `Delta::with_embed_length()`.
+    #[must_use]
+    pub fn with_embed_length(mut self, embed_length: Option<&'a EmbedLength>) -> Self {
+        self.embed_length = embed_length;
+        self
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.peek_len() < usize::MAX
+    }
+
+    /// # `peek()`
+    ///
+    /// Returns the delta operation that is next in line to be processed, counting
+    /// from the end. But does NOT advance to the previous operation.
+    pub fn peek(&self) -> Option<&DeltaOperation> {
+        let index = self.index.get();
+        if index < self.ops.len() {
+            return self.ops.get(self.ops.len() - 1 - index);
+        }
+        None
+    }
+
+    /// # `next()`
+    ///
+    /// Returns the next operation counting from the end, and advances the index
+    /// to the operation in front of it.
+    pub fn next(&self) -> Option<&DeltaOperation> {
+        let ret = self.peek();
+        if ret.is_some() {
+            self.index.set(self.index.get() + 1);
+            self.offset.set(0);
+        }
+        ret
+    }
+
+    /// # `peek_len()`
+    ///
+    /// Returns the remaining length of the Delta operation we point to:
+    ///     `op[i].len - offset`
+    ///
+    /// or `usize::MAX` when the iterator is exhausted.
+    pub fn peek_len(&self) -> usize {
+        match self.peek() {
+            Some(op) => op.op_len_with(self.embed_length) - self.offset.get(),
+            None => usize::MAX,
+        }
+    }
+
+    /// # `peek_type()`
+    ///
+    /// Returns the `OpType` of the next operation without advancing the index.
+    pub fn peek_type(&self) -> OpType {
+        self.peek().map_or(OpType::Retain, DeltaOperation::op_type)
+    }
+
+    /// # `next_len()`
+    ///
+    /// Returns the trailing part of the remaining `DeltaOperation`
+    /// depending on the length of the input parameter len:
+    ///
+    ///  - If len == 0 the remainder of the operation is returned
+    ///  - If len > 0 the last len positions of the remainder are returned
+    ///  - If len takes us past the start of the `DeltaOperation`, we get the remainder of the `DeltaOperation`
+    ///
+    /// When the iterator is exhausted a `retain(usize::MAX)` is returned.
+    ///
+    /// # Panics
+    /// when internal index offset or index values are wrong, or when `len` splits an embed
+    pub fn next_len(&self, len: usize) -> DeltaOperation {
+        let length = if len == 0 { usize::MAX } else { len };
+
+        let Some(next_op) = self.peek() else {
+            return DeltaOperation::retain(usize::MAX);
+        };
+        let index = self.index.get();
+        let offset = self.offset.get();
+        let remaining = next_op.op_len_with(self.embed_length) - offset;
+
+        //Updating index for next step
+        let act_len = if length >= remaining {
+            self.index.set(index + 1);
+            self.offset.set(0);
+            remaining
+        } else {
+            self.offset.set(offset + length);
+            length
+        };
+
+        //returning resulting operation: delete, retain, insert; slices keep the id
+        match next_op.op_type() {
+            OpType::Delete => DeltaOperation::delete(act_len).with_id_of(next_op),
+            OpType::Retain => {
+                DeltaOperation::retain_attr(act_len, next_op.attributes.clone()).with_id_of(next_op)
+            }
+            OpType::Insert => {
+                if next_op.is_string() {
+                    let s = next_op.string_val().unwrap();
+                    let end = s.len() - offset;
+                    return DeltaOperation::insert_attr(
+                        s[end - act_len..end].to_string(),
+                        next_op.attributes.clone(),
+                    )
+                    .with_id_of(next_op);
+                }
+                // Embeds are atomic, also when they span multiple positions
+                assert_eq!(offset, 0);
+                assert_eq!(act_len, remaining, "a length of {len} splits an embed");
+                next_op.clone()
+            }
+        }
+    }
+
+    /// # `position()`
+    ///
+    /// Returns the current `(index, offset)` of the iterator, both counted from the end:
+    /// the number of operations passed, and the offset from the end of the current one.
+    pub fn position(&self) -> (usize, usize) {
+        (self.index.get(), self.offset.get())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -332,6 +627,39 @@ mod tests {
         assert_eq!(nxt, expect);
     }
 
+    #[test]
+    fn delta_iter_try_next_len_passes() {
+        let mut attr = Attributes::default();
+        attr.insert("bold", true);
+
+        let delta = get_delta();
+        let iter = DeltaIterator::new(&delta);
+        assert_eq!(
+            iter.try_next_len(2).unwrap(),
+            DeltaOperation::insert_attr("He", attr)
+        );
+        iter.try_next_len(0).unwrap();
+        iter.try_next_len(0).unwrap();
+        iter.try_next_len(0).unwrap();
+        assert_eq!(iter.try_next_len(0).unwrap(), DeltaOperation::delete(4));
+        assert!(matches!(iter.try_next_len(0), Err(Error::IteratorIsEmpty)));
+    }
+
+    #[test]
+    fn delta_iter_try_next_len_malformed_passes() {
+        let delta = Delta::new(vec![
+            DeltaOperation::insert("A"),
+            DeltaOperation::insert(""),
+            DeltaOperation::retain(0),
+        ]);
+        let iter = DeltaIterator::new(&delta);
+        iter.try_next_len(0).unwrap();
+        assert!(matches!(
+            iter.try_next_len(1),
+            Err(Error::ZeroLengthOperation { index: 1 })
+        ));
+    }
+
     #[test]
     fn delta_iter_rest_1_passes() {
         let mut attr = Attributes::default();
@@ -376,4 +704,130 @@ mod tests {
         let tv: Vec<DeltaOperation> = Vec::new();
         assert_eq!(iter.rest(), tv);
     }
+
+    #[test]
+    fn reverse_iter_peek_type_passes() {
+        let delta = get_delta();
+        let iter = ReverseDeltaIterator::new(&delta);
+        assert_eq!(iter.peek_type(), OpType::Delete);
+        iter.next_len(0);
+        assert_eq!(iter.peek_type(), OpType::Insert);
+        iter.next_len(0);
+        assert_eq!(iter.peek_type(), OpType::Retain);
+        iter.next_len(0);
+        assert_eq!(iter.peek_type(), OpType::Insert);
+        iter.next_len(0);
+        assert!(!iter.has_next());
+        assert_eq!(iter.next_len(0), DeltaOperation::retain(usize::MAX));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn reverse_iter_next_length_passes() {
+        let mut attr = Attributes::default();
+        attr.insert("bold", true);
+
+        let delta = get_delta();
+        let iter = ReverseDeltaIterator::new(&delta);
+        assert_eq!(iter.next_len(3), DeltaOperation::delete(3));
+        assert_eq!(iter.position(), (0, 3));
+        assert_eq!(iter.peek_len(), 1);
+        assert_eq!(iter.next_len(3), DeltaOperation::delete(1));
+        assert_eq!(iter.position(), (1, 0));
+
+        // embeds are returned whole
+        assert_eq!(iter.next_len(1), delta[2]);
+        assert_eq!(iter.next_len(2), DeltaOperation::retain(2));
+        assert_eq!(iter.next_len(0), DeltaOperation::retain(1));
+        assert_eq!(
+            iter.next_len(2),
+            DeltaOperation::insert_attr("lo", attr.clone())
+        );
+        assert_eq!(iter.peek_len(), 3);
+        assert_eq!(iter.next_len(10), DeltaOperation::insert_attr("Hel", attr));
+        assert!(!iter.has_next());
+    }
+
+    #[test]
+    fn reverse_iter_reconstruct_passes() {
+        let delta = get_delta();
+        for step in 1..6 {
+            let iter = ReverseDeltaIterator::new(&delta);
+            let mut pieces = Vec::new();
+            while iter.has_next() {
+                pieces.push(iter.next_len(step));
+            }
+            let mut rebuilt = Delta::default();
+            for op in pieces.into_iter().rev() {
+                rebuilt.push(op);
+            }
+            assert_eq!(rebuilt.get_ops_ref(), delta.get_ops_ref());
+        }
+    }
+
+    #[test]
+    fn delta_iter_next_borrowed_passes() {
+        let delta = get_delta();
+        for step in [0, 1, 2, 3, 7] {
+            let iter = DeltaIterator::new(&delta);
+            let borrowed = DeltaIterator::new(&delta);
+            while iter.has_next() {
+                let view = borrowed.next_borrowed(step).unwrap();
+                assert_eq!(view.op_type(), iter.peek_type());
+                assert_eq!(view.into_op(), iter.next_len(step));
+                assert_eq!(borrowed.position(), iter.position());
+            }
+            assert!(borrowed.next_borrowed(step).is_none());
+        }
+
+        // whole inserts are borrowed, slices own their part of the string
+        let iter = DeltaIterator::new(&delta);
+        let view = iter.next_borrowed(2).unwrap();
+        assert!(matches!(view.value, Some(Cow::Owned(_))));
+        assert_eq!(view.len, 2);
+        assert!(matches!(
+            iter.next_borrowed(0).unwrap().value,
+            Some(Cow::Owned(_))
+        ));
+        assert!(iter.next_borrowed(0).unwrap().value.is_none());
+        assert!(matches!(
+            iter.next_borrowed(0).unwrap().value,
+            Some(Cow::Borrowed(_))
+        ));
+
+        let delta = Delta::new(vec![DeltaOperation::insert("Hello")]);
+        let iter = DeltaIterator::new(&delta);
+        assert!(matches!(
+            iter.next_borrowed(9).unwrap().value,
+            Some(Cow::Borrowed(_))
+        ));
+    }
+
+    #[test]
+    fn delta_iter_embed_length_passes() {
+        let embed_length = EmbedLength::new(|_| 3);
+        let mut delta = Delta::default();
+        delta.insert("ab");
+        delta.insert(1);
+        delta.insert("c");
+
+        let iter = DeltaIterator::new(&delta).with_embed_length(Some(&embed_length));
+        iter.next_len(0);
+        assert_eq!(iter.peek_len(), 3);
+        assert!(matches!(
+            iter.try_next_len(2),
+            Err(Error::SplitEmbed {
+                len: 2,
+                embed_len: 3
+            })
+        ));
+        assert_eq!(iter.position(), (1, 0));
+        assert_eq!(iter.try_next_len(5).unwrap(), DeltaOperation::insert(1));
+        assert_eq!(iter.peek_len(), 1);
+
+        let iter = ReverseDeltaIterator::new(&delta).with_embed_length(Some(&embed_length));
+        iter.next_len(0);
+        assert_eq!(iter.peek_len(), 3);
+        assert_eq!(iter.next_len(3), DeltaOperation::insert(1));
+    }
 }
@@ -21,6 +21,14 @@
 //! If you think of Deltas as the instructions from going from one document to another,
 //! the way Deltas represent a document is by expressing the instructions starting from
 //! an empty document.
+//!
+//! # Thread safety
+//!
+//! The document types `Delta`, `DeltaOperation`, `Attributes`, `AttrVal` and `OpsVal`
+//! are `Send + Sync`, so documents can be shared between threads, e.g. by a server
+//! holding documents for several clients. This is asserted at compile time.
+//! Iterators such as `DeltaIterator` keep their position in a `Cell`, and are `Send` only,
+//! as is `CachedDelta`, which caches its lengths in a `Cell`.
 
 #![deny(clippy::all)]
 #![warn(clippy::pedantic)]
@@ -37,12 +45,29 @@ pub mod types;
 
 //Delta document format definition
 pub mod attributes;
+pub mod cached_delta;
 pub mod delta;
 pub mod operations;
 
 //Operations on the delta document
+pub mod cursor;
 pub mod document;
 mod error;
 pub mod iterator;
 pub mod optransform;
+#[cfg(feature = "test-util")]
+pub mod test_util;
 pub mod utils;
+
+//Compile time check that the document types can be shared between threads,
+//and that `CachedDelta` can at least be moved to another thread
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    fn assert_send<T: Send>() {}
+    assert_send_sync::<delta::Delta>();
+    assert_send_sync::<operations::DeltaOperation>();
+    assert_send_sync::<operations::OpsVal>();
+    assert_send_sync::<attributes::Attributes>();
+    assert_send_sync::<types::attr_val::AttrVal>();
+    assert_send::<cached_delta::CachedDelta>();
+};
@@ -14,15 +14,154 @@ use crate::types::attr_val::AttrVal;
 use crate::types::ops_kind::OpKind;
 use serde::{Deserialize, Serialize};
 use std::borrow::{Borrow, BorrowMut};
+use std::cmp::Ordering;
+use std::fmt;
 #[cfg(test)]
 use std::fmt::{Display, Formatter};
-
+use std::sync::{Arc, RwLock};
 
 /// Operations may have the same structure as an attribute value
 /// As a result the `OpsMap` is identical to the `AttrMap` too,
 pub type OpsVal = AttrVal;
 pub type OpsMap = AttrMap;
 
+/// # `EmbedLength`
+///
+/// Function that determines the length of an embed, i.e. of an insert that is not
+/// a string. Custom embeds, like a table serialized as one object, may span multiple
+/// positions in the document. A delta created with `Delta::with_embed_length()` measures
+/// its embeds with it, in `delta_length()`, `slice()`, `compose()` and friends.
+///
+/// Lengths smaller than 1 are taken as 1. Without a function all embeds have length 1.
+///
+/// Embeds are atomic: composing a retain or delete that covers only part of an embed
+/// returns `Error::SplitEmbed`. Slicing includes an embed when it starts in the slice.
+/// ```
+/// use delta::operations::{DeltaOperation, EmbedLength, OpsMap};
+///
+/// let cells = EmbedLength::new(|embed| {
+///     embed
+///         .map_val()
+///         .ok()
+///         .and_then(|m| m.get("cells"))
+///         .and_then(|c| c.number_val().ok())
+///         .unwrap_or(1)
+/// });
+///
+/// let mut table = OpsMap::default();
+/// table.insert("cells", 3);
+/// let op = DeltaOperation::insert(table);
+/// assert_eq!(op.op_len(), 1);
+/// assert_eq!(op.op_len_with(Some(&cells)), 3);
+/// ```
+#[derive(Clone)]
+pub struct EmbedLength(Arc<dyn Fn(&OpsVal) -> usize + Send + Sync>);
+
+impl EmbedLength {
+    pub fn new<F>(embed_length: F) -> Self
+    where
+        F: Fn(&OpsVal) -> usize + Send + Sync + 'static,
+    {
+        EmbedLength(Arc::new(embed_length))
+    }
+
+    /// # `embed_len()`
+    ///
+    /// Returns the length of `embed`, at least 1.
+    pub fn embed_len(&self, embed: &OpsVal) -> usize {
+        (self.0)(embed).max(1)
+    }
+}
+
+impl fmt::Debug for EmbedLength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EmbedLength")
+    }
+}
+
+/// Reserved attribute key of a retain, holding an instruction to merge into the embed it retains
+pub const EMBED_MERGE_KEY: &str = "$embed";
+
+type EmbedMerge = Box<dyn Fn(&OpsVal, &AttrVal) -> OpsVal + Send + Sync>;
+
+/// Registered merge function for embeds, `None` leaves `$embed` an ordinary attribute
+static EMBED_MERGE: RwLock<Option<EmbedMerge>> = RwLock::new(None);
+
+/// # `set_embed_merge()`
+///
+/// Registers the function that merges an instruction into the value of an embed.
+/// Once registered, `compose()` reads the attribute `EMBED_MERGE_KEY` (`"$embed"`) of a
+/// retain that formats an embed insert as such an instruction: the embed is replaced
+/// by `embed_merge(embed, instruction)`, and the key is dropped from the attributes.
+/// This allows a change to mutate an embed in place, instead of deleting and
+/// reinserting it. `invert()` and `apply_with_inverse()` undo such a merge by
+/// deleting the merged embed and inserting the original one.
+///
+/// Without a registration, `"$embed"` is an attribute like any other. Retains over
+/// text, or over a retain of a change, keep the key as an attribute as well.
+///
+/// The registration is process wide, and replaces an earlier one. The function must
+/// not call `set_embed_merge()` itself.
+/// ```
+/// use delta::attributes::Attributes;
+/// use delta::delta::Delta;
+/// use delta::operations::{set_embed_merge, OpsMap, OpsVal, EMBED_MERGE_KEY};
+/// use delta::optransform::OpTransform;
+///
+/// set_embed_merge(|embed, instruction| {
+///     let (Ok(embed), Ok(instruction)) = (embed.map_val(), instruction.map_val()) else {
+///         return embed.clone();
+///     };
+///     let mut merged = embed.clone();
+///     for (key, value) in instruction.iter() {
+///         merged.insert(key.clone(), value.clone());
+///     }
+///     OpsVal::Map(merged)
+/// });
+///
+/// let mut counter = OpsMap::default();
+/// counter.insert("count", 1);
+/// let mut doc = Delta::default();
+/// doc.insert(counter);
+///
+/// let mut increment = OpsMap::default();
+/// increment.insert("count", 2);
+/// let mut attr = Attributes::default();
+/// attr.insert(EMBED_MERGE_KEY, increment.clone());
+/// let mut change = Delta::default();
+/// change.retain_attr(1, attr);
+///
+/// let mut expected = Delta::default();
+/// expected.insert(increment);
+/// assert_eq!(doc.compose(&change).unwrap(), expected);
+/// ```
+pub fn set_embed_merge<F>(embed_merge: F)
+where
+    F: Fn(&OpsVal, &AttrVal) -> OpsVal + Send + Sync + 'static,
+{
+    if let Ok(mut registered) = EMBED_MERGE.write() {
+        *registered = Some(Box::new(embed_merge));
+    }
+}
+
+/// # `reset_embed_merge()`
+///
+/// Removes the function registered with `set_embed_merge()`, `"$embed"` is an ordinary attribute again.
+pub fn reset_embed_merge() {
+    if let Ok(mut registered) = EMBED_MERGE.write() {
+        *registered = None;
+    }
+}
+
+/// Merges `instruction` into `embed` with the registered function,
+/// `None` when no function is registered.
+pub(crate) fn merge_embed(embed: &OpsVal, instruction: &AttrVal) -> Option<OpsVal> {
+    match EMBED_MERGE.read() {
+        Ok(registered) => registered.as_ref().map(|f| f(embed, instruction)),
+        Err(_) => None,
+    }
+}
+
 #[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
 pub enum OpType {
     Delete,
@@ -30,7 +169,20 @@ pub enum OpType {
     Insert,
 }
 
-/// # DeltaOperation()
+/// # `LengthUnit`
+///
+/// Unit in which `len_in()` measures the length of an operation:
+///  - `Bytes`: UTF-8 bytes, as `op_len()` does
+///  - `Chars`: Unicode scalar values, as Rust's `chars()`
+///  - `Utf16`: UTF-16 code units, as JavaScript and Quill do
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LengthUnit {
+    Bytes,
+    Chars,
+    Utf16,
+}
+
+/// # `DeltaOperation()`
 ///
 /// Operation definition for the delta format in Rust.
 ///
@@ -67,6 +219,8 @@ pub struct DeltaOperation {
     pub(crate) kind: OpKind,
     #[serde(default, skip_serializing_if = "Attributes::is_empty")]
     pub(crate) attributes: Attributes,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<String>,
 }
 
 impl DeltaOperation {
@@ -74,6 +228,7 @@ impl DeltaOperation {
         DeltaOperation {
             kind: OpKind::Insert(value.into()),
             attributes: Attributes::default(),
+            id: None,
         }
     }
 
@@ -81,13 +236,45 @@ impl DeltaOperation {
         DeltaOperation {
             kind: OpKind::Insert(value.into()),
             attributes: attr,
+            id: None,
         }
     }
 
+    /// # `insert_json()`
+    ///
+    /// Builds an insert operation from a JSON value, e.g. to bridge arbitrary JSON embeds.
+    /// The value must be a string, an unsigned integer, or an object.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::operations::DeltaOperation;
+    ///
+    /// let value = serde_json::json!({ "image": "octocat.png" });
+    /// let op = DeltaOperation::insert_json(value, Attributes::default()).unwrap();
+    /// assert_eq!(op.op_len(), 1);
+    /// ```
+    ///
+    /// # Errors
+    /// `SerdeUnknownType` for arrays, booleans and null, `NotAnUnsigned` for other
+    /// numbers than unsigned integers, and `SerdeNestedMap` for too deeply nested objects
+    #[cfg(feature = "json")]
+    pub fn insert_json(value: serde_json::Value, attr: Attributes) -> Result<Self, Error> {
+        if matches!(
+            value,
+            serde_json::Value::Array(_) | serde_json::Value::Bool(_) | serde_json::Value::Null
+        ) {
+            return Err(Error::SerdeUnknownType {
+                tpe: value.to_string(),
+            });
+        }
+        Ok(DeltaOperation::insert_attr(OpsVal::try_from(value)?, attr))
+    }
+
     pub fn retain(value: usize) -> Self {
         DeltaOperation {
             kind: OpKind::Retain(value),
             attributes: Attributes::default(),
+            id: None,
         }
     }
 
@@ -95,6 +282,7 @@ impl DeltaOperation {
         DeltaOperation {
             kind: OpKind::Retain(value),
             attributes: attr,
+            id: None,
         }
     }
 
@@ -103,10 +291,48 @@ impl DeltaOperation {
         DeltaOperation {
             kind: OpKind::Delete(value),
             attributes: Attributes::default(),
+            id: None,
         }
     }
 
-    /// # add_attr()
+    /// # `with_id()`
+    ///
+    /// Returns the operation with a stable, opaque id, to track it across changes,
+    /// e.g. for presence. The id is serialized as `"id"` when present.
+    ///
+    /// Slices of the operation, as taken by `compose()`, `transform()` or `split_at()`,
+    /// keep the id. `push()` only merges neighbours with the same id, and `compose()`
+    /// gives a retained operation the id of `self`'s operation, or else of `other`'s.
+    /// ```
+    /// use delta::delta::Delta;
+    /// use delta::operations::DeltaOperation;
+    ///
+    /// let mut delta = Delta::default();
+    /// delta.push(DeltaOperation::insert("Hello").with_id("a"));
+    /// delta.push(DeltaOperation::insert(" World").with_id("b"));
+    /// assert_eq!(delta.len(), 2);
+    /// assert_eq!(delta[1].id(), Some("b"));
+    /// ```
+    #[must_use]
+    pub fn with_id<S: Into<String>>(mut self, id: S) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// # `id()`
+    ///
+    /// Returns the id set with `with_id()`, if any.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Copies the id of `other`, for operations derived from it
+    pub(crate) fn with_id_of(mut self, other: &DeltaOperation) -> Self {
+        self.id.clone_from(&other.id);
+        self
+    }
+
+    /// # `add_attr()`
     /// set the attribute in a shorthand way
     /// ```rust
     /// use crate::delta::operations::DeltaOperation;
@@ -118,25 +344,54 @@ impl DeltaOperation {
         self.attributes.insert(key.into(), value.into());
     }
 
-    /// # set_attributes()
+    /// # `set_attributes()`
     /// set multiple attributes at once
     pub fn set_attributes<V: Into<Attributes>>(&mut self, values: V) {
         self.attributes = values.into();
     }
 
-    /// # op_len()
+    /// # `op_len()`
     ///
     /// An object is an image or other thing, we treat it as having length 1
     /// In those cases tine insert value is NOT a string.
     pub fn op_len(&self) -> usize {
+        self.op_len_with(None)
+    }
+
+    /// # `op_len_with()`
+    ///
+    /// Returns the length like `op_len()`, but measures an embed with `embed_length`
+    /// when given, see `EmbedLength`.
+    pub fn op_len_with(&self, embed_length: Option<&EmbedLength>) -> usize {
         match self.kind {
             OpKind::Delete(len) | OpKind::Retain(len) => len,
             OpKind::Insert(OpsVal::String(ref val)) => val.len(),
-            OpKind::Insert(_) => 1,
+            OpKind::Insert(ref embed) => embed_length.map_or(1, |f| f.embed_len(embed)),
         }
     }
 
-    /// # op_type()
+    /// # `len_in()`
+    ///
+    /// Returns the length of the operation in the given unit. Only string inserts depend
+    /// on the unit; embeds, retains and deletes have the length as stored, see `op_len()`.
+    ///
+    /// ```
+    /// use delta::operations::{DeltaOperation, LengthUnit};
+    ///
+    /// let op = DeltaOperation::insert("a😀");
+    /// assert_eq!(op.len_in(LengthUnit::Bytes), 5);
+    /// assert_eq!(op.len_in(LengthUnit::Chars), 2);
+    /// assert_eq!(op.len_in(LengthUnit::Utf16), 3);
+    /// ```
+    pub fn len_in(&self, unit: LengthUnit) -> usize {
+        match (&self.kind, unit) {
+            (OpKind::Insert(OpsVal::String(val)), LengthUnit::Chars) => val.chars().count(),
+            (OpKind::Insert(OpsVal::String(val)), LengthUnit::Utf16) => val.encode_utf16().count(),
+            _ => self.op_len(),
+        }
+    }
+
+    /// # `op_type()`
     ///
     /// set the attribute in a shorthand way
     pub fn op_type(&self) -> OpType {
@@ -147,7 +402,7 @@ impl DeltaOperation {
         }
     }
 
-    /// # insert_value()
+    /// # `insert_value()`
     ///
     /// returns the serde value of the insert operation
     ///
@@ -159,21 +414,32 @@ impl DeltaOperation {
         panic!("Hey no value found in this operation");
     }
 
-    /// # set_op_kind()
+    /// # `integer_embed()`
+    ///
+    /// Returns the value of an integer embed, such as `insert(1)`.
+    /// Returns `None` for string and map inserts, and for retain and delete operations.
+    pub fn integer_embed(&self) -> Option<usize> {
+        if let OpKind::Insert(OpsVal::Number(val)) = &self.kind {
+            return Some(*val);
+        }
+        None
+    }
+
+    /// # `set_op_kind()`
     ///
     /// Sets the operation kind for this delta operation.
     pub fn set_op_kind(&mut self, s: OpKind) {
         *self.kind.borrow_mut() = s;
     }
 
-    /// remove_attribute()
+    /// `remove_attribute()`
     ///
     /// Removes the attribute that is associated with the given key value.
     pub fn remove_attribute(&mut self, key: &str) {
         self.attributes.remove(key);
     }
 
-    /// # string_val()
+    /// # `string_val()`
     ///
     /// a shorthand way to get the string value out of the serde value
     pub(crate) fn string_val(&self) -> Result<&str, Error> {
@@ -186,11 +452,11 @@ impl DeltaOperation {
     }
 
     pub(crate) fn is_string(&self) -> bool {
-        return self.insert_value().is_string();
+        self.insert_value().is_string()
     }
 
     pub(crate) fn is_object(&self) -> bool {
-        return !self.insert_value().is_string();
+        !self.insert_value().is_string()
     }
 
     pub fn get_attributes(&self) -> &Attributes {
@@ -226,7 +492,7 @@ impl DeltaOperation {
         false
     }
 
-    /// # is_equal()
+    /// # `is_equal()`
     ///
     /// Two delta operations are considered equal if both the operation, and the attributes have the same values.
     /// That means that:
@@ -240,11 +506,39 @@ impl DeltaOperation {
         self.is_same_operation(other) && self.is_same_attributes(other)
     }
 
-    /// # is_empty()
+    /// # `is_empty()`
     /// Returns true when the operation has zero length
     pub fn is_empty(&self) -> bool {
         self.op_len() == 0
     }
+
+    /// # `cmp_by_type_then_len()`
+    ///
+    /// Compares operations by type first, in the order insert, retain, delete,
+    /// and by length for operations of the same type. Values and attributes are
+    /// not compared, so operations that differ can still compare `Equal`.
+    ///
+    /// There is no semantic ordering of operations, hence no `Ord` implementation;
+    /// this comparator is meant for sorting and grouping, e.g. in analytics.
+    /// ```
+    /// use delta::operations::DeltaOperation;
+    ///
+    /// let mut ops = vec![DeltaOperation::delete(1), DeltaOperation::insert("ab")];
+    /// ops.sort_by(DeltaOperation::cmp_by_type_then_len);
+    /// assert_eq!(ops[0], DeltaOperation::insert("ab"));
+    /// ```
+    pub fn cmp_by_type_then_len(&self, other: &Self) -> Ordering {
+        fn rank(op_type: &OpType) -> u8 {
+            match op_type {
+                OpType::Insert => 0,
+                OpType::Retain => 1,
+                OpType::Delete => 2,
+            }
+        }
+        rank(&self.op_type())
+            .cmp(&rank(&other.op_type()))
+            .then_with(|| self.op_len().cmp(&other.op_len()))
+    }
 }
 
 //Note display is one form is serialization, but we can not read it back.
@@ -270,11 +564,11 @@ impl Display for DeltaOperation {
             }
             OpKind::Insert(val) => {
                 if self.attributes.is_empty() {
-                    write!(f, r##"Operation -> Insert[{val}]"##)
+                    write!(f, r"Operation -> Insert[{val}]")
                 } else {
                     write!(
                         f,
-                        r##"Operation -> Insert[{}], {}"##,
+                        r"Operation -> Insert[{}], {}",
                         val,
                         display_fmt(&self.attributes)
                     )
@@ -286,7 +580,7 @@ impl Display for DeltaOperation {
 
 #[cfg(test)]
 mod test {
-    use crate::operations::{DeltaOperation, OpType, OpsMap, OpsVal};
+    use crate::operations::{DeltaOperation, LengthUnit, OpType, OpsMap, OpsVal};
     use crate::types::attr_val::AttrVal;
     use crate::types::ops_kind::OpKind;
 
@@ -342,6 +636,39 @@ mod test {
         assert_eq!(op.op_len(), 3);
     }
 
+    #[test]
+    fn len_in_passes() {
+        // 'é' takes 2 bytes, the emoji 4 bytes and 2 UTF-16 code units
+        let op = insert("Hé 😀!");
+        assert_eq!(op.len_in(LengthUnit::Bytes), 9);
+        assert_eq!(op.len_in(LengthUnit::Bytes), op.op_len());
+        assert_eq!(op.len_in(LengthUnit::Chars), 5);
+        assert_eq!(op.len_in(LengthUnit::Utf16), 6);
+
+        let mut o = OpsMap::default();
+        o.insert("image", "octocat.png");
+        for op in [insert(o), retain(3), delete(3)] {
+            for unit in [LengthUnit::Bytes, LengthUnit::Chars, LengthUnit::Utf16] {
+                assert_eq!(op.len_in(unit), op.op_len());
+            }
+        }
+    }
+
+    #[test]
+    fn integer_embed_passes() {
+        let mut op = insert(1);
+        op.add_attr("font", "green");
+        assert_eq!(op.integer_embed(), Some(1));
+        assert_eq!(insert(42).integer_embed(), Some(42));
+
+        let mut o = OpsMap::default();
+        o.insert("image", "octocat.png");
+        assert_eq!(insert(o).integer_embed(), None);
+        assert_eq!(insert("1").integer_embed(), None);
+        assert_eq!(retain(1).integer_embed(), None);
+        assert_eq!(delete(1).integer_embed(), None);
+    }
+
     #[test]
     fn attr_add_passes() {
         let mut op1 = insert("Hallo");
@@ -395,4 +722,44 @@ mod test {
         assert_eq!(op1.attributes.len(), 1);
         assert_eq!(op1.op_len(), 5);
     }
+
+    #[test]
+    fn cmp_by_type_then_len_passes() {
+        let mut bold = insert("ccc");
+        bold.add_attr("bold", true);
+
+        let mut ops = [
+            delete(2),
+            retain(3),
+            insert("ab"),
+            delete(1),
+            bold,
+            retain(1),
+            insert(1),
+        ];
+        ops.sort_by(DeltaOperation::cmp_by_type_then_len);
+
+        let types: Vec<OpType> = ops.iter().map(DeltaOperation::op_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                OpType::Insert,
+                OpType::Insert,
+                OpType::Insert,
+                OpType::Retain,
+                OpType::Retain,
+                OpType::Delete,
+                OpType::Delete
+            ]
+        );
+        let lens: Vec<usize> = ops.iter().map(DeltaOperation::op_len).collect();
+        assert_eq!(lens, vec![1, 2, 3, 1, 3, 1, 2]);
+        assert_eq!(ops[2].get_attributes().len(), 1);
+
+        // only type and length are compared
+        assert_eq!(
+            insert("ab").cmp_by_type_then_len(&insert("cd")),
+            std::cmp::Ordering::Equal
+        );
+    }
 }
@@ -5,23 +5,119 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use crate::attributes::{compose, transform};
+use crate::attributes::{compose, transform, Attributes};
 use crate::delta::Delta;
 use crate::error::Error;
-use crate::iterator::DeltaIterator;
-use crate::operations::{DeltaOperation, OpType};
+use crate::iterator::{DeltaIterator, OpView};
+use crate::operations::{merge_embed, DeltaOperation, OpType, EMBED_MERGE_KEY};
+use std::borrow::Cow;
+
+/// A `[start, end)` range of positions in a document.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
 
 pub trait OpTransform {
-    /// # compose()
+    /// # `compose()`
     ///
     /// Returns a Delta that is equivalent to applying the operations of own Delta,
     /// followed by another Delta.
     ///
     /// `other` - Delta to compose
+    ///
+    /// Attributes are composed without comparing them to the values already present.
+    /// A retain setting an attribute to the value it already has, is kept when composing
+    /// two changes, so the intent of both changes stays on record, e.g. for audit logs.
     /// # Errors
     fn compose(&self, other: &Delta) -> Result<Delta, Error>;
 
-    /// # transform()
+    /// # `compose_with_flags()`
+    ///
+    /// Composes like `compose()`, with control over null attribute values.
+    ///
+    /// By default, like in Quill, an attribute set to null by `other` is kept when it
+    /// formats a retain of `self`, since the removal still has to be applied to the
+    /// document, but is dropped when it formats an insert of `self`, since the inserted
+    /// text has nothing to remove. `compose()` equals `compose_with_flags(other, false)`.
+    ///
+    /// With `keep_null_on_insert` the null is kept on inserts too, so an explicit
+    /// removal survives a round-trip.
+    ///
+    /// ```
+    /// use delta::attributes::Attributes;
+    /// use delta::delta::Delta;
+    /// use delta::optransform::OpTransform;
+    /// use delta::types::attr_val::AttrVal;
+    ///
+    /// let mut a = Delta::default();
+    /// a.insert("A");
+    ///
+    /// let mut remove_bold = Attributes::default();
+    /// remove_bold.insert("bold", AttrVal::Null);
+    /// let mut b = Delta::default();
+    /// b.retain_attr(1, remove_bold.clone());
+    ///
+    /// let mut expected = Delta::default();
+    /// expected.insert_attr("A", remove_bold);
+    /// assert_eq!(a.compose_with_flags(&b, true).unwrap(), expected);
+    /// ```
+    /// # Errors
+    fn compose_with_flags(&self, other: &Delta, keep_null_on_insert: bool) -> Result<Delta, Error>;
+
+    /// # `compose_tracked()`
+    ///
+    /// Composes like `compose()`, and additionally returns the spans of the result
+    /// that were newly inserted by `other`, as opposed to carried over from `self`.
+    ///
+    /// Span positions count the inserts and retains of the composed delta, i.e. they
+    /// are positions in the document the composed delta produces. Adjacent spans are
+    /// merged, and the spans are in ascending order.
+    ///
+    /// ```
+    /// use delta::delta::Delta;
+    /// use delta::optransform::{OpTransform, Span};
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Hello");
+    ///
+    /// let mut change = Delta::default();
+    /// change.retain(5);
+    /// change.insert(" World");
+    ///
+    /// let (_, spans) = doc.compose_tracked(&change).unwrap();
+    /// assert_eq!(spans, vec![Span { start: 5, end: 11 }]);
+    /// ```
+    /// # Errors
+    fn compose_tracked(&self, other: &Delta) -> Result<(Delta, Vec<Span>), Error>;
+
+    /// # `compose_repeat()`
+    ///
+    /// Returns the Delta that is equivalent to composing `change` on own Delta
+    /// `times` times. With `times == 0` own Delta is returned unchanged.
+    ///
+    /// Since compose is associative, the repeated change is built by repeated
+    /// squaring, which takes O(log(times)) compose steps in stead of `times`.
+    ///
+    /// ```
+    /// use delta::delta::Delta;
+    /// use delta::optransform::OpTransform;
+    ///
+    /// let mut doc = Delta::default();
+    /// doc.insert("Ha");
+    ///
+    /// let mut change = Delta::default();
+    /// change.insert("Ha");
+    ///
+    /// let mut expected = Delta::default();
+    /// expected.insert("HaHaHaHa");
+    /// assert_eq!(doc.compose_repeat(&change, 3).unwrap(), expected);
+    /// ```
+    /// # Errors
+    fn compose_repeat(&self, change: &Delta, times: usize) -> Result<Delta, Error>;
+
+    /// # `transform()`
     ///
     /// Transform given Delta against own operations.
     ///
@@ -32,10 +128,31 @@ pub trait OpTransform {
     /// If priority is  `true`, then `this` takes priority over `other`, that is, its
     /// actions are considered to happened "first".
     ///
+    /// Both deltas are taken as changes. A document delta, only containing inserts,
+    /// is a valid change too: it inserts the whole document at position 0, so there
+    /// is no input to reject. Transforming against a document shifts `other` behind
+    /// the inserted content, where an insert of `other` at position 0 only goes in
+    /// front of it when `priority` is `false`.
+    ///
     /// # Errors
     fn transform(&self, other: &Delta, priority: bool) -> Result<Delta, Error>;
 
-    /// # transform_position()
+    /// # `transform_with()`
+    ///
+    /// Transforms like `transform()`, but the attributes of overlapping retains are
+    /// resolved by `f` in stead of by `attributes::transform()`. This allows domain
+    /// specific conflict rules, e.g. numeric attributes taking the maximum.
+    ///
+    /// `f` gets our attributes, their attributes and the priority, and returns the
+    /// attributes that their retain keeps. `transform()` equals
+    /// `transform_with(other, priority, attributes::transform)`.
+    ///
+    /// # Errors
+    fn transform_with<F>(&self, other: &Delta, priority: bool, f: F) -> Result<Delta, Error>
+    where
+        F: Fn(&Attributes, &Attributes, bool) -> Attributes;
+
+    /// # `transform_position()`
     ///
     /// Transform an index against the quill delta.
     ///
@@ -45,98 +162,121 @@ pub trait OpTransform {
     ///
     /// # Errors
     fn transform_position(&self, index: usize, priority: bool) -> Result<usize, Error>;
+
+    /// # `transform_anchor()`
+    ///
+    /// Transforms a range `(start, end)` of the document against this change, like
+    /// `transform_position()` does for a single index, for annotations anchored to a
+    /// range that is stored separately from the content, such as comments.
+    ///
+    /// Both ends are transformed with the same `priority`, as Quill does for a selection.
+    /// Text inserted inside the range extends it, deleted text shrinks it, and when the
+    /// whole range is deleted it collapses to `start == end`. With `priority` text
+    /// inserted exactly at `start` is taken in to the range and text inserted at `end`
+    /// is not, without `priority` it is the other way around.
+    ///
+    /// ```
+    /// use delta::delta::Delta;
+    /// use delta::optransform::OpTransform;
+    ///
+    /// // "Hello World" with the comment anchored to "World"
+    /// let mut change = Delta::default();
+    /// change.insert("Oh, ");
+    /// change.retain(6);
+    /// change.delete(1);
+    /// assert_eq!(change.transform_anchor((6, 11), false).unwrap(), (10, 14));
+    /// ```
+    ///
+    /// # Errors
+    fn transform_anchor(
+        &self,
+        anchor: (usize, usize),
+        priority: bool,
+    ) -> Result<(usize, usize), Error>;
+
+    /// # `transform_position_clamped()`
+    ///
+    /// Transform an index against the quill delta, like `transform_position()`,
+    /// but the result is clamped to the length of the transformed document.
+    ///
+    /// A change delta does not know the length of the document it applies to,
+    /// so it must be given as `base_length`. An `index` past the end of the base
+    /// document is treated as pointing to its end.
+    ///
+    /// # Errors
+    fn transform_position_clamped(
+        &self,
+        index: usize,
+        priority: bool,
+        base_length: usize,
+    ) -> Result<usize, Error>;
+
+    /// # `conflicts_with()`
+    ///
+    /// Returns true when this change delta and a concurrent change delta `other`,
+    /// both made against the same base document, touch overlapping regions.
+    ///
+    /// Deletes and formatting retains cover a range of the base document, an insert
+    /// covers the position where it is inserted. Ranges that only touch at their
+    /// boundaries do not conflict, but two inserts at the same position do.
+    ///
+    /// This is meant to warn users; `transform()` converges regardless.
+    fn conflicts_with(&self, other: &Delta) -> bool;
 }
 
 impl OpTransform for Delta {
     fn compose(&self, other: &Delta) -> Result<Delta, Error> {
-        let this_iter = &DeltaIterator::new(self);
-        let other_iter = &DeltaIterator::new(other);
-        let mut delta = Delta::default();
-
-        //Define closure to handle stuff on the first retain sequence
-        let mut handle_retain = |first_other: &DeltaOperation| {
-            let mut first_left = first_other.op_len(); //we know here it is a "Retain"
-            while this_iter.peek_type() == OpType::Insert && this_iter.peek_len() < first_left {
-                first_left -= this_iter.peek_len();
-                let t = this_iter.next_len(usize::MAX);
-                delta.push(t);
-            }
-            if first_other.op_len() - first_left > 0 {
-                other_iter.next_len(first_other.op_len() - first_left);
-            }
-        };
-
-        let first_other = other_iter.peek();
-        if let Some(val) = first_other {
-            if val.op_type() == OpType::Retain {
-                handle_retain(val);
-            }
-        }
+        Ok(compose_tracking(self, other, false)?.0)
+    }
 
-        while this_iter.has_next() || other_iter.has_next() {
-            if other_iter.peek_type() == OpType::Insert {
-                delta.push(other_iter.next_len(0));
-            } else if this_iter.peek_type() == OpType::Delete {
-                delta.push(this_iter.next_len(0));
-            } else {
-                let v = [this_iter.peek_len(), other_iter.peek_len()];
-                let Some(val) = v.iter().min() else {
-                    return Err(Error::EmptyVectorMinOp);
-                };
-                let l = *val;
-                let this_op = this_iter.next_len(l);
-                let other_op = other_iter.next_len(l);
-                if other_op.op_type() == OpType::Retain {
-                    let mut new_op: DeltaOperation = if this_op.op_type() == OpType::Retain {
-                        DeltaOperation::retain(l)
-                    } else {
-                        DeltaOperation::insert(this_op.insert_value().clone())
-                    };
-                    // Preserve null when composing with a retain, otherwise remove it for inserts
-                    let attr = compose(
-                        &this_op.attributes,
-                        &other_op.attributes,
-                        this_op.op_type() == OpType::Retain,
-                    );
-                    new_op.set_attributes(attr);
-                    delta.push(new_op);
-                    // Optimization if rest of other is just retain
-                    if !other_iter.has_next() {
-                        let Some(d_last) = delta.last() else {
-                            return Err(Error::EmptyVectorLastOp);
-                        };
-                        let Some(s_last) = self.last() else {
-                            return Err(Error::EmptyVectorLastOp);
-                        };
-                        if d_last.is_equal(s_last) {
-                            let rest = this_iter.rest();
-                            return Ok(delta.append_delta_operation(rest).chop().to_owned());
-                        }
-                    }
+    fn compose_tracked(&self, other: &Delta) -> Result<(Delta, Vec<Span>), Error> {
+        compose_tracking(self, other, false)
+    }
 
-                    // Other op should be delete, we could be an insert or retain
-                    // Insert + delete cancels out
-                } else if other_op.op_type() == OpType::Delete
-                    && this_op.op_type() == OpType::Retain
-                {
-                    delta.push(other_op.clone());
-                }
+    fn compose_repeat(&self, change: &Delta, times: usize) -> Result<Delta, Error> {
+        let mut result = self.clone();
+        let mut power = change.clone();
+        let mut n = times;
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.compose(&power)?;
+            }
+            n >>= 1;
+            if n > 0 {
+                power = power.compose(&power)?;
             }
         }
-        Ok(delta.chop().to_owned())
+        Ok(result)
+    }
+
+    fn compose_with_flags(&self, other: &Delta, keep_null_on_insert: bool) -> Result<Delta, Error> {
+        Ok(compose_tracking(self, other, keep_null_on_insert)?.0)
     }
 
     fn transform(&self, other: &Delta, priority: bool) -> Result<Delta, Error> {
-        let this_iter = DeltaIterator::new(self);
-        let other_iter = DeltaIterator::new(other);
-        let mut delta = Delta::default();
+        self.transform_with(other, priority, transform)
+    }
+
+    fn transform_with<F>(&self, other: &Delta, priority: bool, f: F) -> Result<Delta, Error>
+    where
+        F: Fn(&Attributes, &Attributes, bool) -> Attributes,
+    {
+        let embed_length = self.embed_length().or(other.embed_length());
+        let this_iter = DeltaIterator::new(self).with_embed_length(embed_length);
+        let other_iter = DeltaIterator::new(other).with_embed_length(embed_length);
+        let mut delta = self.empty_like_either(other);
         while this_iter.has_next() || other_iter.has_next() {
+            skip_empty(&this_iter);
+            skip_empty(&other_iter);
+            if !this_iter.has_next() && !other_iter.has_next() {
+                break;
+            }
             if this_iter.peek_type() == OpType::Insert
                 && (priority || other_iter.peek_type() != OpType::Insert)
             {
-                delta.retain(this_iter.next_len(0).op_len());
+                delta.retain(this_iter.try_next_len(0)?.op_len_with(embed_length));
             } else if other_iter.peek_type() == OpType::Insert {
-                delta.push(other_iter.next_len(0));
+                delta.push(other_iter.try_next_len(0)?);
             } else {
                 let v = [this_iter.peek_len(), other_iter.peek_len()];
                 let Some(val) = v.iter().min() else {
@@ -146,15 +286,15 @@ impl OpTransform for Delta {
                 let this_op = this_iter.next_len(l);
                 let other_op = other_iter.next_len(l);
                 if this_op.op_type() == OpType::Delete {
-                    continue;
+                    // Our delete either makes their delete redundant or removes their retain
                 } else if other_op.op_type() == OpType::Delete {
+                    // Deletes split by our operations are coalesced again by push(),
+                    // the iterator never puts attributes on a delete
                     delta.push(other_op.clone());
                 } else {
                     // We retain either their retain or insert
-                    delta.retain_attr(
-                        l,
-                        transform(&this_op.attributes, &other_op.attributes, priority),
-                    );
+                    let attr = f(&this_op.attributes, &other_op.attributes, priority);
+                    delta.push(DeltaOperation::retain_attr(l, attr).with_id_of(&other_op));
                 }
             }
         }
@@ -163,7 +303,7 @@ impl OpTransform for Delta {
     }
 
     fn transform_position(&self, mut index: usize, priority: bool) -> Result<usize, Error> {
-        let this_iter = DeltaIterator::new(self);
+        let this_iter = DeltaIterator::new(self).with_embed_length(self.embed_length());
         let mut offset: usize = 0;
         while this_iter.has_next() && offset <= index {
             let l = this_iter.peek_len();
@@ -183,4 +323,237 @@ impl OpTransform for Delta {
         }
         Ok(index)
     }
+
+    fn transform_anchor(
+        &self,
+        anchor: (usize, usize),
+        priority: bool,
+    ) -> Result<(usize, usize), Error> {
+        let start = self.transform_position(anchor.0, priority)?;
+        let end = self.transform_position(anchor.1, priority)?;
+        Ok((start, end))
+    }
+
+    fn transform_position_clamped(
+        &self,
+        index: usize,
+        priority: bool,
+        base_length: usize,
+    ) -> Result<usize, Error> {
+        let mut length = base_length;
+        for op in self.iter() {
+            match op.op_type() {
+                OpType::Insert => length += self.len_of(op),
+                OpType::Delete => length = length.saturating_sub(op.op_len()),
+                OpType::Retain => {}
+            }
+        }
+        let index = self.transform_position(usize::min(index, base_length), priority)?;
+        Ok(usize::min(index, length))
+    }
+
+    fn conflicts_with(&self, other: &Delta) -> bool {
+        let ours = edit_ranges(self);
+        let theirs = edit_ranges(other);
+        ours.iter()
+            .any(|a| theirs.iter().any(|b| ranges_overlap(*a, *b)))
+    }
+}
+
+/// Private method
+/// Returns the retain or insert `this_op` of length `len` retained by `other_op`, with the
+/// attributes of both composed. `None` stands for the implicit retain after an exhausted
+/// delta. A retain over an embed may carry an instruction to merge into it, which is
+/// consumed when an embed merge function is registered.
+/// The result keeps the id of `this_op`, or else of `other_op`.
+fn retained_op(
+    this_op: Option<OpView>,
+    other_op: &OpView,
+    len: usize,
+    keep_null_on_insert: bool,
+) -> DeltaOperation {
+    let id_source = match &this_op {
+        Some(this) if this.op.id.is_some() => this.op,
+        _ => other_op.op,
+    };
+    let no_attributes = Attributes::default();
+    let this_attr = this_op.as_ref().map_or(&no_attributes, OpView::attributes);
+    let Some(value) = this_op.and_then(|this| this.value) else {
+        // Preserve null when composing with a retain
+        let attr = compose(this_attr, other_op.attributes(), true);
+        return DeltaOperation::retain_attr(len, attr).with_id_of(id_source);
+    };
+
+    let mut other_attr = Cow::Borrowed(other_op.attributes());
+    let merged = match other_attr.get(EMBED_MERGE_KEY) {
+        Some(instruction) if !value.is_string() => merge_embed(&value, instruction),
+        _ => None,
+    };
+    if merged.is_some() {
+        other_attr.to_mut().remove(EMBED_MERGE_KEY);
+    }
+    // Remove null for inserts, unless asked to keep it
+    let attr = compose(this_attr, &other_attr, keep_null_on_insert);
+    let value = merged.unwrap_or_else(|| value.into_owned());
+    DeltaOperation::insert_attr(value, attr).with_id_of(id_source)
+}
+
+/// Private method
+/// Implements `compose()`, and tracks the spans of the result inserted by `other`.
+/// `position` is the length of the document produced by the ops pushed so far,
+/// deletes do not add to it.
+fn compose_tracking(
+    this: &Delta,
+    other: &Delta,
+    keep_null_on_insert: bool,
+) -> Result<(Delta, Vec<Span>), Error> {
+    let embed_length = this.embed_length().or(other.embed_length());
+    let this_iter = &DeltaIterator::new(this).with_embed_length(embed_length);
+    let other_iter = &DeltaIterator::new(other).with_embed_length(embed_length);
+    let mut delta = this.empty_like_either(other);
+    let mut spans: Vec<Span> = Vec::new();
+    let mut position: usize = 0;
+
+    //Define closure to handle stuff on the first retain sequence
+    let mut handle_retain = |first_other: &DeltaOperation| -> Result<(), Error> {
+        let mut first_left = first_other.op_len(); //we know here it is a "Retain"
+        skip_empty(this_iter);
+        while this_iter.peek_type() == OpType::Insert && this_iter.peek_len() < first_left {
+            first_left -= this_iter.peek_len();
+            let t = this_iter.try_next_len(usize::MAX)?;
+            position += t.op_len_with(embed_length);
+            delta.push(t);
+            skip_empty(this_iter);
+        }
+        if first_other.op_len() - first_left > 0 {
+            other_iter.try_next_len(first_other.op_len() - first_left)?;
+        }
+        Ok(())
+    };
+
+    skip_empty(other_iter);
+    let first_other = other_iter.peek();
+    if let Some(val) = first_other {
+        if val.op_type() == OpType::Retain && val.get_attributes().is_empty() {
+            handle_retain(val)?;
+        }
+    }
+
+    while this_iter.has_next() || other_iter.has_next() {
+        skip_empty(this_iter);
+        skip_empty(other_iter);
+        if !this_iter.has_next() && !other_iter.has_next() {
+            break;
+        }
+        // Optimization if other is exhausted: composing with its implicit retain
+        // leaves the rest of this unchanged
+        if !other_iter.has_next() {
+            delta.append_delta_operation(this_iter.rest());
+            break;
+        }
+        if other_iter.peek_type() == OpType::Insert {
+            let op = other_iter.try_next_len(0)?;
+            let end = position + op.op_len_with(embed_length);
+            match spans.last_mut() {
+                Some(last) if last.end == position => last.end = end,
+                _ => spans.push(Span {
+                    start: position,
+                    end,
+                }),
+            }
+            position = end;
+            delta.push(op);
+        } else if this_iter.peek_type() == OpType::Delete {
+            delta.push(this_iter.try_next_len(0)?);
+        } else {
+            let v = [this_iter.peek_len(), other_iter.peek_len()];
+            let Some(val) = v.iter().min() else {
+                return Err(Error::EmptyVectorMinOp);
+            };
+            let l = *val;
+            // Embeds are atomic, a retain or delete has to cover them as a whole
+            this_iter.check_embed_split(l)?;
+            // Borrowed views, so attributes and whole inserts are not copied in between
+            let this_op = this_iter.next_borrowed(l);
+            let Some(other_op) = other_iter.next_borrowed(l) else {
+                return Err(Error::IteratorIsEmpty);
+            };
+            // An exhausted this behaves as an implicit retain
+            let this_type = this_op.as_ref().map_or(OpType::Retain, OpView::op_type);
+            if other_op.op_type() == OpType::Retain {
+                position += l;
+                delta.push(retained_op(this_op, &other_op, l, keep_null_on_insert));
+                // Optimization if rest of other is just retain
+                if !other_iter.has_next() {
+                    let Some(d_last) = delta.last() else {
+                        return Err(Error::EmptyVectorLastOp);
+                    };
+                    let Some(s_last) = this.last() else {
+                        return Err(Error::EmptyVectorLastOp);
+                    };
+                    if d_last.is_equal(s_last) {
+                        let rest = this_iter.rest();
+                        delta.append_delta_operation(rest).chop();
+                        return Ok((delta, spans));
+                    }
+                }
+
+                // Other op should be delete, we could be an insert or retain
+                // Insert + delete cancels out
+            } else if other_op.op_type() == OpType::Delete && this_type == OpType::Retain {
+                delta.push(other_op.into_op());
+            }
+        }
+    }
+    delta.chop();
+    Ok((delta, spans))
+}
+
+/// Private method
+/// Advances the iterator past zero length operations, such as an empty insert.
+/// The builders never create them, but a delta constructed from a vector of
+/// operations may contain them, and their `peek_len()` of 0 breaks the `min()` logic.
+fn skip_empty(iter: &DeltaIterator) {
+    while iter.has_next() && iter.peek_len() == 0 {
+        iter.next();
+    }
+}
+
+/// Private method
+/// Collects the `[start, end)` ranges in the base document that a change delta edits.
+/// An insert results in an empty range at the insert position.
+fn edit_ranges(delta: &Delta) -> Vec<(usize, usize)> {
+    let iter = DeltaIterator::new(delta).with_embed_length(delta.embed_length());
+    let mut ranges = Vec::new();
+    let mut index = 0;
+    while iter.has_next() {
+        let op = iter.next_len(0);
+        let len = delta.len_of(&op);
+        match op.op_type() {
+            OpType::Insert => ranges.push((index, index)),
+            OpType::Delete => {
+                ranges.push((index, index + len));
+                index += len;
+            }
+            OpType::Retain => {
+                if !op.get_attributes().is_empty() {
+                    ranges.push((index, index + len));
+                }
+                index += len;
+            }
+        }
+    }
+    ranges
+}
+
+/// Private method
+/// Two ranges overlap when they share a position; an empty range (insert)
+/// overlaps when it lies strictly inside the other range, or on the same position.
+fn ranges_overlap(a: (usize, usize), b: (usize, usize)) -> bool {
+    match (a.0 == a.1, b.0 == b.1) {
+        (true, true) => a.0 == b.0,
+        (true, false) => b.0 < a.0 && a.0 < b.1,
+        (false, true) => a.0 < b.0 && b.0 < a.1,
+        (false, false) => a.0 < b.1 && b.0 < a.1,
+    }
 }
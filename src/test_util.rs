@@ -0,0 +1,117 @@
+// Copyright 2024 quill-delta-rs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Generators for realistic deltas, used by the benchmarks.
+//!
+//! Available with the `test-util` feature, so applications can benchmark
+//! with the same input. The output is deterministic for a given length.
+
+use crate::attributes::Attributes;
+use crate::delta::Delta;
+use crate::operations::OpsMap;
+
+const WORDS: [&str; 12] = [
+    "lorem",
+    "ipsum",
+    "dolor",
+    "sit",
+    "amet",
+    "delta",
+    "quill",
+    "rust",
+    "editor",
+    "text",
+    "compose",
+    "transform",
+];
+
+/// Small linear congruential generator, good enough to vary the generated content.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, max: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        #[allow(clippy::cast_possible_truncation)]
+        let val = (self.0 >> 33) as usize;
+        val % max
+    }
+}
+
+/// # `generate_document()`
+///
+/// Generates a document delta of about `len` characters. It contains plain and
+/// formatted words, lines ending with block formatted line breaks, and image embeds.
+pub fn generate_document(len: usize) -> Delta {
+    let mut rnd = Lcg(len as u64);
+    let mut bold = Attributes::default();
+    bold.insert("bold", true);
+    let mut italic = Attributes::default();
+    italic.insert("italic", true);
+    let mut header = Attributes::default();
+    header.insert("header", 1);
+    let mut image = OpsMap::default();
+    image.insert("image", "octocat.png");
+
+    let mut doc = Delta::default();
+    let mut length = 0;
+    while length < len {
+        let word = WORDS[rnd.next(WORDS.len())];
+        match rnd.next(10) {
+            0 => doc.insert_attr(word, bold.clone()),
+            1 => doc.insert_attr(word, italic.clone()),
+            _ => doc.insert(word),
+        }
+        length += word.len();
+        match rnd.next(12) {
+            0 => doc.insert_attr("\n", header.clone()),
+            1 => doc.insert("\n"),
+            2 => doc.insert(image.clone()),
+            _ => doc.insert(" "),
+        }
+        length += 1;
+    }
+    doc.insert("\n");
+    doc
+}
+
+/// # `generate_change()`
+///
+/// Generates a change delta with `edits` edits, spread over a document of length `doc_len`.
+/// The edits are inserts, deletes and formatting retains.
+pub fn generate_change(doc_len: usize, edits: usize) -> Delta {
+    let mut rnd = Lcg((doc_len + edits) as u64);
+    let mut bold = Attributes::default();
+    bold.insert("bold", true);
+
+    let mut change = Delta::default();
+    let step = usize::max(doc_len / usize::max(edits, 1), 2);
+    let mut index = 0;
+    for _ in 0..edits {
+        let skip = rnd.next(step / 2 + 1);
+        if index + skip + 1 >= doc_len {
+            break;
+        }
+        change.retain(skip);
+        index += skip;
+        let len = usize::min(rnd.next(step / 2) + 1, doc_len - index - 1);
+        match rnd.next(3) {
+            0 => change.insert(WORDS[rnd.next(WORDS.len())]),
+            1 => {
+                change.delete(len);
+                index += len;
+            }
+            _ => {
+                change.retain_attr(len, bold.clone());
+                index += len;
+            }
+        }
+    }
+    change
+}
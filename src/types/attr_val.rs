@@ -26,7 +26,35 @@ pub enum AttrVal {
     Null,
 }
 
+/// # `AttrValKind`
+///
+/// The type of an `AttrVal`, without its value, see `AttrVal::kind()`.
+///
+/// There is no kind for floats or arrays: `AttrVal` cannot hold them, and they are
+/// rejected when reading attributes from JSON.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AttrValKind {
+    String,
+    Number,
+    Bool,
+    Map,
+    Null,
+}
+
 impl AttrVal {
+    /// # `kind()`
+    ///
+    /// Returns the type of the value, to match on instead of chaining the `is_*()` tests.
+    pub fn kind(&self) -> AttrValKind {
+        match self {
+            AttrVal::String(_) => AttrValKind::String,
+            AttrVal::Number(_) => AttrValKind::Number,
+            AttrVal::Bool(_) => AttrValKind::Bool,
+            AttrVal::Map(_) => AttrValKind::Map,
+            AttrVal::Null => AttrValKind::Null,
+        }
+    }
+
     /// # Errors
     /// `GetValueWrongType` when the `AttrVal` does not contain this type
     pub fn str_val(&self) -> anyhow::Result<&str, Error> {
@@ -48,7 +76,7 @@ impl AttrVal {
         })
     }
 
-    /// Note: Calling this function on a String(), or Number() will result in None too
+    /// Note: Calling this function on a `String()`, or `Number()` will result in None too
     /// # Errors
     /// `GetValueWrongType` when the `AttrVal` does not contain this type
     pub fn map_val(&self) -> Result<&AttrMap, Error> {
@@ -136,12 +164,49 @@ impl From<AttrMap> for AttrVal {
 impl TryFrom<Value> for AttrVal {
     type Error = Error;
     fn try_from(s: Value) -> Result<Self, Self::Error> {
-        serde_val_to_attr_val(s, true)
+        serde_val_to_attr_val(s, usize::MAX)
+    }
+}
+
+impl AttrVal {
+    /// # `from_json_with_depth()`
+    ///
+    /// Reads an attribute value from JSON, allowing maps to be nested at most `max_depth`
+    /// levels deep: a map directly in the value has depth 1, a map in that map depth 2 etc.
+    /// A depth of 0 rejects all maps.
+    ///
+    /// Plain deserialization does not limit the depth of maps, beyond the recursion
+    /// limit of `serde_json` itself. Use this for untrusted input with a stricter schema.
+    /// ```
+    /// use delta::types::attr_val::AttrVal;
+    ///
+    /// let json = r#"{ "font": { "size": 15 } }"#;
+    /// assert!(AttrVal::from_json_with_depth(json, 2).is_ok());
+    /// assert!(AttrVal::from_json_with_depth(json, 1).is_err());
+    /// ```
+    ///
+    /// # Errors
+    /// `SerdeNestedMap` when maps are nested deeper than `max_depth`, `Json` for invalid JSON,
+    /// `SerdeUnknownType` for arrays, and `NotAnUnsigned` for other numbers than unsigned integers
+    pub fn from_json_with_depth(json: &str, max_depth: usize) -> Result<AttrVal, Error> {
+        let value: Value = serde_json::from_str(json)?;
+        serde_val_to_attr_val(value, max_depth)
+    }
+
+    /// # `from_json_value()`
+    ///
+    /// Converts a JSON value in to an attribute value, like `from_json_with_depth()`.
+    ///
+    /// # Errors
+    /// `SerdeNestedMap` when maps are nested deeper than `max_depth`,
+    /// `SerdeUnknownType` for arrays, and `NotAnUnsigned` for other numbers than unsigned integers
+    pub fn from_json_value(value: Value, max_depth: usize) -> Result<AttrVal, Error> {
+        serde_val_to_attr_val(value, max_depth)
     }
 }
 
 #[allow(clippy::cast_possible_truncation)]
-fn serde_val_to_attr_val(value: Value, allow_nesting: bool) -> Result<AttrVal, Error> {
+fn serde_val_to_attr_val(value: Value, max_depth: usize) -> Result<AttrVal, Error> {
     match value {
         Value::Null => Ok(AttrVal::Null),
         Value::String(s) => Ok(AttrVal::String(s)),
@@ -153,8 +218,8 @@ fn serde_val_to_attr_val(value: Value, allow_nesting: bool) -> Result<AttrVal, E
             Ok(AttrVal::Number(nn as usize))
         }
         Value::Object(o) => {
-            if allow_nesting {
-                Ok(AttrVal::Map(serde_val_to_map(o, allow_nesting)?))
+            if max_depth > 0 {
+                Ok(AttrVal::Map(serde_val_to_map(o, max_depth - 1)?))
             } else {
                 Err(SerdeNestedMap {
                     value: Value::Object(o).to_string(),
@@ -167,42 +232,14 @@ fn serde_val_to_attr_val(value: Value, allow_nesting: bool) -> Result<AttrVal, E
     }
 }
 
-#[allow(clippy::cast_possible_truncation)]
 fn serde_val_to_map(
     value: serde_json::map::Map<String, Value>,
-    allow_nesting: bool,
+    max_depth: usize,
 ) -> Result<AttrMap, Error> {
     let mut att = AttrMap::default();
-    for (kk, vv) in value {
-        let k = kk.to_string();
-        let v = match vv {
-            Value::Null => AttrVal::Null,
-            Value::String(s) => AttrVal::String(s),
-            Value::Bool(b) => AttrVal::Bool(b),
-            Value::Number(n) => {
-                let Some(nn) = n.as_u64() else {
-                    return Err(Error::NotAnUnsigned);
-                };
-                AttrVal::Number(nn as usize)
-            }
-            Value::Object(o) => {
-                if allow_nesting {
-                    serde_val_to_attr_val(Value::Object(o), allow_nesting)?
-                } else {
-                    return Err(SerdeNestedMap {
-                        value: Value::Object(o).to_string(),
-                    });
-                }
-            }
-            Value::Array(_) => {
-                return Err(SerdeUnknownType {
-                    tpe: vv.to_string(),
-                })
-            }
-        };
-        att.insert(k, v);
+    for (k, v) in value {
+        att.insert(k, serde_val_to_attr_val(v, max_depth)?);
     }
-
     Ok(att)
 }
 
@@ -228,11 +265,10 @@ fn fmt_attrval(attrval: &AttrVal, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             write!(f, "{b}")
         }
         AttrVal::Map(m) => {
-            let mut out = String::new();
             for (k, v) in &**m {
-                out.push_str(&format!("({k}->{v}), "));
+                write!(f, "({k}->{v}), ")?;
             }
-            write!(f, "{out}")
+            Ok(())
         }
     }
 }
@@ -240,22 +276,24 @@ fn fmt_attrval(attrval: &AttrVal, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 #[cfg(test)]
 mod test {
     use crate::attributes::Attributes;
-    use crate::types::attr_val::{AttrMap, AttrVal};
+    use crate::error::Error;
+    use crate::operations::DeltaOperation;
+    use crate::types::attr_val::{AttrMap, AttrVal, AttrValKind};
     use log::warn;
 
     #[test]
     fn attr_val_from_x_passes() {
         let val = AttrVal::from("I am a test");
-        warn!("Unsupported format: {}", val);
+        warn!("Unsupported format: {val}");
 
         let val = AttrVal::from(true);
-        warn!("Unsupported format: {}", val);
+        warn!("Unsupported format: {val}");
 
         let val = AttrVal::Null;
-        warn!("Unsupported format: {}", val);
+        warn!("Unsupported format: {val}");
 
         let val = AttrVal::from(42);
-        warn!("Unsupported format: {}", val);
+        warn!("Unsupported format: {val}");
 
         let mut m = AttrMap::default();
         m.insert("number".to_string(), 42);
@@ -292,4 +330,41 @@ mod test {
         assert!(!s.contains("attr"));
         let _map3: AttrMap = serde_json::from_str(&s).unwrap();
     }
+
+    #[test]
+    fn attr_val_max_depth_passes() {
+        let json = r#"{ "a": { "b": { "c": 1 } } }"#;
+
+        let value: serde_json::Value = serde_json::from_str(json).unwrap();
+        let val = AttrVal::from_json_value(value.clone(), 3).unwrap();
+        assert!(val.map_val().unwrap()["a"].is_map());
+
+        let res = AttrVal::from_json_value(value.clone(), 2);
+        assert!(matches!(res, Err(Error::SerdeNestedMap { .. })));
+        let res = AttrVal::from_json_value(value, 0);
+        assert!(matches!(res, Err(Error::SerdeNestedMap { .. })));
+
+        // plain deserialization does not limit the depth
+        let mut deep = String::from("1");
+        for _ in 0..32 {
+            deep = format!(r#"{{ "n": {deep} }}"#);
+        }
+        let json = format!(r#"{{ "insert": "a", "attributes": {{ "deep": {deep} }} }}"#);
+        assert!(serde_json::from_str::<DeltaOperation>(&json).is_ok());
+
+        assert!(AttrVal::from_json_with_depth(&deep, 32).is_ok());
+        let res = AttrVal::from_json_with_depth(&deep, 31);
+        assert!(matches!(res, Err(Error::SerdeNestedMap { .. })));
+        let res = AttrVal::from_json_with_depth("{ \"a\": ", 32);
+        assert!(matches!(res, Err(Error::Json(_))));
+    }
+
+    #[test]
+    fn attr_val_kind_passes() {
+        assert_eq!(AttrVal::from("text").kind(), AttrValKind::String);
+        assert_eq!(AttrVal::from(42).kind(), AttrValKind::Number);
+        assert_eq!(AttrVal::from(true).kind(), AttrValKind::Bool);
+        assert_eq!(AttrVal::from(AttrMap::default()).kind(), AttrValKind::Map);
+        assert_eq!(AttrVal::Null.kind(), AttrValKind::Null);
+    }
 }
@@ -10,14 +10,19 @@ use serde_derive::{Deserialize, Serialize};
 #[cfg(test)]
 use std::fmt;
 
+/// The kind of a delta operation, with its value.
+///
+/// Serialization always uses the canonical Quill keys `insert`, `retain` and `delete`.
+/// For importing data from other serializers, deserialization also accepts
+/// capitalized and upper case keys, and the abbreviations `ins`, `ret` and `del`.
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum OpKind {
-    #[serde(rename = "insert")]
+    #[serde(rename = "insert", alias = "Insert", alias = "INSERT", alias = "ins")]
     Insert(OpsVal),
-    #[serde(rename = "retain")]
+    #[serde(rename = "retain", alias = "Retain", alias = "RETAIN", alias = "ret")]
     Retain(usize),
-    #[serde(rename = "delete")]
+    #[serde(rename = "delete", alias = "Delete", alias = "DELETE", alias = "del")]
     Delete(usize),
 }
 
@@ -7,7 +7,7 @@
 
 use crate::delta::Delta;
 use crate::iterator::DeltaIterator;
-use crate::operations::DeltaOperation;
+use crate::operations::{DeltaOperation, OpType};
 
 pub trait DeltaTransformations {
     fn filter<F>(&self, predicate: F) -> Delta
@@ -21,6 +21,15 @@ pub trait DeltaTransformations {
     where
         F: Fn(&DeltaOperation);
 
+    //
+    // Execute for each DeltaOperation in a delta using a
+    // closure f(&mut DeltaOperation), which may change the operation in place.
+    // Afterwards the delta is compacted again, since changed operations may
+    // be merged with their neighbours, or may have become empty.
+    fn for_each_mut<F>(&mut self, predicate: F)
+    where
+        F: FnMut(&mut DeltaOperation);
+
     //
     // length of the delta operations regardless of the OpKind
     fn delta_length(&self) -> usize;
@@ -55,6 +64,12 @@ pub trait DeltaTransformations {
     // `start` - Start index of subset, default to 0
     // `end` - End index of subset, defaults to rest of operations; use `usize::MAX` for all
     fn slice(&self, start: usize, end: usize) -> Delta;
+
+    //
+    // Returns a one-line summary of the operations for logging, e.g.
+    // `[ins:5, ret:3(bold), del:2]`, with the length of each operation
+    // and the sorted keys of its attributes, if any.
+    fn summary(&self) -> String;
 }
 
 impl DeltaTransformations for Delta {
@@ -87,10 +102,18 @@ impl DeltaTransformations for Delta {
         self.iter().for_each(predicate);
     }
 
+    fn for_each_mut<F>(&mut self, predicate: F)
+    where
+        F: FnMut(&mut DeltaOperation),
+    {
+        self.iter_mut().for_each(predicate);
+        self.compact();
+    }
+
     fn delta_length(&self) -> usize {
         let mut len: usize = 0;
         for d in self.iter() {
-            len += d.op_len();
+            len += self.len_of(d);
         }
         len
     }
@@ -113,8 +136,8 @@ impl DeltaTransformations for Delta {
     where
         F: Fn(&DeltaOperation) -> bool,
     {
-        let mut passed: Delta = Delta::default();
-        let mut failed: Delta = Delta::default();
+        let mut passed: Delta = self.empty_like();
+        let mut failed: Delta = self.empty_like();
         self.iter().for_each(|d| {
             if predicate(d) {
                 passed.push(d.clone());
@@ -144,20 +167,46 @@ impl DeltaTransformations for Delta {
             einde = self.delta_length();
         }
 
-        let mut delta = Delta::default();
-        let iter = DeltaIterator::new(self);
+        let mut delta = self.empty_like();
+        let iter = DeltaIterator::new(self).with_embed_length(self.embed_length());
         let mut index: usize = 0;
         while index < einde && iter.has_next() {
-            let next_op: DeltaOperation;
-            if index < start {
-                next_op = iter.next_len(start - index);
-                index += &next_op.op_len();
+            let in_slice = index >= start;
+            let mut len = if in_slice {
+                einde - index
             } else {
-                next_op = iter.next_len(einde - index);
-                index += &next_op.op_len();
+                start - index
+            };
+            // Embeds are atomic, an embed is part of the slice when it starts in it
+            if iter.check_embed_split(len).is_err() {
+                len = 0;
+            }
+            let next_op = iter.next_len(len);
+            index += self.len_of(&next_op);
+            if in_slice {
                 delta.push(next_op);
             }
         }
         delta
     }
+
+    fn summary(&self) -> String {
+        let ops: Vec<String> = self
+            .iter()
+            .map(|op| {
+                let kind = match op.op_type() {
+                    OpType::Insert => "ins",
+                    OpType::Retain => "ret",
+                    OpType::Delete => "del",
+                };
+                let mut keys: Vec<&str> = op.get_attributes().keys().map(String::as_str).collect();
+                if keys.is_empty() {
+                    return format!("{kind}:{}", op.op_len());
+                }
+                keys.sort_unstable();
+                format!("{kind}:{}({})", op.op_len(), keys.join(","))
+            })
+            .collect();
+        format!("[{}]", ops.join(", "))
+    }
 }
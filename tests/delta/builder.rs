@@ -1,8 +1,9 @@
 #[cfg(test)]
 mod tests {
     use delta::attributes::Attributes;
-    use delta::delta::Delta;
-    use delta::operations::{DeltaOperation, OpsMap, OpsVal};
+    use delta::delta::{Delta, InsertDeleteOrder};
+    use delta::operations::{DeltaOperation, OpType, OpsMap, OpsVal};
+    use delta::optransform::OpTransform;
     use delta::utils::DeltaTransformations;
 
     #[test]
@@ -156,6 +157,134 @@ mod tests {
         assert_eq!(delta1, delta2);
     }
 
+    #[test]
+    pub fn build_delete_first_ordering_passes() -> anyhow::Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        // the same edit: replace "cd" by "XY", and "f" by "Z"
+        let build = |mut delta: Delta| {
+            delta.retain(2);
+            delta.insert("X");
+            delta.delete(1);
+            delta.insert("Y");
+            delta.delete(1);
+            delta.retain(1);
+            delta.delete(1);
+            delta.insert_attr("Z", bold.clone());
+            delta
+        };
+        let insert_first = build(Delta::default());
+        let delete_first = build(Delta::with_ordering(InsertDeleteOrder::DeleteFirst));
+
+        assert_eq!(
+            insert_first,
+            Delta::new(vec![
+                DeltaOperation::retain(2),
+                DeltaOperation::insert("XY"),
+                DeltaOperation::delete(2),
+                DeltaOperation::retain(1),
+                DeltaOperation::insert_attr("Z", bold.clone()),
+                DeltaOperation::delete(1),
+            ])
+        );
+        assert_eq!(
+            delete_first,
+            Delta::new(vec![
+                DeltaOperation::retain(2),
+                DeltaOperation::delete(2),
+                DeltaOperation::insert("XY"),
+                DeltaOperation::retain(1),
+                DeltaOperation::delete(1),
+                DeltaOperation::insert_attr("Z", bold.clone()),
+            ])
+        );
+        assert!(insert_first.is_normalized());
+        assert!(delete_first.is_normalized());
+
+        let mut doc = Delta::default();
+        doc.insert("abcdefg");
+        let mut expected = Delta::default();
+        expected.insert("abXYe");
+        expected.insert_attr("Z", bold);
+        expected.insert("g");
+        assert_eq!(doc.compose(&insert_first)?, expected);
+        assert_eq!(doc.compose(&delete_first)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    pub fn build_delete_first_ordering_compact_passes() {
+        let mut delta = Delta::with_ordering(InsertDeleteOrder::DeleteFirst);
+        delta.extend([
+            DeltaOperation::insert("a"),
+            DeltaOperation::delete(1),
+            DeltaOperation::insert("b"),
+            DeltaOperation::delete(2),
+        ]);
+        assert!(!delta.is_normalized());
+        delta.compact();
+        assert_eq!(
+            delta,
+            Delta::new(vec![
+                DeltaOperation::delete(3),
+                DeltaOperation::insert("ab")
+            ])
+        );
+        assert!(delta.is_normalized());
+
+        // the delete moves in front of inserts with different attributes too
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut delta = Delta::with_ordering(InsertDeleteOrder::DeleteFirst);
+        delta.retain(1);
+        delta.delete(1);
+        delta.insert_attr("a", bold.clone());
+        delta.insert("b");
+        delta.insert(1);
+        delta.delete(2);
+        let expected = vec![
+            DeltaOperation::retain(1),
+            DeltaOperation::delete(3),
+            DeltaOperation::insert_attr("a", bold),
+            DeltaOperation::insert("b"),
+            DeltaOperation::insert(1),
+        ];
+        assert_eq!(delta.clone().get_ops(), expected);
+        assert!(delta.is_normalized());
+        delta.compact();
+        assert_eq!(delta.get_ops(), expected);
+
+        // the ordering is not part of the content
+        let mut a = Delta::with_ordering(InsertDeleteOrder::DeleteFirst);
+        a.retain(1);
+        let mut b = Delta::default();
+        b.retain(1);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    pub fn build_try_insert_passes() -> anyhow::Result<()> {
+        let mut delta = Delta::default();
+        let err = delta.try_insert("").unwrap_err();
+        assert!(err.to_string().contains("zero length"));
+        assert!(delta.is_empty());
+
+        delta.try_insert("x")?;
+        delta.try_insert(1)?;
+        delta.try_insert("y")?;
+
+        // the lenient insert ignores the empty value
+        delta.insert("");
+
+        let mut expected = Delta::default();
+        expected.insert("x");
+        expected.insert(1);
+        expected.insert("y");
+        assert_eq!(delta, expected);
+        Ok(())
+    }
+
     #[test]
     pub fn build_delete_passes() {
         let mut delta = Delta::default();
@@ -306,4 +435,162 @@ mod tests {
             &Attributes::default()
         );
     }
+
+    #[test]
+    pub fn build_push_compose_retain_same_range_passes() {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        bold.insert("color", "red");
+
+        let mut color = Attributes::default();
+        color.insert("color", "blue");
+        color.insert("italic", true);
+
+        let mut expected = Attributes::default();
+        expected.insert("bold", true);
+        expected.insert("color", "blue");
+        expected.insert("italic", true);
+
+        let mut delta = Delta::default();
+        delta.retain_attr(3, bold.clone());
+        delta.push_compose_retain(DeltaOperation::retain_attr(3, color.clone()));
+
+        assert_eq!(delta.len(), 1);
+        assert_eq!(delta.delta_length(), 3);
+        assert_eq!(delta.first().unwrap().get_attributes(), &expected);
+
+        // push() keeps both attribute sets as consecutive ranges
+        let mut delta = Delta::default();
+        delta.retain_attr(3, bold.clone());
+        delta.push(DeltaOperation::retain_attr(3, color));
+
+        assert_eq!(delta.len(), 2);
+        assert_eq!(delta.delta_length(), 6);
+        assert_eq!(delta.first().unwrap().get_attributes(), &bold);
+    }
+
+    #[test]
+    pub fn build_push_compose_retain_keeps_null_passes() {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut remove = Attributes::default();
+        remove.insert("bold", OpsVal::Null);
+
+        let mut delta = Delta::default();
+        delta.retain_attr(2, bold);
+        delta.push_compose_retain(DeltaOperation::retain_attr(2, remove.clone()));
+
+        assert_eq!(delta.len(), 1);
+        assert_eq!(delta.first().unwrap().get_attributes(), &remove);
+    }
+
+    #[test]
+    pub fn build_push_compose_retain_different_range_passes() {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut italic = Attributes::default();
+        italic.insert("italic", true);
+
+        let mut delta = Delta::default();
+        delta.retain_attr(2, bold.clone());
+        delta.push_compose_retain(DeltaOperation::retain_attr(3, italic.clone()));
+
+        assert_eq!(delta.len(), 2);
+        assert_eq!(delta.delta_length(), 5);
+        assert_eq!(delta.first().unwrap().get_attributes(), &bold);
+        assert_eq!(delta.get(1).unwrap().get_attributes(), &italic);
+
+        let mut delta = Delta::default();
+        delta.insert("ab");
+        delta.push_compose_retain(DeltaOperation::retain_attr(2, italic));
+        assert_eq!(delta.len(), 2);
+    }
+
+    #[test]
+    pub fn build_merge_deletes_passes() {
+        let mut delta = Delta::new(vec![
+            DeltaOperation::retain(1),
+            DeltaOperation::delete(2),
+            DeltaOperation::retain(0),
+            DeltaOperation::insert(""),
+            DeltaOperation::delete(3),
+            DeltaOperation::retain(0),
+            DeltaOperation::insert("a"),
+            DeltaOperation::delete(1),
+        ]);
+        delta.merge_deletes();
+        assert_eq!(
+            delta,
+            Delta::new(vec![
+                DeltaOperation::retain(1),
+                DeltaOperation::delete(5),
+                DeltaOperation::insert("a"),
+                DeltaOperation::delete(1),
+            ])
+        );
+
+        // deletes with different ids stay apart
+        let mut delta = Delta::new(vec![
+            DeltaOperation::delete(1).with_id("a"),
+            DeltaOperation::delete(0),
+            DeltaOperation::delete(1).with_id("b"),
+        ]);
+        delta.merge_deletes();
+        assert_eq!(delta.len(), 2);
+        assert_eq!(delta.delta_length(), 2);
+
+        // empty operations are dropped also without a delete to merge
+        let mut delta = Delta::new(vec![
+            DeltaOperation::retain(0),
+            DeltaOperation::insert("a"),
+            DeltaOperation::retain(0),
+        ]);
+        delta.merge_deletes();
+        assert_eq!(delta, Delta::new(vec![DeltaOperation::insert("a")]));
+    }
+
+    #[test]
+    pub fn build_clamp_inserts_passes() {
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut change = Delta::default();
+        change.retain(1);
+        change.insert("ä");
+        change.delete(2);
+        change.retain(1);
+        change.insert("bcd");
+        change.insert(OpsVal::from(img.clone()));
+        change.retain(3);
+        change.insert("e");
+        change.delete(1);
+
+        let mut clamped = change.clone();
+        clamped.clamp_inserts(3);
+        let mut expected = Delta::default();
+        expected.retain(1);
+        expected.insert("ä");
+        expected.delete(2);
+        expected.retain(1);
+        expected.insert("bc");
+        expected.retain(3);
+        expected.delete(1);
+        assert_eq!(clamped, expected);
+
+        // embeds count as one character
+        let mut clamped = change.clone();
+        clamped.clamp_inserts(5);
+        assert_eq!(clamped[5], DeltaOperation::insert(img));
+        assert_eq!(clamped.len(), 8);
+
+        // without a limit nothing changes, with zero all inserts are dropped
+        let mut clamped = change.clone();
+        clamped.clamp_inserts(usize::MAX);
+        assert_eq!(clamped, change);
+        change.clamp_inserts(0);
+        assert!(change.iter().all(|op| op.op_type() != OpType::Insert));
+        assert_eq!(change.len(), 4);
+    }
 }
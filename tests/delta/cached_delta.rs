@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod tests {
+    use delta::attributes::Attributes;
+    use delta::cached_delta::CachedDelta;
+    use delta::delta::Delta;
+    use delta::document::Document;
+    use delta::operations::DeltaOperation;
+
+    #[test]
+    fn cached_length_passes() {
+        let mut a = Delta::default();
+        a.insert("Hello");
+        a.retain(3);
+
+        let cached = CachedDelta::from(a.clone());
+        assert!(!cached.is_cached());
+        assert_eq!(cached.document_length(), a.document_length());
+        assert!(cached.is_cached());
+        assert_eq!(cached.delta_length(), 8);
+        assert_eq!(cached.into_inner(), a);
+    }
+
+    #[test]
+    fn cached_invalidate_on_push_passes() {
+        let mut cached = CachedDelta::default();
+        cached.insert("Hello");
+        assert_eq!(cached.document_length(), 5);
+
+        cached.push(DeltaOperation::insert(" World"));
+        assert!(!cached.is_cached());
+        assert_eq!(cached.document_length(), 11);
+        assert_eq!(cached.len(), 1);
+
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        cached.insert_attr("!", bold);
+        assert_eq!(cached.document_length(), 12);
+
+        cached.delta_mut().pop();
+        assert_eq!(cached.document_length(), 11);
+        assert_eq!(cached.delta_length(), 11);
+    }
+}
@@ -1,7 +1,9 @@
 use anyhow::Result;
 use delta::attributes::Attributes;
 use delta::delta::Delta;
-use delta::optransform::OpTransform;
+use delta::document::Document;
+use delta::operations::{DeltaOperation, OpsMap};
+use delta::optransform::{OpTransform, Span};
 use delta::types::attr_val::AttrVal;
 
 #[test]
@@ -315,6 +317,35 @@ fn compose_remove_all_attributes_passes() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn compose_remove_partial_attributes_passes() -> Result<()> {
+    let mut bold_red = Attributes::default();
+    bold_red.insert("bold", true);
+    bold_red.insert("color", "red");
+    let mut a = Delta::default();
+    a.insert_attr("A", bold_red.clone());
+    a.insert_attr(1, bold_red);
+    a.retain(1);
+
+    let mut no_color = Attributes::default();
+    no_color.insert("color", AttrVal::Null);
+    let mut b = Delta::default();
+    b.retain_attr(3, no_color.clone());
+
+    // removing one attribute keeps the others on the inserts,
+    // and the removal itself on the retain
+    let mut bold = Attributes::default();
+    bold.insert("bold", true);
+    let mut expected = Delta::default();
+    expected.insert_attr("A", bold.clone());
+    expected.insert_attr(1, bold);
+    expected.retain_attr(1, no_color);
+
+    let r = a.compose(&b)?;
+    assert_eq!(&r, &expected);
+    Ok(())
+}
+
 #[test]
 fn compose_remove_all_embed_attributes_passes() -> Result<()> {
     let mut attr = Attributes::default();
@@ -477,3 +508,459 @@ fn compose_retain_end_optimization_join_passes() -> Result<()> {
     assert_eq!(&r, &expected);
     Ok(())
 }
+
+#[test]
+fn compose_zero_length_insert_passes() -> Result<()> {
+    let a = Delta::new(vec![
+        DeltaOperation::insert("AB"),
+        DeltaOperation::insert(""),
+        DeltaOperation::insert_attr("", Attributes::default()),
+        DeltaOperation::insert("C"),
+    ]);
+
+    let mut bold = Attributes::default();
+    bold.insert("bold", true);
+    let b = Delta::new(vec![
+        DeltaOperation::retain(1),
+        DeltaOperation::insert(""),
+        DeltaOperation::delete(1),
+        DeltaOperation::retain_attr(1, bold.clone()),
+    ]);
+
+    let mut expected = Delta::default();
+    expected.insert("A");
+    expected.insert_attr("C", bold);
+
+    assert_eq!(a.compose(&b)?, expected);
+
+    let c = Delta::new(vec![DeltaOperation::insert("")]);
+    assert_eq!(c.compose(&c)?, Delta::default());
+    assert_eq!(a.compose(&c)?.document_length(), 3);
+    Ok(())
+}
+
+#[test]
+fn compose_malformed_terminates_passes() {
+    // zero length operations of every kind, and lengths at the iterator's end marker
+    let a = Delta::new(vec![
+        DeltaOperation::retain(0),
+        DeltaOperation::insert("AB"),
+        DeltaOperation::delete(0),
+        DeltaOperation::retain(usize::MAX),
+    ]);
+    let b = Delta::new(vec![
+        DeltaOperation::delete(0),
+        DeltaOperation::retain(1),
+        DeltaOperation::retain(0),
+        DeltaOperation::insert(""),
+        DeltaOperation::delete(usize::MAX),
+    ]);
+    let c = Delta::new(vec![
+        DeltaOperation::delete(usize::MAX),
+        DeltaOperation::retain(0),
+    ]);
+
+    for (x, y) in [(&a, &b), (&b, &a), (&a, &c), (&c, &a), (&b, &c), (&c, &b)] {
+        assert!(x.compose(y).is_ok());
+    }
+}
+
+#[test]
+fn transform_zero_length_insert_passes() -> Result<()> {
+    let a = Delta::new(vec![
+        DeltaOperation::retain(1),
+        DeltaOperation::insert(""),
+        DeltaOperation::delete(1),
+    ]);
+    let b = Delta::new(vec![
+        DeltaOperation::insert(""),
+        DeltaOperation::insert("X"),
+    ]);
+
+    let mut expected = Delta::default();
+    expected.insert("X");
+
+    assert_eq!(a.transform(&b, true)?, expected);
+    Ok(())
+}
+
+#[test]
+fn compose_retain_all_passes() -> Result<()> {
+    let mut bold = Attributes::default();
+    bold.insert("bold", true);
+
+    let mut italic = Attributes::default();
+    italic.insert("italic", true);
+
+    let mut a = Delta::default();
+    a.insert("Hello ");
+    a.insert_attr("World", italic.clone());
+    a.insert(1);
+
+    let b = Delta::retain_all(a.document_length(), bold.clone());
+    assert_eq!(b.len(), 1);
+
+    let mut both = italic.clone();
+    both.insert("bold", true);
+    let mut expected = Delta::default();
+    expected.insert_attr("Hello ", bold.clone());
+    expected.insert_attr("World", both);
+    expected.insert_attr(1, bold);
+    assert_eq!(a.compose(&b)?, expected);
+
+    let c = Delta::retain_all(a.document_length(), Attributes::default());
+    assert_eq!(c.len(), 1);
+    assert_eq!(a.compose(&c)?, a);
+    Ok(())
+}
+
+#[test]
+fn compose_identity_change_passes() -> Result<()> {
+    let mut bold = Attributes::default();
+    bold.insert("bold", true);
+
+    let mut doc = Delta::default();
+    doc.insert("Hello ");
+    doc.insert_attr("World", bold.clone());
+
+    // explicit, so not empty as a chopped delta would be
+    let identity = Delta::identity_change(doc.document_length());
+    assert!(!identity.is_empty());
+    assert_eq!(identity.len(), 1);
+    assert_eq!(identity[0], DeltaOperation::retain(11));
+    assert!(!identity.is_normalized());
+
+    assert_eq!(doc.compose(&identity)?, doc);
+    let mut change = Delta::default();
+    change.retain_attr(5, bold);
+    assert_eq!(change.compose(&identity)?, change);
+    assert_eq!(identity.compose(&change)?, change);
+
+    assert!(Delta::identity_change(0).is_empty());
+    Ok(())
+}
+
+#[test]
+fn compose_tracked_insert_passes() -> Result<()> {
+    let mut a = Delta::default();
+    a.insert("Hello World");
+
+    let mut b = Delta::default();
+    b.retain(6);
+    b.insert("big ");
+    b.delete(5);
+    b.insert("Rust");
+
+    let (r, spans) = a.compose_tracked(&b)?;
+    assert_eq!(r, a.compose(&b)?);
+    // "big " and "Rust" are adjacent after the delete, and reported as a single span
+    assert_eq!(spans, vec![Span { start: 6, end: 14 }]);
+    Ok(())
+}
+
+#[test]
+fn compose_tracked_change_passes() -> Result<()> {
+    let mut bold = Attributes::default();
+    bold.insert("bold", true);
+
+    let mut a = Delta::default();
+    a.retain(2);
+    a.insert("A");
+    a.delete(1);
+
+    let mut b = Delta::default();
+    b.insert("B");
+    b.retain_attr(3, bold);
+    b.insert("C");
+
+    let (r, spans) = a.compose_tracked(&b)?;
+    assert_eq!(r, a.compose(&b)?);
+    assert_eq!(
+        spans,
+        vec![Span { start: 0, end: 1 }, Span { start: 4, end: 5 }]
+    );
+
+    // nothing new is inserted
+    let (_, spans) = b.compose_tracked(&Delta::default())?;
+    assert!(spans.is_empty());
+    Ok(())
+}
+
+#[test]
+fn compose_with_flags_keep_null_on_insert_passes() -> Result<()> {
+    let mut a = Delta::default();
+    a.insert("A");
+    a.retain(1);
+
+    let mut remove_bold = Attributes::default();
+    remove_bold.insert("bold", AttrVal::Null);
+    let mut b = Delta::default();
+    b.retain_attr(2, remove_bold.clone());
+
+    // default as in Quill: the null is dropped on the insert, kept on the retain
+    let mut expected = Delta::default();
+    expected.insert("A");
+    expected.retain_attr(1, remove_bold.clone());
+    assert_eq!(a.compose(&b)?, expected);
+    assert_eq!(a.compose_with_flags(&b, false)?, expected);
+
+    // keep the null on the insert too
+    let mut expected = Delta::default();
+    expected.insert_attr("A", remove_bold.clone());
+    expected.retain_attr(1, remove_bold);
+    assert_eq!(a.compose_with_flags(&b, true)?, expected);
+    Ok(())
+}
+
+#[test]
+fn compose_exhausted_other_tail_passes() -> Result<()> {
+    let mut bold = Attributes::default();
+    bold.insert("bold", true);
+    let mut italic = Attributes::default();
+    italic.insert("italic", true);
+    let mut null_bold = Attributes::default();
+    null_bold.insert("bold", AttrVal::Null);
+
+    let mut a = Delta::default();
+    a.insert("Hello ");
+    for i in 0..100 {
+        a.insert_attr(
+            "World ",
+            if i % 2 == 0 {
+                bold.clone()
+            } else {
+                italic.clone()
+            },
+        );
+        a.insert(1);
+    }
+    let tail = a.document_length() - 3;
+
+    let mut b = Delta::default();
+    b.retain_attr(3, italic.clone());
+
+    // Reference: an explicit retain over the tail keeps the other delta going to the end
+    let mut b_full = b.clone();
+    b_full.retain(tail);
+
+    let r = a.compose(&b)?;
+    assert_eq!(r, a.compose(&b_full)?);
+    assert_eq!(r.len(), a.len() + 1);
+    assert_eq!(r.document_length(), a.document_length());
+
+    // attributes of the tail are kept as is, also null values
+    let mut a = Delta::default();
+    a.insert("Hello ");
+    a.insert_attr("World", null_bold.clone());
+    a.insert("!");
+    let mut b = Delta::default();
+    b.delete(1);
+
+    let mut b_full = b.clone();
+    b_full.retain(a.document_length() - 1);
+
+    let mut expected = Delta::default();
+    expected.insert("ello ");
+    expected.insert_attr("World", null_bold);
+    expected.insert("!");
+    assert_eq!(a.compose(&b)?, expected);
+    assert_eq!(a.compose(&b_full)?, expected);
+    Ok(())
+}
+
+#[test]
+fn compose_repeat_passes() -> Result<()> {
+    let mut bold = Attributes::default();
+    bold.insert("bold", true);
+
+    let mut doc = Delta::default();
+    doc.insert("Hello");
+
+    let mut append = Delta::default();
+    append.retain(5);
+    append.insert_attr("!", bold.clone());
+
+    let mut mixed = Delta::default();
+    mixed.retain(1);
+    mixed.delete(1);
+    mixed.insert("xy");
+    mixed.retain_attr(2, bold);
+
+    for change in [&append, &mixed] {
+        let mut manual = doc.clone();
+        for times in 0..10 {
+            assert_eq!(doc.compose_repeat(change, times)?, manual);
+            manual = manual.compose(change)?;
+        }
+    }
+
+    assert_eq!(doc.compose_repeat(&append, 0)?, doc);
+    Ok(())
+}
+
+#[test]
+fn replay_passes() -> Result<()> {
+    let mut bold = Attributes::default();
+    bold.insert("bold", true);
+
+    let mut base = Delta::default();
+    base.insert("Hello World");
+
+    let mut c1 = Delta::default();
+    c1.retain(11);
+    c1.insert("!");
+    let mut c2 = Delta::default();
+    c2.retain_attr(5, bold.clone());
+    let mut c3 = Delta::default();
+    c3.retain(5);
+    c3.delete(6);
+    c3.insert(" there");
+
+    let states = Delta::replay(&base, &[c1.clone(), c2.clone(), c3.clone()])?;
+
+    let mut s1 = Delta::default();
+    s1.insert("Hello World!");
+    let mut s2 = Delta::default();
+    s2.insert_attr("Hello", bold.clone());
+    s2.insert(" World!");
+    let mut s3 = Delta::default();
+    s3.insert_attr("Hello", bold);
+    s3.insert(" there!");
+    assert_eq!(states, vec![s1, s2, s3]);
+
+    assert_eq!(states[2], base.compose(&c1)?.compose(&c2)?.compose(&c3)?);
+    assert!(Delta::replay(&base, &[])?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn compose_keeps_ids_passes() -> Result<()> {
+    let mut doc = Delta::default();
+    doc.push(DeltaOperation::insert("Hello").with_id("a"));
+    doc.push(DeltaOperation::insert(" World").with_id("b"));
+    assert_eq!(doc.len(), 2);
+
+    let mut bold = Attributes::default();
+    bold.insert("bold", true);
+    let mut change = Delta::default();
+    change.retain(2);
+    change.push(DeltaOperation::retain_attr(6, bold.clone()).with_id("c"));
+    change.push(DeltaOperation::insert("!").with_id("d"));
+    change.delete(1);
+
+    // the inserts keep their ids, also where they are split by the change
+    let result = doc.compose(&change)?;
+    let ids: Vec<Option<&str>> = result.iter().map(DeltaOperation::id).collect();
+    assert_eq!(ids, [Some("a"), Some("a"), Some("b"), Some("d"), Some("b")]);
+    assert_eq!(
+        result[1],
+        DeltaOperation::insert_attr("llo", bold).with_id("a")
+    );
+
+    // retains without id of their own get the id of the other retain
+    let mut other = Delta::default();
+    other.push(DeltaOperation::retain(4).with_id("e"));
+    other.insert("X");
+    let composed = change.compose(&other)?;
+    let ids: Vec<Option<&str>> = composed.iter().map(DeltaOperation::id).collect();
+    assert_eq!(
+        ids,
+        [Some("e"), Some("c"), None, Some("c"), Some("d"), None]
+    );
+
+    // transformed operations keep their ids
+    let transformed = other.transform(&change, true)?;
+    assert!(transformed.iter().any(|op| op.id() == Some("c")));
+    assert!(transformed.iter().any(|op| op.id() == Some("d")));
+    Ok(())
+}
+
+#[test]
+fn compose_retain_clear_all_passes() -> Result<()> {
+    let mut bold = Attributes::default();
+    bold.insert("bold", true);
+    let mut italic_red = Attributes::default();
+    italic_red.insert("italic", true);
+    italic_red.insert("color", "red");
+    let mut link = Attributes::default();
+    link.insert("link", "https://quilljs.com");
+
+    let mut doc = Delta::default();
+    doc.insert_attr("Hello", bold.clone());
+    doc.insert(" ");
+    doc.insert_attr("World", italic_red);
+    doc.insert_attr("!", link.clone());
+
+    // clears bold, italic and color, but not the link outside the range
+    let change = Delta::retain_clear_all(&doc, 3, 11)?;
+    assert_eq!(change.len(), 2);
+    assert_eq!(change[1].get_attributes().len(), 3);
+
+    let mut expected = Delta::default();
+    expected.insert_attr("Hel", bold);
+    expected.insert("lo World");
+    expected.insert_attr("!", link);
+    assert_eq!(doc.compose(&change)?, expected);
+
+    assert!(Delta::retain_clear_all(&doc, 0, 0)?.is_empty());
+    assert!(Delta::retain_clear_all(&doc, 3, 13).is_err());
+    assert!(Delta::retain_clear_all(&doc, 4, 3).is_err());
+    assert!(Delta::retain_clear_all(&change, 0, 1).is_err());
+    Ok(())
+}
+
+#[test]
+fn compose_matches_apply_with_inverse_passes() -> Result<()> {
+    let mut bold = Attributes::default();
+    bold.insert("bold", true);
+    let mut italic = Attributes::default();
+    italic.insert("italic", true);
+    let mut unbold = Attributes::default();
+    unbold.insert("bold", AttrVal::Null);
+    let mut image = OpsMap::default();
+    image.insert("image", "octocat.png");
+
+    let mut doc = Delta::default();
+    doc.insert_attr("Hello", bold.clone());
+    doc.insert(" big ");
+    doc.insert_attr(image, italic.clone());
+    doc.insert_attr("World\n", bold.clone());
+
+    // slices strings, formats embeds, removes formatting, inserts and deletes
+    let mut change = Delta::default();
+    change.retain(2);
+    change.retain_attr(5, italic.clone());
+    change.delete(2);
+    change.retain_attr(1, bold);
+    change.insert("X");
+    change.retain_attr(3, unbold);
+    change.delete(1);
+    change.retain_attr(2, italic);
+
+    let (expected, _) = doc.apply_with_inverse(&change)?;
+    assert_eq!(doc.compose(&change)?, expected);
+    Ok(())
+}
+
+#[test]
+fn compose_keeps_redundant_attributes_passes() -> Result<()> {
+    let mut bold = Attributes::default();
+    bold.insert("bold", true);
+
+    // setting bold on text a previous change already made bold is recorded
+    let mut a = Delta::default();
+    a.retain(2);
+    a.retain_attr(3, bold.clone());
+    let mut b = Delta::default();
+    b.retain_attr(5, bold.clone());
+    let mut expected = Delta::default();
+    expected.retain_attr(5, bold.clone());
+    assert_eq!(a.compose(&b)?, expected);
+    assert_eq!(b.compose(&b)?, expected);
+
+    // the bold text of a document stays bold
+    let mut doc = Delta::default();
+    doc.insert_attr("Hello", bold);
+    assert_eq!(doc.compose(&b)?, doc);
+    Ok(())
+}
@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use delta::attributes::Attributes;
+    use delta::cursor::DeltaCursor;
+    use delta::delta::Delta;
+    use delta::optransform::OpTransform;
+
+    #[test]
+    fn cursor_empty_passes() -> Result<()> {
+        let cursor = DeltaCursor::default();
+        assert_eq!(cursor.finish()?, Delta::default());
+        Ok(())
+    }
+
+    #[test]
+    fn cursor_type_text_passes() -> Result<()> {
+        let mut cursor = DeltaCursor::default();
+        cursor.move_to(5).type_text(" big").type_text(",");
+        assert_eq!(cursor.position(), 10);
+
+        let mut expected = Delta::default();
+        expected.retain(5);
+        expected.insert(" big,");
+        assert_eq!(cursor.finish()?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn cursor_move_by_passes() {
+        let mut cursor = DeltaCursor::default();
+        cursor.move_to(5).move_by(3);
+        assert_eq!(cursor.position(), 8);
+        cursor.move_by(-2);
+        assert_eq!(cursor.position(), 6);
+        cursor.move_by(-10);
+        assert_eq!(cursor.position(), 0);
+    }
+
+    #[test]
+    fn cursor_backspace_passes() -> Result<()> {
+        let mut cursor = DeltaCursor::default();
+        cursor.move_to(5).backspace(2);
+        assert_eq!(cursor.position(), 3);
+        cursor.backspace(10);
+        assert_eq!(cursor.position(), 0);
+
+        let mut expected = Delta::default();
+        expected.delete(5);
+        assert_eq!(cursor.finish()?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn cursor_typing_sequence_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut doc = Delta::default();
+        doc.insert("Hello World\n");
+
+        let mut cursor = DeltaCursor::default();
+        cursor.move_to(11);
+        cursor.type_text("!!");
+        cursor.backspace(1);
+        cursor.move_to(0);
+        cursor.format(bold.clone(), 5);
+        cursor.move_to(6);
+        cursor.type_text("big ");
+        cursor.delete_forward(1);
+        cursor.type_text("w");
+        assert_eq!(cursor.position(), 11);
+        let change = cursor.finish()?;
+
+        let mut expected_change = Delta::default();
+        expected_change.retain_attr(5, bold.clone());
+        expected_change.retain(1);
+        expected_change.insert("big w");
+        expected_change.delete(1);
+        expected_change.retain(4);
+        expected_change.insert("!");
+        assert_eq!(change, expected_change);
+
+        let mut expected = Delta::default();
+        expected.insert_attr("Hello", bold);
+        expected.insert(" big world!\n");
+        assert_eq!(doc.compose(&change)?, expected);
+        Ok(())
+    }
+}
@@ -3,8 +3,8 @@ mod tests {
     use anyhow::Result;
     use delta::attributes::Attributes;
     use delta::delta::Delta;
-    use delta::document::Document;
-    use delta::operations::OpsMap;
+    use delta::document::{DiffGranularity, Document};
+    use delta::operations::{DeltaOperation, OpsMap};
     use delta::optransform::OpTransform;
     use delta::types::attr_map::AttrMap;
     use delta::types::attr_val::AttrVal;
@@ -427,4 +427,872 @@ mod tests {
             Ok(_) => panic!("invalid result from diff()"),
         };
     }
+
+    #[test]
+    fn min_equal_collapses_short_gap_passes() -> Result<()> {
+        let mut a = Delta::default();
+        a.insert("aXYb");
+
+        let mut b = Delta::default();
+        b.insert("cXYd");
+
+        let mut expected = Delta::default();
+        expected.insert("cXYd");
+        expected.delete(4);
+
+        let r = a.diff_min_equal(&b, 3)?;
+        assert_eq!(r, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn min_equal_keeps_long_gap_passes() -> Result<()> {
+        let mut a = Delta::default();
+        a.insert("aXYb");
+
+        let mut b = Delta::default();
+        b.insert("cXYd");
+
+        let mut expected = Delta::default();
+        expected.insert("c");
+        expected.delete(1);
+        expected.retain(2);
+        expected.insert("d");
+        expected.delete(1);
+
+        let r = a.diff_min_equal(&b, 2)?;
+        assert_eq!(r, expected);
+        assert_eq!(r, a.diff(&b, 0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn min_equal_keeps_leading_and_trailing_passes() -> Result<()> {
+        let mut a = Delta::default();
+        a.insert("XaY");
+
+        let mut b = Delta::default();
+        b.insert("XbY");
+
+        let mut expected = Delta::default();
+        expected.retain(1);
+        expected.insert("b");
+        expected.delete(1);
+
+        let r = a.diff_min_equal(&b, 3)?;
+        assert_eq!(r, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn reordered_attributes_passes() -> Result<()> {
+        let mut m1 = AttrMap::default();
+        m1.insert("family", "Helvetica");
+        m1.insert("size", "15px");
+        m1.insert("weight", 400);
+        let mut atr1 = Attributes::default();
+        atr1.insert("font", m1);
+        atr1.insert("bold", true);
+        atr1.insert("color", "red");
+
+        let mut m2 = AttrMap::default();
+        m2.insert("weight", 400);
+        m2.insert("size", "15px");
+        m2.insert("family", "Helvetica");
+        let mut atr2 = Attributes::default();
+        atr2.insert("color", "red");
+        atr2.insert("bold", true);
+        atr2.insert("font", m2);
+
+        let mut a = Delta::default();
+        a.insert_attr("A", atr1);
+        let mut b = Delta::default();
+        b.insert_attr("A", atr2);
+
+        assert_eq!(a.diff(&b, 0)?, Delta::default());
+        Ok(())
+    }
+
+    #[test]
+    fn reordered_embed_passes() -> Result<()> {
+        let mut e1 = OpsMap::default();
+        e1.insert("image", "octocat.png");
+        e1.insert("width", 420);
+        e1.insert("height", 315);
+
+        let mut e2 = OpsMap::default();
+        e2.insert("height", 315);
+        e2.insert("image", "octocat.png");
+        e2.insert("width", 420);
+
+        let mut a = Delta::default();
+        a.insert("A");
+        a.insert(e1);
+        let mut b = Delta::default();
+        b.insert("A");
+        b.insert(e2);
+
+        assert_eq!(a.diff(&b, 0)?, Delta::default());
+        Ok(())
+    }
+
+    #[test]
+    fn granularity_sentence_tokenizer_passes() -> Result<()> {
+        let sentences: &dyn Fn(&str) -> Vec<&str> = &|s| s.split_inclusive(". ").collect();
+
+        let mut a = Delta::default();
+        a.insert("The cat sat. The dog ran. Birds sing.");
+
+        let mut b = Delta::default();
+        b.insert("The cat sat. A dog ran. Birds sing.");
+
+        let mut expected = Delta::default();
+        expected.retain(13);
+        expected.insert("A dog ran. ");
+        expected.delete(13);
+
+        let r = a.diff_granularity(&b, &DiffGranularity::Token(sentences))?;
+        assert_eq!(r, expected);
+        assert_eq!(a.compose(&r)?, b);
+        Ok(())
+    }
+
+    #[test]
+    fn granularity_tokenizer_skipping_text_passes() -> Result<()> {
+        // split_whitespace() drops the white space, which is then compared as tokens of its own
+        let words: &dyn Fn(&str) -> Vec<&str> = &|s| s.split_whitespace().collect();
+
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut a = Delta::default();
+        a.insert("hello  big ");
+        a.insert_attr("world", bold.clone());
+
+        let mut b = Delta::default();
+        b.insert("hello  small ");
+        b.insert_attr("world", bold);
+
+        let mut expected = Delta::default();
+        expected.retain(7);
+        expected.insert("small");
+        expected.delete(3);
+
+        let r = a.diff_granularity(&b, &DiffGranularity::Token(words))?;
+        assert_eq!(r, expected);
+        assert_eq!(a.compose(&r)?, b);
+        Ok(())
+    }
+
+    #[test]
+    fn granularity_word_passes() -> Result<()> {
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut a = Delta::default();
+        a.insert("hello world, ");
+        a.insert(img.clone());
+
+        let mut b = Delta::default();
+        b.insert("help world! ");
+        b.insert(img);
+
+        let mut expected = Delta::default();
+        expected.insert("help");
+        expected.delete(5);
+        expected.retain(6);
+        expected.insert("!");
+        expected.delete(1);
+
+        let r = a.diff_granularity(&b, &DiffGranularity::Word)?;
+        assert_eq!(r, expected);
+        assert_eq!(a.compose(&r)?, b);
+        assert_eq!(
+            a.diff_granularity(&b, &DiffGranularity::Char)?,
+            a.diff(&b, 0)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn equal_run_over_uneven_ops_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut a = Delta::default();
+        a.insert_attr("a", bold);
+        a.insert("bcdef");
+
+        let mut b = Delta::default();
+        b.insert("abcxyz");
+
+        let mut unbold = Attributes::default();
+        unbold.insert("bold", AttrVal::Null);
+        let mut expected = Delta::default();
+        expected.retain_attr(1, unbold);
+        expected.retain(2);
+        expected.insert("xyz");
+        expected.delete(3);
+
+        let r = a.diff(&b, 0)?;
+        assert_eq!(r, expected);
+        assert_eq!(a.compose(&r)?, b);
+        Ok(())
+    }
+
+    #[test]
+    fn diff_text_keeps_formatting_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut a = Delta::default();
+        a.insert("Hello ");
+        a.insert_attr("World", bold.clone());
+
+        let mut expected = Delta::default();
+        expected.retain(6);
+        expected.insert("brave ");
+        expected.retain(5);
+        expected.insert("!");
+
+        let r = a.diff_text("Hello brave World!", 0)?;
+        assert_eq!(r, expected);
+
+        let mut doc = Delta::default();
+        doc.insert("Hello brave ");
+        doc.insert_attr("World", bold);
+        doc.insert("!");
+        assert_eq!(a.compose(&r)?, doc);
+        Ok(())
+    }
+
+    #[test]
+    fn diff_text_deletes_embed_passes() -> Result<()> {
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut a = Delta::default();
+        a.insert("A");
+        a.insert(img);
+        a.insert("B");
+
+        let mut expected = Delta::default();
+        expected.retain(1);
+        expected.delete(1);
+
+        assert_eq!(a.diff_text("AB", 0)?, expected);
+
+        let mut change = Delta::default();
+        change.retain(1);
+        assert!(change.diff_text("A", 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn diff_text_non_ascii_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        // multi-byte text before the edit
+        let mut a = Delta::default();
+        a.insert("h\u{e9}llo\n");
+        let r = a.diff_text("h\u{e9}llo!\n", 0)?;
+        let mut expected = Delta::default();
+        expected.retain("h\u{e9}llo".len());
+        expected.insert("!");
+        assert_eq!(r, expected);
+        let mut doc = Delta::default();
+        doc.insert("h\u{e9}llo!\n");
+        assert_eq!(a.compose(&r)?, doc);
+
+        // multi-byte text inside the edit, and after it
+        let mut a = Delta::default();
+        a.insert_attr("cr\u{e8}me ", bold.clone());
+        a.insert("br\u{fb}l\u{e9}e \u{1f370}");
+        let new_text = "cr\u{e8}me \u{e0} la br\u{fb}l\u{e9}e \u{1f370}!";
+        let r = a.diff_text(new_text, "cr\u{e8}me \u{e0} la ".len())?;
+        let mut doc = Delta::default();
+        doc.insert_attr("cr\u{e8}me ", bold.clone());
+        doc.insert("\u{e0} la br\u{fb}l\u{e9}e \u{1f370}!");
+        assert_eq!(a.compose(&r)?, doc);
+
+        let r = a.diff_text("cr\u{e8}me \u{1f370}", 0)?;
+        let mut doc = Delta::default();
+        doc.insert_attr("cr\u{e8}me ", bold);
+        doc.insert("\u{1f370}");
+        assert_eq!(a.compose(&r)?, doc);
+        Ok(())
+    }
+
+    #[test]
+    fn diff_text_cursor_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut a = Delta::default();
+        a.insert("\u{e9}");
+        a.insert_attr("\u{e9}", bold);
+
+        // typing a third "é" either before or after the bold one
+        let mut expected = Delta::default();
+        expected.insert("\u{e9}");
+        assert_eq!(a.diff_text("\u{e9}\u{e9}\u{e9}", 2)?, expected);
+
+        let mut expected = Delta::default();
+        expected.retain(4);
+        expected.insert("\u{e9}");
+        assert_eq!(a.diff_text("\u{e9}\u{e9}\u{e9}", 6)?, expected);
+
+        // deleting one of them, the cursor is where it was
+        let mut expected = Delta::default();
+        expected.delete(2);
+        assert_eq!(a.diff_text("\u{e9}", 0)?, expected);
+
+        let mut expected = Delta::default();
+        expected.retain(2);
+        expected.delete(2);
+        assert_eq!(a.diff_text("\u{e9}", 2)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn embed_placeholder_literal_round_trip_passes() -> Result<()> {
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        // text matching the former placeholder next to embeds
+        let mut a = Delta::default();
+        a.insert("\0x");
+        a.insert(img.clone());
+        a.insert("\0y");
+
+        let mut b = Delta::default();
+        b.insert("\0");
+        b.insert(1);
+        b.insert("\0\0");
+        b.insert(img.clone());
+        b.insert("y\0");
+
+        assert_eq!(a.compose(&a.diff(&b, 0)?)?, b);
+        assert_eq!(b.compose(&b.diff(&a, 0)?)?, a);
+        assert_eq!(a.compose(&a.diff_min_equal(&b, 2)?)?, b);
+        assert_eq!(
+            a.compose(&a.diff_granularity(&b, &DiffGranularity::Word)?)?,
+            b
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn embed_distinct_placeholders_passes() -> Result<()> {
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut a = Delta::default();
+        a.insert(img.clone());
+
+        let mut b = Delta::default();
+        b.insert(1);
+        b.insert(img);
+
+        // the embeds are told apart, so the image is retained
+        let mut expected = Delta::default();
+        expected.insert(1);
+        assert_eq!(a.diff(&b, 0)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn explicit_embeds_passes() -> Result<()> {
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut a = Delta::default();
+        a.insert("Hello");
+        a.insert(img.clone());
+        a.insert("World");
+        a.insert(img.clone());
+
+        let mut b = Delta::default();
+        b.insert("Hello");
+        b.insert(img.clone());
+        b.insert("World!");
+        b.insert(img.clone());
+
+        // diff() merges the retained embed, and drops the trailing one
+        let mut expected = Delta::default();
+        expected.retain(11);
+        expected.insert("!");
+        assert_eq!(a.diff(&b, 0)?, expected);
+
+        let expected = Delta::new(vec![
+            DeltaOperation::retain(5),
+            DeltaOperation::retain(1),
+            DeltaOperation::retain(5),
+            DeltaOperation::insert("!"),
+            DeltaOperation::retain(1),
+        ]);
+        let diff = a.diff_explicit_embeds(&b)?;
+        assert_eq!(diff, expected);
+        assert_eq!(a.compose(&diff)?, b);
+        Ok(())
+    }
+
+    #[test]
+    fn explicit_embeds_formatted_passes() -> Result<()> {
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut a = Delta::default();
+        a.insert("ab");
+        a.insert(img.clone());
+        a.insert("cd");
+
+        let mut b = Delta::default();
+        b.insert_attr("ab", bold.clone());
+        b.insert_attr(img, bold.clone());
+        b.insert_attr("c", bold.clone());
+        b.insert("d");
+
+        let expected = Delta::new(vec![
+            DeltaOperation::retain_attr(2, bold.clone()),
+            DeltaOperation::retain_attr(1, bold.clone()),
+            DeltaOperation::retain_attr(1, bold),
+        ]);
+        let diff = a.diff_explicit_embeds(&b)?;
+        assert_eq!(diff, expected);
+        assert_eq!(a.compose(&diff)?, b);
+
+        // no embeds: same as diff()
+        let mut c = Delta::default();
+        c.insert("abcd");
+        let mut d = Delta::default();
+        d.insert("abXd");
+        assert_eq!(c.diff_explicit_embeds(&d)?, c.diff(&d, 0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn no_clears_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut bold_italic = bold.clone();
+        bold_italic.insert("italic", true);
+        let mut red = Attributes::default();
+        red.insert("color", "red");
+
+        // "Hello" loses italic, " World" loses bold and turns red
+        let mut a = Delta::default();
+        a.insert_attr("Hello", bold_italic);
+        a.insert_attr(" World", bold.clone());
+        let mut b = Delta::default();
+        b.insert_attr("Hello", bold.clone());
+        b.insert_attr(" World", red.clone());
+
+        let mut no_italic = Attributes::default();
+        no_italic.insert("italic", AttrVal::Null);
+        let mut no_bold_red = red.clone();
+        no_bold_red.insert("bold", AttrVal::Null);
+        let mut expected = Delta::default();
+        expected.retain_attr(5, no_italic);
+        expected.retain_attr(6, no_bold_red);
+        let diff = a.diff(&b, 0)?;
+        assert_eq!(diff, expected);
+        assert_eq!(a.compose(&diff)?, b);
+
+        // the clears are left out, the new color is kept
+        let mut expected = Delta::default();
+        expected.retain(5);
+        expected.retain_attr(6, red);
+        let diff = a.diff_no_clears(&b)?;
+        assert_eq!(diff, expected);
+
+        let mut bold_red = bold.clone();
+        bold_red.insert("color", "red");
+        let mut composed = Delta::default();
+        composed.insert_attr("Hello", a.first().unwrap().get_attributes().clone());
+        composed.insert_attr(" World", bold_red);
+        assert_eq!(a.compose(&diff)?, composed);
+
+        // only clears: no change at all
+        let mut plain = Delta::default();
+        plain.insert("Hello World");
+        assert!(a.diff_no_clears(&plain)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn edit_distance_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut a = Delta::default();
+        a.insert("Hello ");
+        a.insert_attr("World", bold.clone());
+        a.insert(img.clone());
+
+        // identical text, attributes are ignored
+        let mut b = Delta::default();
+        b.insert_attr("Hello World", bold);
+        b.insert(img);
+        assert_eq!(a.edit_distance(&a)?, 0);
+        assert_eq!(a.edit_distance(&b)?, 0);
+
+        // one char changed: delete + insert
+        let mut c = Delta::default();
+        c.insert("Hello Wxrld");
+        assert_eq!(a.edit_distance(&c)?, 3);
+        let mut d = Delta::default();
+        d.insert("Hello Wxrld");
+        d.insert(1);
+        assert_eq!(c.edit_distance(&d)?, 1);
+        let mut e = Delta::default();
+        e.insert("Hello World");
+        assert_eq!(c.edit_distance(&e)?, 2);
+        assert_eq!(e.edit_distance(&c)?, 2);
+
+        // disjoint documents: delete all, insert all
+        let mut f = Delta::default();
+        f.insert("abc");
+        let mut g = Delta::default();
+        g.insert("wxyz");
+        assert_eq!(f.edit_distance(&g)?, 7);
+        assert_eq!(f.edit_distance(&Delta::default())?, 3);
+
+        let mut change = Delta::default();
+        change.retain(1);
+        assert!(change.edit_distance(&f).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn morph_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut a = Delta::default();
+        a.insert("The quick ");
+        a.insert_attr("brown", bold.clone());
+        a.insert(img.clone());
+        a.insert(" fox\n");
+
+        let mut b = Delta::default();
+        b.insert_attr("The", bold);
+        b.insert(" slow ");
+        b.insert(img);
+        b.insert(" fox jumps\n");
+
+        let change = a.morph_into(&b, 0)?;
+        assert_eq!(change, a.diff(&b, 0)?);
+        assert_eq!(a.compose(&change)?, b);
+
+        let mut morphed = a.clone();
+        morphed.morph(&b)?;
+        assert_eq!(morphed, b);
+        morphed.morph(&a)?;
+        assert_eq!(morphed, a);
+
+        let mut change = Delta::default();
+        change.retain(1);
+        assert!(change.morph(&a).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn non_ascii_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut a = Delta::default();
+        a.insert("Caf\u{e9} ");
+        a.insert_attr("cr\u{e8}me", bold.clone());
+        a.insert(" \u{1f370}!");
+
+        let mut b = Delta::default();
+        b.insert_attr("Caf\u{e9} cr\u{e8}me", bold);
+        b.insert(" br\u{fb}l\u{e9}e \u{1f370}");
+
+        assert!(a.diff(&a, 0)?.is_empty());
+        let diff = a.diff(&b, 0)?;
+        assert_eq!(a.compose(&diff)?, b);
+        assert_eq!(b.compose(&b.diff(&a, 0)?)?, a);
+        Ok(())
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn normalized_passes() -> Result<()> {
+        // "crème brûlée" precomposed, and with combining accents
+        let nfc_text = "cr\u{e8}me br\u{fb}l\u{e9}e";
+        let nfd_text = "cre\u{300}me bru\u{302}le\u{301}e";
+        let mut nfc = Delta::default();
+        nfc.insert(nfc_text);
+        let mut nfd = Delta::default();
+        nfd.insert(nfd_text);
+
+        assert!(!nfc.diff(&nfd, 0)?.is_empty());
+        assert!(nfc.diff_normalized(&nfd, 0)?.is_empty());
+        assert!(nfd.diff_normalized(&nfc, 0)?.is_empty());
+        assert_eq!(nfd.normalized(), nfc);
+
+        // offsets are in the normalized text
+        let mut plural = Delta::default();
+        plural.insert("cr\u{e8}mes br\u{fb}l\u{e9}es");
+        let diff = nfd.diff_normalized(&plural, 0)?;
+        let mut expected = Delta::default();
+        expected.retain("cr\u{e8}me".len());
+        expected.insert("s");
+        expected.retain(" br\u{fb}l\u{e9}e".len());
+        expected.insert("s");
+        assert_eq!(diff, expected);
+        assert_eq!(nfd.normalized().compose(&diff)?, plural);
+        Ok(())
+    }
+
+    #[test]
+    fn capped_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut a = Delta::default();
+        a.insert("abcdefghij");
+        let mut b = Delta::default();
+        b.insert("aXcXeXgXiX");
+        b.insert_attr("!", bold.clone());
+
+        // a highly fragmented diff falls back to replacing everything
+        let diff = a.diff(&b, 0)?;
+        assert!(diff.len() > 10);
+        assert_eq!(a.diff_capped(&b, diff.len())?, diff);
+
+        let capped = a.diff_capped(&b, 10)?;
+        let mut expected = Delta::default();
+        expected.insert("aXcXeXgXiX");
+        expected.insert_attr("!", bold);
+        expected.delete(10);
+        assert_eq!(capped, expected);
+        assert_eq!(a.compose(&capped)?, b);
+
+        assert_eq!(a.diff_capped(&Delta::default(), 0)?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn capped_formatted_other_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut a = Delta::default();
+        a.insert("abcdefghij");
+        let mut b = Delta::default();
+        b.insert("aXcXeXgXiX");
+        b.insert_attr("!", bold.clone());
+        b.insert("?");
+
+        // other has more than max_ops - 1 operations, so the fallback exceeds max_ops
+        let capped = a.diff_capped(&b, 2)?;
+        let mut expected = Delta::default();
+        expected.insert("aXcXeXgXiX");
+        expected.insert_attr("!", bold.clone());
+        expected.insert("?");
+        expected.delete(10);
+        assert_eq!(capped, expected);
+        assert!(capped.len() > 2);
+        assert_eq!(a.compose(&capped)?, b);
+
+        // a fallback longer than the diff is not used
+        let mut b = Delta::default();
+        for c in ["a", "b", "c", "d", "e", "f"] {
+            b.insert(c);
+            b.insert_attr(c, bold.clone());
+        }
+        let mut a = Delta::default();
+        a.insert("aabbccddeeff");
+        let diff = a.diff(&b, 0)?;
+        assert!(diff.len() > 2 && diff.len() < b.len() + 1);
+        assert_eq!(a.diff_capped(&b, 2)?, diff);
+        Ok(())
+    }
+
+    #[test]
+    fn bidirectional_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut a = Delta::default();
+        a.insert("Hello ");
+        a.insert(img.clone());
+        a.insert(" World\n");
+        let mut b = Delta::default();
+        b.insert_attr("Hallo", bold);
+        b.insert(" World");
+        b.insert(img);
+        b.insert("!\n");
+
+        let (forward, backward) = a.bidirectional_diff(&b)?;
+        assert_eq!(forward, a.diff(&b, 0)?);
+        assert_eq!(a.compose(&forward)?, b);
+        assert_eq!(b.compose(&backward)?, a);
+
+        let (forward, backward) = a.bidirectional_diff(&a)?;
+        assert!(forward.is_empty() && backward.is_empty());
+
+        let mut change = Delta::default();
+        change.retain(1);
+        assert!(a.bidirectional_diff(&change).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn patch_string_passes() -> Result<()> {
+        let mut header = Attributes::default();
+        header.insert("header", 1);
+
+        let mut a = Delta::default();
+        a.insert("Title");
+        a.insert_attr("\n", header.clone());
+        a.insert("First paragraph.\n\n1\n2\n3\n4\n5\n6\n7\n\nSecond paragraph.\n");
+        let mut b = Delta::default();
+        b.insert("Title");
+        b.insert_attr("\n", header);
+        b.insert("First paragraph, edited.\n\n1\n2\n3\n4\n5\n6\n7\n\n");
+        b.insert("Second paragraph.\nA new third one.\n");
+
+        let expected = "\
+@@ -1,5 +1,5 @@
+ Title
+-First paragraph.
++First paragraph, edited.
+ 
+ 1
+ 2
+@@ -10,3 +10,4 @@
+ 7
+ 
+ Second paragraph.
++A new third one.
+";
+        assert_eq!(a.patch_string(&b)?, expected);
+        assert_eq!(a.patch_string(&a)?, "");
+
+        // a formatting change replaces the line by the same text
+        let mut c = Delta::default();
+        c.insert("Title\nFirst paragraph.\n");
+        assert_eq!(
+            a.patch_string(&c)?.lines().take(4).collect::<Vec<_>>(),
+            ["@@ -1,12 +1,2 @@", "-Title", "+Title", " First paragraph."]
+        );
+
+        let mut change = Delta::default();
+        change.retain(1);
+        assert!(a.patch_string(&change).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn attribute_aware_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut a = Delta::default();
+        a.insert("Hello World\n");
+        let mut b = Delta::default();
+        b.insert("Hello ");
+        b.insert_attr("World", bold.clone());
+        b.insert("\n");
+
+        // equal text gives a retain with the formatting change
+        let text_only = a.diff(&b, 0)?;
+        let mut expected = Delta::default();
+        expected.retain(6);
+        expected.retain_attr(5, bold.clone());
+        assert_eq!(text_only, expected);
+
+        // with attributes in the equality, the formatted text is replaced
+        let attribute_aware = a.diff_attribute_aware(&b)?;
+        let mut expected = Delta::default();
+        expected.retain(6);
+        expected.insert_attr("World", bold);
+        expected.delete(5);
+        assert_eq!(attribute_aware, expected);
+        assert_eq!(a.compose(&attribute_aware)?, b);
+        assert_eq!(a.compose(&text_only)?, b);
+
+        assert!(a.diff_attribute_aware(&a)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn trimmed_prefix_suffix_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let prefix = "Lorem ipsum dolor sit amet, äöü ".repeat(2_000);
+        let suffix = " consectetur adipiscing elit. 😀".repeat(2_000);
+        let mut a = Delta::default();
+        a.insert(prefix.as_str());
+        a.insert(img.clone());
+        a.insert("cat");
+        a.insert(suffix.as_str());
+        let mut b = Delta::default();
+        b.insert(prefix.as_str());
+        b.insert(img);
+        b.insert("dog");
+        b.insert(suffix.as_str());
+
+        // only the middle edit, positions count bytes
+        let diff = a.diff(&b, 0)?;
+        let mut expected = Delta::default();
+        expected.retain(prefix.len() + 1);
+        expected.insert("dog");
+        expected.delete(3);
+        assert_eq!(diff, expected);
+        assert_eq!(a.compose(&diff)?, b);
+
+        // a formatting change in the shared text still gives a retain with attributes
+        let mut c = Delta::default();
+        c.insert(prefix.as_str());
+        c.insert_attr("Lorem", bold.clone());
+        c.insert(prefix.as_str());
+        let mut d = c.clone();
+        d.insert("!");
+        let mut e = Delta::default();
+        e.insert(prefix.as_str());
+        e.insert("Lorem");
+        e.insert(prefix.as_str());
+        e.insert("!");
+        let diff = e.diff(&c, 0)?;
+        let mut expected = Delta::default();
+        expected.retain(prefix.len());
+        expected.retain_attr(5, bold);
+        expected.retain(prefix.len());
+        expected.delete(1);
+        assert_eq!(diff, expected);
+        assert_eq!(c.diff(&d, 0)?.len(), 2);
+
+        // edits at the very start and end
+        let mut f = Delta::default();
+        f.insert("X");
+        f.insert(suffix.as_str());
+        let mut g = Delta::default();
+        g.insert(suffix.as_str());
+        g.insert("Y");
+        let diff = f.diff(&g, 0)?;
+        let mut expected = Delta::default();
+        expected.delete(1);
+        expected.retain(suffix.len());
+        expected.insert("Y");
+        assert_eq!(diff, expected);
+        Ok(())
+    }
 }
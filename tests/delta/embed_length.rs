@@ -0,0 +1,219 @@
+#[cfg(test)]
+mod tests {
+    use delta::attributes::Attributes;
+    use delta::delta::Delta;
+    use delta::document::Document;
+    use delta::operations::{OpsMap, OpsVal};
+    use delta::optransform::OpTransform;
+    use delta::utils::DeltaTransformations;
+
+    // "table" embeds span the number of cells they hold
+    fn cells(embed: &OpsVal) -> usize {
+        let Ok(map) = embed.map_val() else {
+            return 1;
+        };
+        map.get("table")
+            .and_then(|cells| cells.number_val().ok())
+            .unwrap_or(1)
+    }
+
+    fn is_split<T>(res: Result<T, impl ToString>) -> bool {
+        res.is_err_and(|err| err.to_string().contains("splits the embed"))
+    }
+
+    fn table() -> OpsMap {
+        let mut table = OpsMap::default();
+        table.insert("table", 3);
+        table
+    }
+
+    fn document() -> Delta {
+        let mut doc = Delta::with_embed_length(cells);
+        doc.insert("ab");
+        doc.insert(table());
+        doc.insert("cd");
+        doc
+    }
+
+    #[test]
+    fn embed_length_passes() {
+        let doc = document();
+        assert_eq!(doc.len_of(doc.get(1).unwrap()), 3);
+        assert_eq!(doc.get(1).unwrap().op_len(), 1);
+        assert_eq!(doc.delta_length(), 7);
+        assert_eq!(doc.document_length(), 7);
+
+        // other embeds keep length 1
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+        let mut doc = Delta::with_embed_length(cells);
+        doc.insert(img);
+        doc.insert(1);
+        assert_eq!(doc.delta_length(), 2);
+
+        // the length is a property of the delta, not of the embed
+        let mut doc = Delta::default();
+        doc.insert(table());
+        assert_eq!(doc.document_length(), 1);
+        assert_eq!(doc, {
+            let mut other = Delta::with_embed_length(cells);
+            other.insert(table());
+            other
+        });
+    }
+
+    #[test]
+    fn embed_length_slice_passes() {
+        let doc = document();
+
+        let mut expected = Delta::default();
+        expected.insert(table());
+        assert_eq!(doc.slice(2, 5), expected);
+
+        let mut expected = Delta::default();
+        expected.insert("ab");
+        assert_eq!(doc.slice(0, 2), expected);
+
+        let mut expected = Delta::default();
+        expected.insert("d");
+        assert_eq!(doc.slice(6, 7), expected);
+
+        // an embed is part of the slice it starts in
+        let mut expected = Delta::default();
+        expected.insert("b");
+        expected.insert(table());
+        assert_eq!(doc.slice(1, 3), expected);
+        let mut expected = Delta::default();
+        expected.insert("c");
+        assert_eq!(doc.slice(3, 6), expected);
+
+        assert_eq!(doc.chunk(5).len(), 2);
+        // chunks never end within an embed
+        let mut expected = Delta::default();
+        expected.insert(table());
+        assert_eq!(doc.chunk(2)[1], expected);
+        assert_eq!(doc.chunk(4)[0].document_length(), 2);
+        assert_eq!(doc.slice(0, 5).document_length(), 5);
+    }
+
+    #[test]
+    fn embed_length_compose_passes() -> anyhow::Result<()> {
+        let doc = document();
+
+        let mut change = Delta::default();
+        change.retain(5);
+        change.insert("X");
+
+        let mut expected = Delta::default();
+        expected.insert("ab");
+        expected.insert(table());
+        expected.insert("Xcd");
+        assert_eq!(doc.compose(&change)?, expected);
+
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut change = Delta::default();
+        change.retain(2);
+        change.retain_attr(3, bold.clone());
+
+        let mut expected = Delta::default();
+        expected.insert("ab");
+        expected.insert_attr(table(), bold);
+        expected.insert("cd");
+        assert_eq!(doc.compose(&change)?, expected);
+
+        let mut change = Delta::default();
+        change.retain(2);
+        change.delete(3);
+
+        let mut expected = Delta::default();
+        expected.insert("abcd");
+        assert_eq!(doc.compose(&change)?, expected);
+
+        // the inverse restores the embed
+        assert_eq!(doc.compose(&change)?.compose(&change.invert(&doc))?, doc);
+
+        // the result measures its embeds like the document
+        let mut change = Delta::default();
+        change.retain(5);
+        change.insert("X");
+        let once = doc.compose(&change)?;
+        assert_eq!(once.document_length(), 8);
+        let mut expected = Delta::default();
+        expected.insert("ab");
+        expected.insert(table());
+        expected.insert("XXcd");
+        assert_eq!(once.compose(&change)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn embed_length_split_fails() {
+        let doc = document();
+
+        let mut delete = Delta::default();
+        delete.retain(2);
+        delete.delete(1);
+        let err = doc.compose(&delete).unwrap_err();
+        assert!(
+            err.to_string().contains("splits the embed of length 3"),
+            "{err}"
+        );
+
+        let mut insert = Delta::default();
+        insert.retain(3);
+        insert.insert("!");
+        assert!(is_split(doc.compose(&insert)));
+
+        // also when the embed is the first operation
+        let mut doc = Delta::with_embed_length(cells);
+        doc.insert(table());
+        let mut insert = Delta::default();
+        insert.retain(1);
+        insert.insert("!");
+        assert!(is_split(doc.compose(&insert)));
+        let mut delete = Delta::default();
+        delete.delete(1);
+        assert!(is_split(doc.compose(&delete)));
+
+        assert!(is_split(document().split_at(4)));
+    }
+
+    #[test]
+    fn embed_length_diff_passes() -> anyhow::Result<()> {
+        let doc = document();
+
+        let mut other = Delta::default();
+        other.insert("abX");
+        other.insert(table());
+        other.insert("c");
+
+        let change = doc.diff(&other, 0)?;
+        assert_eq!(doc.compose(&change)?, other);
+        Ok(())
+    }
+
+    #[test]
+    fn embed_length_apply_with_inverse_passes() -> anyhow::Result<()> {
+        let doc = document();
+
+        let mut change = Delta::default();
+        change.retain(1);
+        change.delete(5);
+        change.insert("X");
+
+        let (result, inverse) = doc.apply_with_inverse(&change)?;
+        assert_eq!(result, doc.compose(&change)?);
+        assert_eq!(result.compose(&inverse)?, doc);
+
+        // a change ending within the embed fails instead of underflowing
+        let mut partial = Delta::default();
+        partial.retain(3);
+        partial.delete(1);
+        assert!(is_split(doc.apply_with_inverse(&partial)));
+        let mut undoable = doc.clone();
+        assert!(is_split(undoable.apply_undoable(&partial)));
+        assert_eq!(undoable, doc);
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests {
+    use delta::attributes::Attributes;
+    use delta::delta::Delta;
+    use delta::document::Document;
+    use delta::operations::{set_embed_merge, OpsMap, OpsVal, EMBED_MERGE_KEY};
+    use delta::optransform::OpTransform;
+    use delta::types::attr_val::AttrVal;
+
+    // The registration is process wide, only "image" embeds are merged,
+    // so other tests running in parallel are not affected.
+    fn register() {
+        set_embed_merge(|embed: &OpsVal, instruction: &AttrVal| {
+            let (Ok(image), Ok(fields)) = (embed.map_val(), instruction.map_val()) else {
+                return embed.clone();
+            };
+            if !image.contains_key("image") {
+                return embed.clone();
+            }
+            let mut merged = image.clone();
+            for (key, value) in fields.iter() {
+                merged.insert(key.clone(), value.clone());
+            }
+            OpsVal::Map(merged)
+        });
+    }
+
+    fn image(width: usize) -> OpsMap {
+        let mut image = OpsMap::default();
+        image.insert("image", "octocat.png");
+        image.insert("width", width);
+        image
+    }
+
+    fn resize(width: usize) -> Attributes {
+        let mut fields = OpsMap::default();
+        fields.insert("width", width);
+        let mut attr = Attributes::default();
+        attr.insert(EMBED_MERGE_KEY, fields);
+        attr
+    }
+
+    #[test]
+    fn embed_merge_passes() -> anyhow::Result<()> {
+        register();
+        let mut alt = Attributes::default();
+        alt.insert("alt", "Octocat");
+
+        let mut doc = Delta::default();
+        doc.insert("ab");
+        doc.insert_attr(image(100), alt.clone());
+        doc.insert("c");
+
+        // the width is merged into the embed, other attributes format it as usual
+        let mut attr = resize(200);
+        attr.insert("bold", true);
+        let mut change = Delta::default();
+        change.retain(2);
+        change.retain_attr(1, attr);
+
+        let mut formatted = alt;
+        formatted.insert("bold", true);
+        let mut expected = Delta::default();
+        expected.insert("ab");
+        expected.insert_attr(image(200), formatted);
+        expected.insert("c");
+        assert_eq!(doc.compose(&change)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn embed_merge_on_text_passes() -> anyhow::Result<()> {
+        register();
+        let mut doc = Delta::default();
+        doc.insert("ab");
+
+        // the key is an ordinary attribute on text, and on retains of a change
+        let mut change = Delta::default();
+        change.retain_attr(2, resize(200));
+        let mut expected = Delta::default();
+        expected.insert_attr("ab", resize(200));
+        assert_eq!(doc.compose(&change)?, expected);
+
+        let mut retain = Delta::default();
+        retain.retain(2);
+        assert_eq!(retain.compose(&change)?, change);
+        Ok(())
+    }
+
+    #[test]
+    fn embed_merge_inverse_passes() -> anyhow::Result<()> {
+        register();
+        let mut alt = Attributes::default();
+        alt.insert("alt", "Octocat");
+
+        let mut doc = Delta::default();
+        doc.insert("ab");
+        doc.insert_attr(image(100), alt);
+        doc.insert("c");
+
+        let mut attr = resize(200);
+        attr.insert("bold", true);
+        let mut change = Delta::default();
+        change.retain(2);
+        change.retain_attr(1, attr);
+
+        // the inverse puts back the original embed, with its attributes
+        let (result, inverse) = doc.apply_with_inverse(&change)?;
+        assert_eq!(result, doc.compose(&change)?);
+        assert_eq!(inverse, change.invert(&doc));
+        assert_eq!(result.compose(&inverse)?, doc);
+        assert_eq!(change.invert_range(&doc, 0, 4)?, inverse);
+
+        let mut undoable = doc.clone();
+        let undo = undoable.apply_undoable(&change)?;
+        assert_eq!(undoable, result);
+        undoable.apply_undoable(&undo)?;
+        assert_eq!(undoable, doc);
+        Ok(())
+    }
+}
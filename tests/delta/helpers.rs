@@ -2,10 +2,12 @@
 mod tests {
     use delta::attributes::Attributes;
     use delta::delta::Delta;
-    use delta::document::Document;
-    use delta::operations::{DeltaOperation, OpsMap};
+    use delta::document::{Document, EachLineOptions, TriState};
+    use delta::operations::{DeltaOperation, OpsMap, OpsVal};
+    use delta::optransform::OpTransform;
     use delta::types::attr_val::AttrVal;
     use delta::utils::DeltaTransformations;
+    use std::cell::RefCell;
 
     #[test]
     fn helper_concat_passes() {
@@ -58,6 +60,91 @@ mod tests {
         assert_eq!(c, expected);
     }
 
+    #[test]
+    fn helper_try_concat_passes() -> anyhow::Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut a = Delta::default();
+        a.insert_attr("Test", bold.clone());
+
+        let mut concat = Delta::default();
+        concat.insert_attr("!", bold.clone());
+        concat.insert("?");
+
+        let mut expected = Delta::default();
+        expected.insert_attr("Test!", bold);
+        expected.insert("?");
+
+        a.try_concat(concat)?;
+        assert_eq!(a, expected);
+
+        a.try_concat(Delta::default())?;
+        assert_eq!(a, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn helper_try_concat_mismatch_passes() {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut a = Delta::default();
+        a.insert("Test");
+
+        let mut concat = Delta::default();
+        concat.insert_attr("!", bold);
+
+        let expected = a.clone();
+        assert!(a.try_concat(concat).is_err());
+        assert_eq!(a, expected);
+    }
+
+    #[test]
+    fn helper_append_block_passes() {
+        let mut header = Attributes::default();
+        header.insert("header", 1);
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut block = Delta::default();
+        block.insert("Text\n");
+
+        // ends with a new line: plain concat
+        let mut doc = Delta::default();
+        doc.insert("Title");
+        doc.insert_attr("\n", header.clone());
+        doc.append_block(block.clone());
+        let mut expected = Delta::default();
+        expected.insert("Title");
+        expected.insert_attr("\n", header);
+        expected.insert("Text\n");
+        assert_eq!(doc, expected);
+
+        // no new line at the end: one is inserted, and merges with the text
+        let mut doc = Delta::default();
+        doc.insert("Title");
+        doc.append_block(block.clone());
+        let mut expected = Delta::default();
+        expected.insert("Title\nText\n");
+        assert_eq!(doc, expected);
+        assert_eq!(doc.to_lines().unwrap().len(), 2);
+
+        // an embed ends the line
+        let mut doc = Delta::default();
+        doc.insert(img.clone());
+        doc.append_block(block.clone());
+        let mut expected = Delta::default();
+        expected.insert(img);
+        expected.insert("\nText\n");
+        assert_eq!(doc, expected);
+
+        // nothing to separate
+        let mut doc = Delta::default();
+        doc.append_block(block.clone());
+        assert_eq!(doc, block);
+    }
+
     #[test]
     fn helper_eachline_passes() -> anyhow::Result<()> {
         let mut bold = Attributes::default();
@@ -100,7 +187,7 @@ mod tests {
             //log::debug!( "line {:?} attribs {:?}",line, attr);
             assert_eq!(delta, expected.get(line).unwrap());
             assert_eq!(attr, attributes.get(line).unwrap());
-            return true;
+            true
         };
         a.each_line(p, None)?;
         Ok(())
@@ -126,7 +213,7 @@ mod tests {
             //log::debug!( "line {:?} delta: {:?}", line, delta);
             //log::debug!( "line {:?} attribs {:?}",line, attr);
             assert_eq!(delta, expected.get(line).unwrap());
-            return true;
+            true
         };
         a.each_line(p, None)?;
         Ok(())
@@ -143,7 +230,7 @@ mod tests {
             //log::debug!( "line {:?} attribs {:?}",line, attr);
             //hey we should never have called the predicate !!
             assert_eq!(true, false);
-            return true;
+            true
         };
         a.each_line(p, None)?;
         Ok(())
@@ -169,7 +256,7 @@ mod tests {
             //log::debug!( "line {:?} delta: {:?}", line, delta);
             //log::debug!( "line {:?} attribs {:?}",line, attr);
             assert_eq!(delta, expected.get(line).unwrap());
-            return true;
+            true
         };
         a.each_line(p, None)?;
         Ok(())
@@ -191,12 +278,12 @@ mod tests {
         e2.insert("World!");
         let f = |delta: &DeltaOperation, _index: usize| -> bool {
             //log::debug!( "line {:?} delta: {:?}", index, delta);
-            return delta.insert_value().is_string();
+            delta.insert_value().is_string()
         };
         let r = a.filter(f);
         assert_eq!(r.len(), 2);
-        assert_eq!(r.get(0).unwrap(), e1.get(0).unwrap());
-        assert_eq!(r.get(1).unwrap(), e2.get(0).unwrap());
+        assert_eq!(r.first().unwrap(), e1.first().unwrap());
+        assert_eq!(r.get(1).unwrap(), e2.first().unwrap());
         //dbg!(r);
     }
 
@@ -245,6 +332,769 @@ mod tests {
         assert_eq!(delta.document_length(), 1);
     }
 
+    #[test]
+    fn helper_to_document_passes() {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut change = Delta::default();
+        change.retain(2);
+        change.insert_attr("AB", bold.clone());
+        change.delete(3);
+        change.retain_attr(4, bold.clone());
+        change.insert_attr("CD", bold.clone());
+        change.insert(img.clone());
+
+        let mut expected = Delta::default();
+        expected.insert_attr("ABCD", bold);
+        expected.insert(img);
+
+        let doc = change.to_document();
+        assert_eq!(doc, expected);
+        // a document is left as is
+        assert_eq!(doc.to_document(), doc);
+    }
+
+    #[test]
+    fn helper_to_document_no_inserts_passes() {
+        let mut change = Delta::default();
+        change.retain(2);
+        change.delete(3);
+
+        assert!(change.to_document().is_empty());
+    }
+
+    #[test]
+    fn helper_format_inserted_passes() -> anyhow::Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut italic = Attributes::default();
+        italic.insert("italic", true);
+
+        let mut doc = Delta::default();
+        doc.insert("Hello ");
+        doc.insert_attr("World", italic.clone());
+        doc.insert(1);
+
+        // spans the boundary between the plain and the italic text
+        let mut formatted = doc.clone();
+        formatted.format_inserted(4, 4, bold.clone())?;
+
+        let mut both = italic.clone();
+        both.insert("bold", true);
+        let mut expected = Delta::default();
+        expected.insert("Hell");
+        expected.insert_attr("o ", bold.clone());
+        expected.insert_attr("Wo", both);
+        expected.insert_attr("rld", italic.clone());
+        expected.insert(1);
+        assert_eq!(formatted, expected);
+
+        // same result as composing the format change
+        let mut change = Delta::default();
+        change.retain(4);
+        change.retain_attr(4, bold.clone());
+        assert_eq!(formatted, doc.compose(&change)?);
+
+        // null removes the attribute
+        let mut no_italic = Attributes::default();
+        no_italic.insert("italic", AttrVal::Null);
+        let mut formatted = doc.clone();
+        formatted.format_inserted(6, 5, no_italic)?;
+        let mut expected = Delta::default();
+        expected.insert("Hello World");
+        expected.insert(1);
+        assert_eq!(formatted, expected);
+
+        // an empty range leaves the document as is
+        let mut formatted = doc.clone();
+        formatted.format_inserted(3, 0, bold)?;
+        assert_eq!(formatted, doc);
+        Ok(())
+    }
+
+    #[test]
+    fn helper_format_inserted_non_document_passes() {
+        let mut change = Delta::default();
+        change.retain(2);
+        change.insert("A");
+        assert!(change.format_inserted(0, 1, Attributes::default()).is_err());
+    }
+
+    #[test]
+    fn helper_ops_with_offsets_passes() {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut delta = Delta::default();
+        delta.insert_attr("AB", bold.clone());
+        delta.insert(1);
+        delta.retain_attr(3, bold);
+        delta.delete(2);
+        delta.insert("CDE");
+
+        // push() reorders the insert before the delete
+        let offsets: Vec<(usize, usize)> = delta
+            .ops_with_offsets()
+            .map(|(offset, op)| (offset, op.op_len()))
+            .collect();
+        assert_eq!(offsets, vec![(0, 2), (2, 1), (3, 3), (6, 3), (9, 2)]);
+
+        assert_eq!(Delta::default().ops_with_offsets().count(), 0);
+    }
+
+    #[test]
+    fn helper_is_normalized_passes() {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut delta = Delta::default();
+        delta.retain(2);
+        delta.insert("Hello");
+        delta.insert(img.clone());
+        delta.insert(img.clone());
+        delta.delete(3);
+        delta.retain_attr(4, bold.clone());
+        assert!(delta.is_normalized());
+        assert!(Delta::default().is_normalized());
+
+        // trailing retain without attributes
+        let mut trailing = delta.clone();
+        trailing.retain(1);
+        assert!(!trailing.is_normalized());
+
+        let unmerged = [
+            vec![DeltaOperation::insert("Hel"), DeltaOperation::insert("lo")],
+            vec![
+                DeltaOperation::retain(1),
+                DeltaOperation::retain(1),
+                DeltaOperation::delete(1),
+            ],
+            vec![DeltaOperation::delete(1), DeltaOperation::delete(1)],
+            vec![DeltaOperation::delete(1), DeltaOperation::insert("A")],
+            vec![DeltaOperation::insert("A"), DeltaOperation::insert("")],
+        ];
+        for ops in unmerged {
+            let mut delta = Delta::new(ops);
+            assert!(!delta.is_normalized());
+            delta.compact();
+            assert!(delta.is_normalized());
+        }
+
+        let distinct = Delta::new(vec![
+            DeltaOperation::insert("A"),
+            DeltaOperation::insert_attr("B", bold.clone()),
+            DeltaOperation::retain(1),
+            DeltaOperation::retain_attr(1, bold),
+        ]);
+        assert!(distinct.is_normalized());
+    }
+
+    #[test]
+    fn helper_embeds_passes() {
+        let mut cat = OpsMap::default();
+        cat.insert("image", "octocat.png");
+        let mut dog = OpsMap::default();
+        dog.insert("image", "dog.png");
+        let mut link = Attributes::default();
+        link.insert("link", "https://example.com");
+
+        let mut doc = Delta::default();
+        doc.insert("Hello ");
+        doc.insert(cat.clone());
+        doc.insert(" World");
+        doc.insert_attr(dog.clone(), link.clone());
+        doc.insert("\n");
+        assert!(doc.contains_embed());
+
+        let embeds: Vec<_> = doc.embeds().collect();
+        assert_eq!(embeds.len(), 2);
+        assert_eq!(embeds[0], (6, &OpsVal::from(cat), &Attributes::default()));
+        assert_eq!(embeds[1], (13, &OpsVal::from(dog), &link));
+
+        let mut text = Delta::default();
+        text.insert("Hello");
+        text.retain(2);
+        text.delete(1);
+        assert!(!text.contains_embed());
+        assert_eq!(text.embeds().count(), 0);
+    }
+
+    #[test]
+    fn helper_remap_embeds_passes() {
+        let embed = |id: usize| {
+            let mut attr = Attributes::default();
+            attr.insert("id", id);
+            let mut img = OpsMap::default();
+            img.insert("image", "octocat.png");
+            DeltaOperation::insert_attr(img, attr)
+        };
+
+        let mut a = Delta::default();
+        a.insert("A");
+        a.push(embed(1));
+        let mut b = Delta::default();
+        b.push(embed(1));
+        b.insert("B");
+        b.push(embed(2));
+
+        // both documents use id 1, renumber them all
+        let mut doc = a.concat(b).to_owned();
+        let next_id = std::cell::Cell::new(0);
+        doc.remap_embeds(|value, attr| {
+            let mut attr = attr.clone();
+            next_id.set(next_id.get() + 1);
+            attr.insert("id", next_id.get());
+            (value.clone(), attr)
+        });
+
+        let mut expected = Delta::default();
+        expected.insert("A");
+        expected.push(embed(1));
+        expected.push(embed(2));
+        expected.insert("B");
+        expected.push(embed(3));
+        assert_eq!(doc, expected);
+
+        // retains and deletes are not embeds
+        let mut change = Delta::default();
+        change.retain(2);
+        change.delete(1);
+        let before = change.clone();
+        change.remap_embeds(|_, _| (OpsVal::from("X"), Attributes::default()));
+        assert_eq!(change, before);
+    }
+
+    #[test]
+    fn helper_inserted_content_passes() {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut change = Delta::default();
+        change.insert("A");
+        change.retain(3);
+        change.insert_attr("B", bold.clone());
+        change.retain_attr(2, bold.clone());
+        change.insert_attr("C", bold.clone());
+        change.delete(4);
+        change.insert(img.clone());
+        change.retain(1);
+
+        // inserts separated by retains in the change are adjacent in the content
+        let mut expected = Delta::default();
+        expected.insert("A");
+        expected.insert_attr("BC", bold);
+        expected.insert(img);
+        assert_eq!(change.inserted_content(), expected);
+
+        let mut no_inserts = Delta::default();
+        no_inserts.retain(3);
+        no_inserts.delete(1);
+        assert!(no_inserts.inserted_content().is_empty());
+    }
+
+    #[test]
+    fn helper_normalize_newlines_passes() {
+        let mut align = Attributes::default();
+        align.insert("align", "right");
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut doc = Delta::new(vec![
+            DeltaOperation::insert("One\r\nTwo\rThree\n\r\n"),
+            DeltaOperation::insert(img.clone()),
+            DeltaOperation::insert("Four\r"),
+            DeltaOperation::insert_attr("\nFive\r", align.clone()),
+            DeltaOperation::insert("\r"),
+        ]);
+        doc.normalize_newlines();
+
+        // the split "\r\n" takes the attributes of the "\n"
+        let mut expected = Delta::default();
+        expected.insert("One\nTwo\nThree\n\n");
+        expected.insert(img);
+        expected.insert("Four");
+        expected.insert_attr("\nFive\n", align);
+        expected.insert("\n");
+        assert_eq!(doc, expected);
+
+        assert_eq!(doc.to_lines().unwrap().len(), 7);
+
+        // a lone "\r" insert becomes a "\n", and merges with its neighbours
+        let mut doc = Delta::new(vec![
+            DeltaOperation::insert("A"),
+            DeltaOperation::insert("\r"),
+            DeltaOperation::insert("\nB"),
+        ]);
+        doc.normalize_newlines();
+        let mut expected = Delta::default();
+        expected.insert("A\nB");
+        assert_eq!(doc, expected);
+    }
+
+    #[test]
+    fn helper_summary_passes() {
+        let mut bold_italic = Attributes::default();
+        bold_italic.insert("italic", true);
+        bold_italic.insert("bold", true);
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut delta = Delta::default();
+        delta.insert("Hello");
+        delta.insert(img);
+        delta.retain_attr(3, bold);
+        delta.retain_attr(4, bold_italic);
+        delta.delete(2);
+        assert_eq!(
+            delta.summary(),
+            "[ins:5, ins:1, ret:3(bold), ret:4(bold,italic), del:2]"
+        );
+        assert_eq!(Delta::default().summary(), "[]");
+    }
+
+    #[test]
+    fn helper_block_formats_passes() -> anyhow::Result<()> {
+        let mut header = Attributes::default();
+        header.insert("header", 1);
+        let mut bullet = Attributes::default();
+        bullet.insert("list", "bullet");
+        let mut ordered_right = Attributes::default();
+        ordered_right.insert("list", "ordered");
+        ordered_right.insert("align", "right");
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut doc = Delta::default();
+        doc.insert("Title");
+        doc.insert_attr("\n", header.clone());
+        doc.insert_attr("One", bold);
+        doc.insert_attr("\n", bullet.clone());
+        doc.insert("Two");
+        doc.insert_attr("\n", bullet.clone());
+        doc.insert("First");
+        doc.insert_attr("\n", ordered_right.clone());
+        doc.insert("Text\n");
+
+        let formats = doc.block_formats()?;
+        assert_eq!(
+            formats,
+            vec![
+                header,
+                bullet.clone(),
+                bullet,
+                ordered_right,
+                Attributes::default()
+            ]
+        );
+        assert!(Delta::default().block_formats()?.is_empty());
+
+        let mut change = Delta::default();
+        change.retain(1);
+        assert!(change.block_formats().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn helper_set_block_format_passes() -> anyhow::Result<()> {
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+        let mut bullet = Attributes::default();
+        bullet.insert("list", "bullet");
+        let mut header = Attributes::default();
+        header.insert("header", 2);
+
+        let mut doc = Delta::default();
+        doc.insert("Title\n");
+        doc.insert(img);
+        doc.insert("Second");
+        doc.insert_attr("\n", bullet);
+        doc.insert("Third\nNo new line");
+
+        // make the second line a header, and remove its list format
+        let mut format = header.clone();
+        format.insert("list", AttrVal::Null);
+        let change = doc.set_block_format(1, format.clone())?;
+        let mut expected = Delta::default();
+        expected.retain(13);
+        expected.retain_attr(1, format);
+        assert_eq!(change, expected);
+
+        let doc = doc.compose(&change)?;
+        assert_eq!(
+            doc.block_formats()?,
+            vec![
+                Attributes::default(),
+                header,
+                Attributes::default(),
+                Attributes::default()
+            ]
+        );
+
+        // the last line has no new line character to format
+        assert!(doc.set_block_format(2, Attributes::default()).is_ok());
+        assert!(doc.set_block_format(3, Attributes::default()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn helper_find_text_passes() -> anyhow::Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut doc = Delta::default();
+        doc.insert("foo ");
+        doc.insert_attr("ba", bold.clone());
+        doc.insert("r foo");
+        doc.insert(1);
+        doc.insert_attr("bar", bold);
+
+        // across operation boundaries
+        assert_eq!(doc.find_text("bar", 0)?, Some(4));
+        assert_eq!(doc.find_text("o b", 0)?, Some(2));
+        assert_eq!(doc.find_text("foo", 1)?, Some(8));
+        // the embed is a '\0'
+        assert_eq!(doc.find_text("o\0b", 0)?, Some(10));
+        assert_eq!(doc.find_text("bar", 5)?, Some(12));
+        assert_eq!(doc.find_text("baz", 0)?, None);
+        assert_eq!(doc.find_text("bar", 100)?, None);
+
+        let mut change = Delta::default();
+        change.retain(1);
+        assert!(change.find_text("a", 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn helper_lines_round_trip_passes() -> anyhow::Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut header = Attributes::default();
+        header.insert("header", 1);
+        let mut list = Attributes::default();
+        list.insert("list", "bullet");
+
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut doc = Delta::default();
+        doc.insert("Title");
+        doc.insert_attr("\n", header.clone());
+        doc.insert("Some ");
+        doc.insert_attr("bold", bold.clone());
+        doc.insert(" text\n\n");
+        doc.insert(img.clone());
+        doc.insert_attr("\n", list.clone());
+        doc.insert_attr("item", bold.clone());
+        doc.insert_attr("\n", list.clone());
+
+        let lines = doc.to_lines()?;
+        assert_eq!(lines.len(), 5);
+
+        let mut title = Delta::default();
+        title.insert("Title");
+        assert_eq!(lines[0], (title, header));
+        assert!(lines[2].0.is_empty());
+        assert_eq!(lines[2].1, Attributes::default());
+        let mut item = Delta::default();
+        item.insert_attr("item", bold);
+        assert_eq!(lines[4], (item, list));
+
+        assert_eq!(Delta::from_lines(lines), doc);
+        assert!(Delta::from_lines(Vec::new()).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn helper_lines_non_document_passes() {
+        let mut change = Delta::default();
+        change.insert("a\n");
+        change.retain(1);
+        assert!(change.to_lines().is_err());
+    }
+
+    #[test]
+    fn helper_changed_lines_passes() -> anyhow::Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut header = Attributes::default();
+        header.insert("header", 1);
+
+        let mut doc = Delta::default();
+        doc.insert("First paragraph.\nSecond paragraph.\nThird paragraph.\n");
+        assert!(doc.changed_lines(&doc)?.is_empty());
+
+        // editing the text of one paragraph
+        let mut edited = Delta::default();
+        edited.insert("First paragraph.\nSecond, edited paragraph.\nThird paragraph.\n");
+        assert_eq!(doc.changed_lines(&edited)?, vec![1]);
+
+        // formatting text, or changing the block attributes
+        let mut formatted = Delta::default();
+        formatted.insert("First paragraph.\nSecond paragraph.\nThird ");
+        formatted.insert_attr("paragraph", bold);
+        formatted.insert(".\n");
+        assert_eq!(doc.changed_lines(&formatted)?, vec![2]);
+        let mut titled = Delta::default();
+        titled.insert("First paragraph.");
+        titled.insert_attr("\n", header);
+        titled.insert("Second paragraph.\nThird paragraph.\n");
+        assert_eq!(doc.changed_lines(&titled)?, vec![0]);
+
+        // lines present in one document only
+        let mut longer = doc.clone();
+        longer.insert("Fourth\n");
+        assert_eq!(doc.changed_lines(&longer)?, vec![3]);
+        assert_eq!(longer.changed_lines(&doc)?, vec![3]);
+
+        let mut change = Delta::default();
+        change.retain(1);
+        assert!(doc.changed_lines(&change).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn helper_line_separator_passes() -> anyhow::Result<()> {
+        let mut header = Attributes::default();
+        header.insert("header", 1);
+
+        let mut doc = Delta::with_line_separator('\u{2028}');
+        doc.insert("Title");
+        doc.insert_attr("\u{2028}", header.clone());
+        doc.insert("Line\nwith a line feed\u{2028}Last");
+        assert_eq!(doc.line_separator(), '\u{2028}');
+        assert_eq!(Delta::default().line_separator(), '\n');
+
+        let lines = doc.to_lines()?;
+        assert_eq!(lines.len(), 3);
+        let mut title = Delta::default();
+        title.insert("Title");
+        assert_eq!(lines[0], (title, header.clone()));
+        let mut line = Delta::default();
+        line.insert("Line\nwith a line feed");
+        assert_eq!(lines[1].0, line);
+        assert_eq!(
+            doc.block_formats()?,
+            [header.clone(), Attributes::default(), Attributes::default()]
+        );
+
+        // each_line uses the separator, unless another one is given
+        let count = RefCell::new(0);
+        doc.each_line(
+            |_, _, _| {
+                *count.borrow_mut() += 1;
+                true
+            },
+            None,
+        )?;
+        assert_eq!(count.take(), 3);
+        doc.each_line(
+            |_, _, _| {
+                *count.borrow_mut() += 1;
+                true
+            },
+            Some('\n'),
+        )?;
+        assert_eq!(count.take(), 2);
+
+        // the block format is set on the separator
+        let change = doc.set_block_format(1, header.clone())?;
+        let mut expected = Delta::default();
+        expected.insert("Title");
+        expected.insert_attr("\u{2028}", header.clone());
+        expected.insert("Line\nwith a line feed");
+        expected.insert_attr("\u{2028}", header);
+        expected.insert("Last");
+        assert_eq!(doc.compose(&change)?, expected);
+
+        let mut other = Delta::default();
+        other.insert("Appended");
+        doc.append_block(other);
+        assert_eq!(doc.to_lines()?.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn helper_hunks_passes() -> anyhow::Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut doc = Delta::default();
+        doc.insert("Hello World, how are you?\n");
+
+        // two separated edit regions
+        let mut change = Delta::default();
+        change.retain_attr(5, bold.clone());
+        change.insert("!");
+        change.retain(8);
+        change.delete(3);
+        change.insert("who");
+        let hunks = change.hunks();
+        assert_eq!(hunks.len(), 2);
+
+        let mut first = Delta::default();
+        first.retain_attr(5, bold.clone());
+        first.insert("!");
+        assert_eq!(hunks[0], first);
+        let mut second = Delta::default();
+        second.retain(13);
+        second.insert("who");
+        second.delete(3);
+        assert_eq!(hunks[1], second);
+
+        // each hunk applies on its own, accepting all gives the whole change
+        let mut expected = Delta::default();
+        expected.insert_attr("Hello", bold);
+        expected.insert("! World, how are you?\n");
+        assert_eq!(doc.compose(&hunks[0])?, expected);
+        let mut expected = Delta::default();
+        expected.insert("Hello World, who are you?\n");
+        assert_eq!(doc.compose(&hunks[1])?, expected);
+        let accepted = hunks[0].compose(&hunks[0].transform(&hunks[1], true)?)?;
+        assert_eq!(doc.compose(&accepted)?, doc.compose(&change)?);
+
+        let mut retain = Delta::default();
+        retain.retain(5);
+        assert!(retain.hunks().is_empty());
+        assert!(Delta::default().hunks().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn helper_apply_hunks_passes() -> anyhow::Result<()> {
+        let mut doc = Delta::default();
+        doc.insert("Hello World, how are you?\n");
+
+        // the first hunk shifts the positions of the second one
+        let mut change = Delta::default();
+        change.delete(5);
+        change.insert("Hi there,");
+        change.retain(8);
+        change.delete(3);
+        change.insert("who");
+        assert_eq!(change.hunks().len(), 2);
+
+        let mut expected = Delta::default();
+        expected.insert("Hi there, World, how are you?\n");
+        assert_eq!(change.apply_hunks(&doc, &[0])?, expected);
+        let mut expected = Delta::default();
+        expected.insert("Hello World, who are you?\n");
+        assert_eq!(change.apply_hunks(&doc, &[1])?, expected);
+
+        let all = doc.compose(&change)?;
+        assert_eq!(change.apply_hunks(&doc, &[0, 1])?, all);
+        assert_eq!(change.apply_hunks(&doc, &[1, 0, 1])?, all);
+        assert_eq!(change.apply_hunks(&doc, &[])?, doc);
+        assert!(change.apply_hunks(&doc, &[2]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn helper_split_at_passes() -> anyhow::Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut doc = Delta::default();
+        doc.insert("Hello ");
+        doc.insert_attr("World", bold.clone());
+        doc.insert(1);
+        doc.insert("\n");
+
+        // mid string
+        let (left, right) = doc.split_at(8)?;
+        let mut expected = Delta::default();
+        expected.insert("Hello ");
+        expected.insert_attr("Wo", bold.clone());
+        assert_eq!(left, expected);
+        let mut expected = Delta::default();
+        expected.insert_attr("rld", bold.clone());
+        expected.insert(1);
+        expected.insert("\n");
+        assert_eq!(right, expected);
+        assert_eq!(left.clone().concat(right).to_owned(), doc);
+
+        // at an op boundary, the embed goes to the right
+        let (left, right) = doc.split_at(11)?;
+        let mut expected = Delta::default();
+        expected.insert("Hello ");
+        expected.insert_attr("World", bold);
+        assert_eq!(left, expected);
+        let mut expected = Delta::default();
+        expected.insert(1);
+        expected.insert("\n");
+        assert_eq!(right, expected);
+
+        // at the ends
+        let (left, right) = doc.split_at(0)?;
+        assert!(left.is_empty());
+        assert_eq!(right, doc);
+        let (left, right) = doc.split_at(doc.document_length())?;
+        assert_eq!(left, doc);
+        assert!(right.is_empty());
+
+        assert!(doc.split_at(14).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn helper_range_has_attribute_passes() -> anyhow::Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut bold_italic = bold.clone();
+        bold_italic.insert("italic", true);
+        let mut not_bold = Attributes::default();
+        not_bold.insert("bold", false);
+
+        let mut doc = Delta::default();
+        doc.insert("Hello ");
+        doc.insert_attr("Wor", bold);
+        doc.insert_attr("ld", bold_italic);
+        doc.insert_attr("!", not_bold);
+
+        let is_bold = AttrVal::Bool(true);
+        assert_eq!(
+            doc.range_has_attribute(6, 11, "bold", &is_bold)?,
+            TriState::All
+        );
+        assert_eq!(
+            doc.range_has_attribute(7, 9, "bold", &is_bold)?,
+            TriState::All
+        );
+        assert_eq!(
+            doc.range_has_attribute(4, 8, "bold", &is_bold)?,
+            TriState::Mixed
+        );
+        assert_eq!(
+            doc.range_has_attribute(6, 12, "bold", &is_bold)?,
+            TriState::Mixed
+        );
+        assert_eq!(
+            doc.range_has_attribute(0, 6, "bold", &is_bold)?,
+            TriState::None
+        );
+        // the value must match too
+        assert_eq!(
+            doc.range_has_attribute(11, 12, "bold", &is_bold)?,
+            TriState::None
+        );
+        assert_eq!(
+            doc.range_has_attribute(3, 3, "bold", &is_bold)?,
+            TriState::None
+        );
+
+        assert!(doc.range_has_attribute(6, 13, "bold", &is_bold).is_err());
+        Ok(())
+    }
+
     #[test]
     fn helper_slice_tart_and_end_chop_passes() {
         let mut delta = Delta::default();
@@ -324,4 +1174,395 @@ mod tests {
 
         assert_eq!(slc, expected);
     }
+
+    #[test]
+    fn helper_chunk_mixed_passes() {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut octo = OpsMap::default();
+        octo.insert("image", "octocat.png");
+
+        let mut doc = Delta::default();
+        doc.insert_attr("Hello", bold.clone());
+        doc.insert(octo.clone());
+        doc.insert(" World\n");
+
+        let chunks = doc.chunk(4);
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks.iter().all(|c| c.document_length() <= 4));
+
+        let mut expected = Delta::default();
+        expected.insert_attr("o", bold);
+        expected.insert(octo);
+        expected.insert(" W");
+        assert_eq!(chunks[1], expected);
+
+        let mut joined = Delta::default();
+        for c in chunks {
+            joined.concat(c);
+        }
+        assert_eq!(joined, doc);
+    }
+
+    #[test]
+    fn helper_chunk_non_ascii_passes() {
+        let mut doc = Delta::default();
+        doc.insert("h\u{e9}llo \u{1f370}\n");
+
+        // chunks end between characters, a character longer than the chunk gets its own
+        let texts: Vec<Delta> = ["h", "\u{e9}", "ll", "o ", "\u{1f370}", "\n"]
+            .into_iter()
+            .map(|text| {
+                let mut chunk = Delta::default();
+                chunk.insert(text);
+                chunk
+            })
+            .collect();
+        assert_eq!(doc.chunk(2), texts);
+
+        let mut joined = Delta::default();
+        for c in doc.chunk(4) {
+            assert!(c.document_length() <= 4);
+            joined.concat(c);
+        }
+        assert_eq!(joined, doc);
+    }
+
+    #[test]
+    fn helper_chunk_edge_cases_passes() {
+        let mut doc = Delta::default();
+        assert!(doc.chunk(4).is_empty());
+
+        doc.insert("Hello");
+        assert_eq!(doc.chunk(0), vec![doc.clone()]);
+        assert_eq!(doc.chunk(10), vec![doc.clone()]);
+    }
+
+    #[test]
+    fn helper_for_each_mut_merges_passes() {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut italic = Attributes::default();
+        italic.insert("italic", true);
+
+        let mut delta = Delta::default();
+        delta.insert_attr("Hello", bold.clone());
+        delta.insert_attr(" ", italic);
+        delta.insert_attr("World", bold.clone());
+        assert_eq!(delta.len(), 3);
+
+        delta.for_each_mut(|op| {
+            op.remove_attribute("italic");
+            op.add_attr("bold", false);
+        });
+
+        let mut not_bold = Attributes::default();
+        not_bold.insert("bold", false);
+
+        let mut expected = Delta::default();
+        expected.insert_attr("Hello World", not_bold);
+        assert_eq!(delta, expected);
+    }
+
+    #[test]
+    fn helper_search_text_passes() -> anyhow::Result<()> {
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut alt = Attributes::default();
+        alt.insert("alt", "Lab Octocat");
+
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut doc = Delta::default();
+        doc.insert_attr("Look: ", bold);
+        doc.insert_attr(img.clone(), alt);
+        doc.insert(img);
+        doc.insert("\n");
+
+        let text = doc.to_search_text(&|_val, attr| match attr.get("alt") {
+            Some(AttrVal::String(s)) => s.clone(),
+            _ => "[embed]".to_string(),
+        })?;
+        assert_eq!(text, "Look: Lab Octocat[embed]\n");
+
+        let mut change = Delta::default();
+        change.retain(1);
+        assert!(change.to_search_text(&|_, _| String::new()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn helper_common_prefix_passes() {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut a = Delta::default();
+        a.retain(4);
+        a.retain_attr(2, bold.clone());
+        a.delete(1);
+        let mut b = Delta::default();
+        b.retain(2);
+        b.retain(2);
+        b.retain_attr(3, bold);
+        assert_eq!(a.common_prefix(&b), 6);
+        assert_eq!(b.common_prefix(&a), 6);
+        assert_eq!(a.common_prefix(&a), 7);
+    }
+
+    #[test]
+    fn helper_no_common_prefix_passes() {
+        let mut a = Delta::default();
+        a.insert("Hello");
+        let mut b = Delta::default();
+        b.retain(5);
+        assert_eq!(a.common_prefix(&b), 0);
+        assert_eq!(a.common_prefix(&Delta::default()), 0);
+    }
+
+    #[test]
+    fn helper_common_prefix_text_passes() {
+        let mut a = Delta::default();
+        a.insert("Hello");
+        let mut b = Delta::default();
+        b.insert("Help");
+        assert_eq!(a.common_prefix(&b), 3);
+
+        // differently split inserts
+        let mut c = Delta::default();
+        c.insert("He");
+        c.retain(1);
+        let mut d = Delta::default();
+        d.push(DeltaOperation::insert("H"));
+        d.push(DeltaOperation::insert("e").with_id("x"));
+        assert_eq!(a.common_prefix(&c), 2);
+        assert_eq!(a.common_prefix(&d), 1);
+    }
+
+    #[test]
+    fn helper_common_prefix_non_ascii_passes() {
+        let mut a = Delta::default();
+        a.insert("été");
+        let mut b = Delta::default();
+        b.insert("x");
+        assert_eq!(a.common_prefix(&b), 0);
+        assert_eq!(b.common_prefix(&a), 0);
+
+        // "é" and "è" share their first byte, but not a char
+        let mut c = Delta::default();
+        c.insert("ét");
+        c.insert_attr("è", Attributes::default());
+        assert_eq!(a.common_prefix(&c), "ét".len());
+        assert_eq!(c.common_prefix(&a), "ét".len());
+
+        let mut d = Delta::default();
+        d.insert("é");
+        d.retain(2);
+        assert_eq!(a.common_prefix(&d), "é".len());
+    }
+
+    #[test]
+    fn helper_iter_rev_passes() {
+        let mut delta = Delta::default();
+        delta.insert("Hello");
+        delta.retain(3);
+        delta.delete(2);
+
+        let mut rebuilt: Vec<DeltaOperation> = delta.iter_rev().cloned().collect();
+        assert_eq!(rebuilt[0], DeltaOperation::delete(2));
+        rebuilt.reverse();
+        assert_eq!(Delta::new(rebuilt), delta);
+        assert_eq!(Delta::default().iter_rev().count(), 0);
+    }
+
+    #[test]
+    fn helper_word_count_passes() -> anyhow::Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut doc = Delta::default();
+        doc.insert("Hello, ");
+        doc.insert_attr("wor", bold);
+        doc.insert("ld!\nIt's  a - test.\n\n");
+        assert_eq!(doc.word_count()?, 6);
+        assert_eq!(Delta::default().word_count()?, 0);
+
+        let mut change = Delta::default();
+        change.retain(2);
+        assert!(change.word_count().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn helper_word_count_embeds_passes() -> anyhow::Result<()> {
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        // embeds separate words
+        let mut doc = Delta::default();
+        doc.insert("See");
+        doc.insert(img.clone());
+        doc.insert("here ");
+        doc.insert(img);
+        doc.insert("\n");
+        assert_eq!(doc.word_count()?, 2);
+        assert_eq!(doc.word_count_with_flags(false)?, 2);
+        assert_eq!(doc.word_count_with_flags(true)?, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn helper_eachline_opts_passes() -> anyhow::Result<()> {
+        let mut alt = Attributes::default();
+        alt.insert("alt", "Octocat");
+
+        let mut octo = OpsMap::default();
+        octo.insert("image", "octocat.png");
+
+        let mut a = Delta::default();
+        a.insert("See");
+        a.insert_attr(octo.clone(), alt.clone());
+        a.insert("here\n");
+        a.insert(octo);
+        a.insert("\nEnd");
+
+        // embeds are excluded from the line content
+        let mut expect1 = Delta::default();
+        expect1.insert("Seehere");
+        let expect2 = Delta::default();
+        let mut expect3 = Delta::default();
+        expect3.insert("End");
+        let expected = [expect1, expect2, expect3];
+
+        let options = EachLineOptions {
+            embed_placeholder: Some("[image]".to_string()),
+            count_embeds: false,
+            include_empty_final_line: false,
+        };
+        a.each_line_opts(
+            |delta: &Delta, _: &Attributes, line: usize| -> bool {
+                assert_eq!(delta, expected.get(line).unwrap());
+                true
+            },
+            None,
+            &options,
+        )?;
+
+        // embeds are replaced by a placeholder with their attributes
+        let mut expect1 = Delta::default();
+        expect1.insert("See");
+        expect1.insert_attr(" ", alt);
+        expect1.insert("here");
+        let mut expect2 = Delta::default();
+        expect2.insert(" ");
+        let mut expect3 = Delta::default();
+        expect3.insert("End");
+        let expected = [expect1, expect2, expect3];
+
+        let options = EachLineOptions {
+            embed_placeholder: Some(" ".to_string()),
+            ..EachLineOptions::default()
+        };
+        a.each_line_opts(
+            |delta: &Delta, _: &Attributes, line: usize| -> bool {
+                assert_eq!(delta, expected.get(line).unwrap());
+                true
+            },
+            None,
+            &options,
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn helper_eachline_empty_final_line_passes() -> anyhow::Result<()> {
+        let lines = |doc: &Delta, include_empty_final_line| -> anyhow::Result<Vec<Delta>> {
+            let lines = RefCell::new(Vec::new());
+            let options = EachLineOptions {
+                include_empty_final_line,
+                ..EachLineOptions::default()
+            };
+            doc.each_line_opts(
+                |line: &Delta, _: &Attributes, _| {
+                    lines.borrow_mut().push(line.clone());
+                    true
+                },
+                None,
+                &options,
+            )?;
+            Ok(lines.into_inner())
+        };
+
+        // an empty document has no lines, or a single empty one
+        let empty = Delta::default();
+        assert!(lines(&empty, false)?.is_empty());
+        assert_eq!(lines(&empty, true)?, [Delta::default()]);
+
+        // a document ending on a line break gets a final empty line on request
+        let mut doc = Delta::default();
+        doc.insert("Hello\nWorld\n");
+        let mut hello = Delta::default();
+        hello.insert("Hello");
+        let mut world = Delta::default();
+        world.insert("World");
+        assert_eq!(lines(&doc, false)?, [hello.clone(), world.clone()]);
+        assert_eq!(
+            lines(&doc, true)?,
+            [hello.clone(), world.clone(), Delta::default()]
+        );
+
+        // text after the last line break is the final line in both cases
+        doc.insert("!");
+        let mut bang = Delta::default();
+        bang.insert("!");
+        assert_eq!(lines(&doc, true)?, [hello, world, bang]);
+        Ok(())
+    }
+
+    #[test]
+    fn helper_split_at_keeps_ids_passes() -> anyhow::Result<()> {
+        let mut doc = Delta::default();
+        doc.push(DeltaOperation::insert("Hello").with_id("a"));
+        doc.push(DeltaOperation::insert(" World"));
+
+        let (left, right) = doc.split_at(2)?;
+        assert_eq!(left[0], DeltaOperation::insert("He").with_id("a"));
+        assert_eq!(right[0], DeltaOperation::insert("llo").with_id("a"));
+        assert_eq!(right[1].id(), None);
+
+        // halves with the same id merge again, different ids do not
+        let mut joined = left.clone();
+        joined.append(right);
+        assert_eq!(joined, doc);
+        assert!(joined.is_normalized());
+        Ok(())
+    }
+
+    #[test]
+    fn helper_is_formatting_only_passes() {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut unbold = Attributes::default();
+        unbold.insert("bold", AttrVal::Null);
+
+        let mut toggle = Delta::default();
+        toggle.retain(3);
+        toggle.retain_attr(5, bold);
+        toggle.retain(2);
+        toggle.retain_attr(1, unbold);
+        assert!(toggle.is_formatting_only());
+        assert!(Delta::default().is_formatting_only());
+
+        let mut typing = toggle.clone();
+        typing.insert("A");
+        assert!(!typing.is_formatting_only());
+
+        let mut deleting = Delta::default();
+        deleting.retain(2);
+        deleting.delete(1);
+        assert!(!deleting.is_formatting_only());
+    }
 }
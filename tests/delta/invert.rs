@@ -147,4 +147,200 @@ mod tests {
         assert_eq!(&res, &base);
         Ok(())
     }
+
+    #[test]
+    fn apply_with_inverse_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut italic = Attributes::default();
+        italic.insert("italic", true);
+
+        let mut base = Delta::default();
+        base.insert("123");
+        base.insert_attr("456", bold.clone());
+        base.insert("789");
+
+        let mut remove_bold = Attributes::default();
+        remove_bold.insert("bold", AttrVal::Null);
+        remove_bold.insert("italic", true);
+
+        let mut change = Delta::default();
+        change.retain(1);
+        change.delete(3);
+        change.insert_attr("abc", italic);
+        change.retain_attr(4, remove_bold);
+        change.insert("X");
+
+        let (result, inverse) = base.apply_with_inverse(&change)?;
+        assert_eq!(result, base.compose(&change)?);
+        assert_eq!(inverse, change.invert(&base));
+        assert_eq!(result.compose(&inverse)?, base);
+        Ok(())
+    }
+
+    #[test]
+    fn apply_with_inverse_not_a_document_passes() {
+        let mut base = Delta::default();
+        base.retain(3);
+
+        let mut change = Delta::default();
+        change.insert("A");
+
+        assert!(base.apply_with_inverse(&change).is_err());
+    }
+
+    #[test]
+    fn apply_undoable_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut base = Delta::default();
+        base.insert("Hello ");
+        base.insert_attr("World", bold.clone());
+
+        let mut change = Delta::default();
+        change.retain_attr(5, bold);
+        change.delete(1);
+        change.insert("!");
+
+        let mut doc = base.clone();
+        let undo = doc.apply_undoable(&change)?;
+        assert_eq!(doc, base.compose(&change)?);
+        doc.apply_undoable(&undo)?;
+        assert_eq!(doc, base);
+
+        // a failing change leaves the delta alone
+        let mut not_a_document = change.clone();
+        assert!(not_a_document.apply_undoable(&undo).is_err());
+        assert_eq!(not_a_document, change);
+        Ok(())
+    }
+
+    #[test]
+    fn invert_null_attributes_json_round_trip_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut italic = Attributes::default();
+        italic.insert("italic", true);
+
+        let mut base = Delta::default();
+        base.insert_attr("Hello", italic);
+        base.insert(" World");
+
+        let mut change = Delta::default();
+        change.retain_attr(11, bold);
+        let inverted = change.invert(&base);
+
+        // the inverse removes bold with a null, which survives serialization
+        let json = serde_json::to_string(&inverted)?;
+        assert!(json.contains(r#""bold":null"#));
+        let inverted_json: Delta = serde_json::from_str(&json)?;
+        assert_eq!(inverted_json, inverted);
+
+        assert_eq!(base.compose(&change)?.compose(&inverted_json)?, base);
+        Ok(())
+    }
+
+    #[test]
+    fn clear_formatting_change_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut bold_italic = bold.clone();
+        bold_italic.insert("italic", true);
+        let mut alt = Attributes::default();
+        alt.insert("alt", "Octocat");
+
+        let mut base = Delta::default();
+        base.insert_attr("Hello", bold_italic);
+        base.insert(" ");
+        base.insert_attr(1, alt);
+        base.insert_attr("World", bold);
+        base.insert("\n");
+
+        let change = base.clear_formatting_change()?;
+        let mut remove = Attributes::default();
+        remove.insert("bold", AttrVal::Null);
+        assert_eq!(change.len(), 4);
+        assert_eq!(change.get(3).unwrap().get_attributes(), &remove);
+
+        let json = serde_json::to_string(&change)?;
+        let change: Delta = serde_json::from_str(&json)?;
+
+        let mut expected = Delta::default();
+        expected.insert("Hello ");
+        expected.insert(1);
+        expected.insert("World\n");
+        let cleared = base.compose(&change)?;
+        assert_eq!(cleared, expected);
+
+        // the inverse restores the formatting
+        assert_eq!(cleared.compose(&change.invert(&base))?, base);
+
+        // nothing to clear
+        assert!(expected.clear_formatting_change()?.is_empty());
+
+        let mut not_a_document = Delta::default();
+        not_a_document.retain(1);
+        assert!(not_a_document.clear_formatting_change().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn invert_range_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut italic = Attributes::default();
+        italic.insert("italic", true);
+
+        let mut base = Delta::default();
+        base.insert("Hello World\nSecond line\n");
+
+        // bold "Hello", replace "World" by "Earth", italic "Second"
+        let mut change = Delta::default();
+        change.retain_attr(5, bold.clone());
+        change.retain(1);
+        change.insert("Earth");
+        change.delete(5);
+        change.retain(1);
+        change.retain_attr(6, italic.clone());
+        let changed = base.compose(&change)?;
+
+        // undo the formatting of the first line only
+        let undo = change.invert_range(&base, 0, 6)?;
+        let mut expected = Delta::default();
+        expected.insert("Hello Earth\n");
+        expected.insert_attr("Second", italic.clone());
+        expected.insert(" line\n");
+        assert_eq!(changed.compose(&undo)?, expected);
+
+        // undo the replacement only
+        let undo = change.invert_range(&base, 6, 11)?;
+        let mut expected = Delta::default();
+        expected.insert_attr("Hello", bold.clone());
+        expected.insert(" World\n");
+        expected.insert_attr("Second", italic.clone());
+        expected.insert(" line\n");
+        assert_eq!(changed.compose(&undo)?, expected);
+
+        // ranges partly covering an operation
+        let undo = change.invert_range(&base, 3, 15)?;
+        let mut expected = Delta::default();
+        expected.insert_attr("Hel", bold);
+        expected.insert("lo World\nSec");
+        expected.insert_attr("ond", italic);
+        expected.insert(" line\n");
+        assert_eq!(changed.compose(&undo)?, expected);
+
+        // the whole document is the full inverse, an empty range changes nothing
+        assert_eq!(
+            change.invert_range(&base, 0, base.document_length())?,
+            change.invert(&base)
+        );
+        assert!(change.invert_range(&base, 4, 4)?.is_empty());
+
+        assert!(change.invert_range(&base, 4, 30).is_err());
+        assert!(change.invert_range(&change, 0, 1).is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,39 @@
+#[cfg(test)]
+mod tests {
+    use delta::attributes::Attributes;
+    use delta::delta::Delta;
+    use delta::iterator::DeltaIterator;
+    use delta::operations::{DeltaOperation, OpsVal};
+    use delta::types::attr_val::AttrVal;
+    use delta::utils::DeltaTransformations;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    fn assert_send<T: Send>() {}
+
+    #[test]
+    fn document_types_are_send_sync_passes() {
+        assert_send_sync::<Delta>();
+        assert_send_sync::<DeltaOperation>();
+        assert_send_sync::<OpsVal>();
+        assert_send_sync::<Attributes>();
+        assert_send_sync::<AttrVal>();
+        assert_send::<DeltaIterator>();
+    }
+
+    #[test]
+    fn delta_shared_between_threads_passes() {
+        let mut doc = Delta::default();
+        doc.insert("Hello\n");
+        let doc = std::sync::Arc::new(doc);
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let doc = std::sync::Arc::clone(&doc);
+                std::thread::spawn(move || doc.delta_length())
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 6);
+        }
+    }
+}
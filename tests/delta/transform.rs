@@ -1,8 +1,10 @@
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
+    use delta::attributes;
     use delta::attributes::Attributes;
     use delta::delta::Delta;
+    use delta::operations::DeltaOperation;
     use delta::optransform::OpTransform;
 
     #[test]
@@ -361,4 +363,193 @@ mod tests {
         assert_eq!(b1, b2);
         Ok(())
     }
+
+    #[test]
+    fn conflicts_disjoint_edits_passes() {
+        let mut a = Delta::default();
+        a.retain(2);
+        a.delete(3);
+
+        let mut b = Delta::default();
+        b.retain(5);
+        b.insert("X");
+        b.retain(2);
+        b.delete(1);
+
+        assert!(!a.conflicts_with(&b));
+        assert!(!b.conflicts_with(&a));
+    }
+
+    #[test]
+    fn conflicts_overlapping_deletes_passes() {
+        let mut a = Delta::default();
+        a.retain(2);
+        a.delete(3);
+
+        let mut b = Delta::default();
+        b.retain(4);
+        b.delete(3);
+
+        assert!(a.conflicts_with(&b));
+        assert!(b.conflicts_with(&a));
+    }
+
+    #[test]
+    fn conflicts_insert_and_format_passes() {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut a = Delta::default();
+        a.retain(3);
+        a.insert("X");
+
+        let mut b = Delta::default();
+        b.retain(1);
+        b.retain_attr(4, bold);
+
+        let mut c = Delta::default();
+        c.retain(3);
+        c.insert("Y");
+
+        let mut d = Delta::default();
+        d.retain(10);
+
+        assert!(a.conflicts_with(&b));
+        assert!(a.conflicts_with(&c));
+        assert!(!a.conflicts_with(&d));
+    }
+
+    #[test]
+    fn transform_coalesce_split_delete_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut a = Delta::default();
+        a.retain(1);
+        a.delete(1);
+        a.retain_attr(1, bold.clone());
+        a.delete(1);
+        a.retain(1);
+
+        let mut b = Delta::default();
+        b.retain(1);
+        b.delete(5);
+
+        let mut expected = Delta::default();
+        expected.retain(1);
+        expected.delete(3);
+
+        let r = a.transform(&b, true)?;
+        assert_eq!(r, expected);
+        assert_eq!(r.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn transform_coalesce_delete_with_attributes_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut a = Delta::default();
+        a.retain(2);
+        a.delete(1);
+
+        // a delete carrying attributes, only possible when building the operations by hand
+        let mut del = DeltaOperation::delete(4);
+        del.set_attributes(bold);
+        let b = Delta::new(vec![del]);
+
+        let mut expected = Delta::default();
+        expected.delete(3);
+
+        assert_eq!(a.transform(&b, false)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn transform_with_max_attribute_passes() -> Result<()> {
+        // numeric "size" takes the maximum, other attributes follow the built-in rules
+        let max_size = |ours: &Attributes, theirs: &Attributes, priority: bool| {
+            let mut res = attributes::transform(ours, theirs, priority);
+            let size = |attr: &Attributes| attr.get("size").and_then(|v| v.number_val().ok());
+            match (size(ours), size(theirs)) {
+                (Some(our), Some(their)) if their > our => res.insert("size", their),
+                (Some(_), Some(_)) => {
+                    res.remove("size");
+                }
+                _ => {}
+            }
+            res
+        };
+
+        let size = |n: usize| {
+            let mut attr = Attributes::default();
+            attr.insert("size", n);
+            attr
+        };
+
+        let mut a = Delta::default();
+        a.retain_attr(2, size(10));
+        a.retain_attr(2, size(20));
+
+        let mut b = Delta::default();
+        b.retain_attr(4, size(14));
+
+        // their larger size wins, also when we have priority
+        let mut expected = Delta::default();
+        expected.retain_attr(2, size(14));
+        assert_eq!(a.transform_with(&b, true, max_size)?, expected);
+        assert_eq!(a.transform_with(&b, false, max_size)?, expected);
+
+        // the built-in rules let our size win with priority
+        assert_eq!(a.transform(&b, true)?, Delta::default());
+        assert_eq!(
+            a.transform_with(&b, true, attributes::transform)?,
+            a.transform(&b, true)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn transform_document_as_self_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let mut doc = Delta::default();
+        doc.insert("Hello");
+
+        // a change against the empty document, shifted behind the document
+        let mut change = Delta::default();
+        change.retain_attr(2, bold.clone());
+        change.delete(1);
+        change.insert("X");
+        let mut expected = Delta::default();
+        expected.retain(5);
+        expected.retain_attr(2, bold);
+        expected.delete(1);
+        expected.insert("X");
+        assert_eq!(doc.transform(&change, true)?, expected);
+        assert_eq!(doc.transform(&change, false)?, expected);
+
+        // an insert at position 0 only wins without priority
+        let mut at_start = Delta::default();
+        at_start.insert("A");
+        let mut behind = Delta::default();
+        behind.retain(5);
+        behind.insert("A");
+        assert_eq!(doc.transform(&at_start, true)?, behind);
+        assert_eq!(doc.transform(&at_start, false)?, at_start);
+
+        // the change transformed against a document has the same effect when
+        // applied after it, as the transformed document applied after the change
+        let mut other = Delta::default();
+        other.insert("Hi");
+        let left = doc.compose(&doc.transform(&other, true)?)?;
+        let right = other.compose(&other.transform(&doc, false)?)?;
+        assert_eq!(left, right);
+        let mut expected = Delta::default();
+        expected.insert("HelloHi");
+        assert_eq!(left, expected);
+        Ok(())
+    }
 }
@@ -1,5 +1,6 @@
 #[cfg(test)]
 mod tests {
+    use delta::attributes::Attributes;
     use delta::delta::Delta;
     use delta::optransform::OpTransform;
 
@@ -13,6 +14,31 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn transform_insert_at_zero_passes() -> anyhow::Result<()> {
+        let mut a = Delta::default();
+        a.insert("AB");
+        a.retain(3);
+        a.insert("C");
+
+        // an insert at the position is placed before it without priority
+        assert_eq!(a.transform_position(0, false)?, 2);
+        assert_eq!(a.transform_position(0, true)?, 0);
+
+        // the same for an insert made of several operations
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut b = Delta::default();
+        b.insert("A");
+        b.insert_attr("B", bold);
+        b.delete(1);
+        assert_eq!(b.len(), 3);
+        assert_eq!(b.transform_position(0, false)?, 2);
+        assert_eq!(b.transform_position(0, true)?, 0);
+        assert_eq!(b.transform_position(1, true)?, 2);
+        Ok(())
+    }
+
     #[test]
     fn transform_insert_after_position_passes() -> anyhow::Result<()> {
         let mut a = Delta::default();
@@ -105,4 +131,98 @@ mod tests {
         assert_eq!(r, 1);
         Ok(())
     }
+
+    #[test]
+    fn transform_clamped_out_of_range_passes() -> anyhow::Result<()> {
+        let mut a = Delta::default();
+        a.retain(2);
+        a.delete(3);
+        a.insert("A");
+
+        // base document of length 6 becomes 6 - 3 + 1 = 4
+        assert_eq!(a.transform_position(100, false)?, 98);
+        assert_eq!(a.transform_position_clamped(100, false, 6)?, 4);
+        assert_eq!(a.transform_position_clamped(6, false, 6)?, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn transform_clamped_in_range_passes() -> anyhow::Result<()> {
+        let mut a = Delta::default();
+        a.retain(2);
+        a.insert("AB");
+
+        assert_eq!(a.transform_position_clamped(1, false, 5)?, 1);
+        assert_eq!(a.transform_position_clamped(3, false, 5)?, 5);
+        assert_eq!(a.transform_position_clamped(9, false, 5)?, 7);
+        Ok(())
+    }
+
+    #[test]
+    fn transform_clamped_delete_all_passes() -> anyhow::Result<()> {
+        let mut a = Delta::default();
+        a.delete(10);
+
+        assert_eq!(a.transform_position_clamped(4, false, 5)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn transform_anchor_passes() -> anyhow::Result<()> {
+        // "Hello big World", the anchor covers "big"
+        let anchor = (6, 9);
+
+        // before the anchor
+        let mut insert = Delta::default();
+        insert.retain(2);
+        insert.insert("xxx");
+        assert_eq!(insert.transform_anchor(anchor, false)?, (9, 12));
+        let mut delete = Delta::default();
+        delete.retain(2);
+        delete.delete(3);
+        assert_eq!(delete.transform_anchor(anchor, false)?, (3, 6));
+
+        // inside the anchor
+        let mut insert = Delta::default();
+        insert.retain(7);
+        insert.insert("xx");
+        assert_eq!(insert.transform_anchor(anchor, false)?, (6, 11));
+        let mut delete = Delta::default();
+        delete.retain(7);
+        delete.delete(1);
+        assert_eq!(delete.transform_anchor(anchor, false)?, (6, 8));
+
+        // after the anchor
+        let mut insert = Delta::default();
+        insert.retain(12);
+        insert.insert("xx");
+        assert_eq!(insert.transform_anchor(anchor, false)?, anchor);
+        let mut delete = Delta::default();
+        delete.retain(10);
+        delete.delete(5);
+        assert_eq!(delete.transform_anchor(anchor, true)?, anchor);
+
+        // at the boundaries the priority decides
+        let mut insert = Delta::default();
+        insert.retain(6);
+        insert.insert("xx");
+        assert_eq!(insert.transform_anchor(anchor, true)?, (6, 11));
+        assert_eq!(insert.transform_anchor(anchor, false)?, (8, 11));
+        let mut insert = Delta::default();
+        insert.retain(9);
+        insert.insert("xx");
+        assert_eq!(insert.transform_anchor(anchor, true)?, (6, 9));
+        assert_eq!(insert.transform_anchor(anchor, false)?, (6, 11));
+
+        // deletes overlapping the anchor shrink it, or collapse it
+        let mut delete = Delta::default();
+        delete.retain(4);
+        delete.delete(4);
+        assert_eq!(delete.transform_anchor(anchor, false)?, (4, 5));
+        let mut delete = Delta::default();
+        delete.retain(5);
+        delete.delete(5);
+        assert_eq!(delete.transform_anchor(anchor, false)?, (5, 5));
+        Ok(())
+    }
 }
@@ -1,4 +1,4 @@
-use diffs::{myers, Diff, Replace};
+use diffs::{myers, Diff, NoReplace, Replace};
 use std::char;
 
 #[test]
@@ -52,3 +52,93 @@ fn test_text() {
     assert_eq!(ddd.otn, bb);
     assert_eq!(ddd.nto, aa);
 }
+
+#[test]
+fn test_typed_error() {
+    let a: Vec<char> = "Hallo dit is een leuk verhaal".chars().collect();
+    let b: Vec<char> = "Hallo is een ander verhaal".chars().collect();
+
+    // Stops the diff at the first deletion
+    struct NoDelete {
+        equal: usize,
+    }
+
+    impl Diff for NoDelete {
+        type Error = diffs::Error;
+        fn equal(&mut self, _o: usize, _n: usize, len: usize) -> Result<(), diffs::Error> {
+            self.equal += len;
+            Ok(())
+        }
+        fn delete(&mut self, o: usize, len: usize, _n: usize) -> Result<(), diffs::Error> {
+            Err(diffs::Error::Message(format!("delete {len} at {o}")))
+        }
+    }
+
+    let mut visitor = NoDelete { equal: 0 };
+    let mut diff = Replace::new(&mut visitor);
+    let res = myers::diff(&mut diff, &a, 0, a.len(), &b, 0, b.len());
+    assert_eq!(res, Err(diffs::Error::Message("delete 4 at 5".to_string())));
+    assert_eq!(visitor.equal, 5);
+
+    let err: diffs::Error = ().into();
+    assert_eq!(err, diffs::Error::Unspecified);
+}
+
+#[test]
+fn test_no_replace() {
+    let a: Vec<char> = "abcXYdefg".chars().collect();
+    let b: Vec<char> = "abcZdeg".chars().collect();
+
+    // Records the calls, and has no use for replace
+    struct Calls {
+        calls: Vec<String>,
+    }
+
+    impl Diff for Calls {
+        type Error = ();
+        fn equal(&mut self, o: usize, n: usize, len: usize) -> Result<(), ()> {
+            self.calls.push(format!("equal {o} {n} {len}"));
+            Ok(())
+        }
+        fn delete(&mut self, o: usize, len: usize, n: usize) -> Result<(), ()> {
+            self.calls.push(format!("delete {o} {len} {n}"));
+            Ok(())
+        }
+        fn insert(&mut self, o: usize, n: usize, len: usize) -> Result<(), ()> {
+            self.calls.push(format!("insert {o} {n} {len}"));
+            Ok(())
+        }
+        fn replace(&mut self, _o: usize, _l: usize, _n: usize, _nl: usize) -> Result<(), ()> {
+            panic!("Should not replace")
+        }
+    }
+
+    let mut visitor = Calls { calls: Vec::new() };
+    let mut diff = Replace::new(NoReplace::new(&mut visitor));
+    myers::diff(&mut diff, &a, 0, a.len(), &b, 0, b.len()).unwrap();
+    assert_eq!(
+        visitor.calls,
+        vec![
+            "equal 0 0 3",
+            "delete 3 2 3",
+            "insert 3 3 1",
+            "equal 5 4 2",
+            "delete 7 1 6",
+            "equal 8 6 1",
+        ]
+    );
+
+    // applying the calls to the old version gives the new version
+    let mut result = Vec::new();
+    for call in &visitor.calls {
+        let mut words = call.split(' ');
+        let kind = words.next();
+        let v: Vec<usize> = words.map(|x| x.parse().unwrap()).collect();
+        match kind {
+            Some("equal") => result.extend_from_slice(&a[v[0]..v[0] + v[2]]),
+            Some("insert") => result.extend_from_slice(&b[v[1]..v[1] + v[2]]),
+            _ => {}
+        }
+    }
+    assert_eq!(result, b);
+}
@@ -2,8 +2,9 @@
 mod test {
     use anyhow::Result;
     use delta::attributes::Attributes;
-    use delta::delta::Delta;
-    use delta::operations::{DeltaOperation, OpsVal};
+    use delta::delta::{Delta, DeltaEnvelope};
+    use delta::operations::{DeltaOperation, OpsMap, OpsVal};
+    use delta::optransform::OpTransform;
     use delta::types::ops_kind::OpKind;
 
     #[test]
@@ -65,7 +66,13 @@ mod test {
             {"insert":".\\n"}
             ]}"#;
         let delta: Delta = serde_json::from_str(json).unwrap();
-        assert_eq!(delta.len(), 5);
+        // the two plain inserts at the end are merged when compacting on load
+        let expected_len = if cfg!(feature = "compact-on-load") {
+            4
+        } else {
+            5
+        };
+        assert_eq!(delta.len(), expected_len);
         let op1 = delta.get(1).unwrap();
         assert_eq!(
             op1.get_op_kind(),
@@ -152,4 +159,231 @@ mod test {
 
         assert_eq!(aap.len(), 18);
     }
+
+    #[test]
+    fn aliased_keys_passes() -> Result<()> {
+        let json = r#"{ "ops": [
+            { "Insert": "Hello", "attributes": { "bold": true } },
+            { "RETAIN": 2 },
+            { "ins": { "image": "octocat.png" } },
+            { "del": 3 },
+            { "Delete": 1 },
+            { "ret": 4 }
+        ]}"#;
+        let delta: Delta = serde_json::from_str(json)?;
+
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+
+        let mut expected = Delta::new(vec![
+            DeltaOperation::insert_attr("Hello", bold),
+            DeltaOperation::retain(2),
+            DeltaOperation::insert(img),
+            DeltaOperation::delete(3),
+            DeltaOperation::delete(1),
+            DeltaOperation::retain(4),
+        ]);
+        if cfg!(feature = "compact-on-load") {
+            expected.compact();
+        }
+        assert_eq!(delta, expected);
+
+        // serialization keeps the canonical names
+        let out = serde_json::to_string(&delta)?;
+        assert!(out.contains(r#""retain":2"#));
+        assert!(out.contains(r#""delete":"#));
+        assert!(!out.contains("del\""));
+        Ok(())
+    }
+
+    #[test]
+    fn compact_fragmented_passes() -> Result<()> {
+        let json = r#"{ "ops": [
+            { "insert": "Hel" },
+            { "insert": "lo" },
+            { "insert": "", "attributes": { "bold": true } },
+            { "delete": 1 },
+            { "delete": 2 },
+            { "retain": 1 },
+            { "retain": 1 }
+        ]}"#;
+        let mut delta: Delta = serde_json::from_str(json)?;
+        delta.compact();
+
+        let mut expected = Delta::default();
+        expected.insert("Hello");
+        expected.delete(3);
+        expected.retain(2);
+        assert_eq!(delta, expected);
+        Ok(())
+    }
+
+    #[cfg(feature = "compact-on-load")]
+    #[test]
+    fn compact_on_load_passes() -> Result<()> {
+        let json = r#"{ "ops": [
+            { "insert": "Hel", "attributes": { "bold": true } },
+            { "insert": "lo", "attributes": { "bold": true } },
+            { "insert": "!" }
+        ]}"#;
+        let delta: Delta = serde_json::from_str(json)?;
+
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut expected = Delta::default();
+        expected.insert_attr("Hello", bold);
+        expected.insert("!");
+        assert_eq!(delta, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn from_jsonl_passes() -> Result<()> {
+        let log = r#"{"ops":[{"insert":"Hello World"}]}
+{"ops":[{"retain":6},{"retain":5,"attributes":{"bold":true}}]}
+
+{"retain":11}
+{"ops":[{"retain":11},{"insert":"!"}]}
+"#;
+        let mut doc = Delta::default();
+        let mut count = 0;
+        for change in Delta::from_jsonl(log.as_bytes()) {
+            doc = doc.compose(&change?)?;
+            count += 1;
+        }
+        assert_eq!(count, 4);
+
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut expected = Delta::default();
+        expected.insert("Hello ");
+        expected.insert_attr("World", bold);
+        expected.insert("!");
+        assert_eq!(doc, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn from_jsonl_error_passes() {
+        let log = "{\"ops\":[{\"insert\":\"a\"}]}\n{\"ops\":[{\"insert\":\"b\"}]}\nnot json\n";
+        let res: Vec<_> = Delta::from_jsonl(log.as_bytes()).collect();
+        assert_eq!(res.len(), 3);
+        assert!(res[0].is_ok());
+        assert!(res[1].is_ok());
+        let err = res[2].as_ref().unwrap_err().to_string();
+        assert!(err.contains("line 3"), "{err}");
+    }
+
+    #[test]
+    fn insert_json_passes() -> Result<()> {
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+
+        let value = serde_json::json!({ "image": "octocat.png", "size": { "width": 10 } });
+        let op = DeltaOperation::insert_json(value, bold.clone())?;
+        let mut size = OpsMap::default();
+        size.insert("width", 10);
+        let mut img = OpsMap::default();
+        img.insert("image", "octocat.png");
+        img.insert("size", size);
+        assert_eq!(op, DeltaOperation::insert_attr(img, bold));
+        assert_eq!(op.op_len(), 1);
+
+        let op = DeltaOperation::insert_json(serde_json::json!(1), Attributes::default())?;
+        assert_eq!(op, DeltaOperation::insert(1));
+        let op = DeltaOperation::insert_json(serde_json::json!("Hello"), Attributes::default())?;
+        assert_eq!(op, DeltaOperation::insert("Hello"));
+        assert_eq!(op.op_len(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn insert_json_unsupported_passes() {
+        for value in [
+            serde_json::json!([1, 2]),
+            serde_json::json!(true),
+            serde_json::json!(null),
+            serde_json::json!(-1),
+            serde_json::json!(1.5),
+        ] {
+            assert!(DeltaOperation::insert_json(value, Attributes::default()).is_err());
+        }
+    }
+
+    #[test]
+    fn envelope_passes() -> Result<()> {
+        let json = r#"{"ops":[{"insert":"Hello","attributes":{"bold":true}},{"retain":3}],"version":5,"author":{"name":"ann"}}"#;
+        let envelope: DeltaEnvelope = serde_json::from_str(json)?;
+        assert_eq!(envelope.meta.len(), 2);
+        assert_eq!(envelope.meta["version"], 5);
+        assert_eq!(envelope.meta["author"]["name"], "ann");
+
+        // the metadata survives a round-trip
+        let written = serde_json::to_string(&envelope)?;
+        let reread: DeltaEnvelope = serde_json::from_str(&written)?;
+        assert_eq!(reread, envelope);
+        let value: serde_json::Value = serde_json::from_str(&written)?;
+        assert_eq!(value, serde_json::from_str::<serde_json::Value>(json)?);
+
+        let mut bold = Attributes::default();
+        bold.insert("bold", true);
+        let mut expected = Delta::default();
+        expected.insert_attr("Hello", bold);
+        expected.retain(3);
+        let meta = envelope.meta.clone();
+        assert_eq!(envelope.into_delta(), expected);
+
+        let envelope = DeltaEnvelope::from_delta_with_meta(expected, meta);
+        assert_eq!(reread, envelope);
+        Ok(())
+    }
+
+    #[test]
+    fn from_str_strict_passes() -> Result<()> {
+        let nested = r#"{"ops":[
+            {"insert":"Hello","attributes":{"font":{"family":"Helvetica","size":{"px":15}}}},
+            {"insert":"\n"}
+        ]}"#;
+        let lenient: Delta = serde_json::from_str(nested)?;
+        assert_eq!(lenient.len(), 2);
+        let err = Delta::from_str_strict(nested).unwrap_err();
+        assert!(err.to_string().contains("nested Map-type"));
+
+        let nested_embed = r#"{"ops":[{"insert":{"image":{"src":"octocat.png"}}}]}"#;
+        assert!(serde_json::from_str::<Delta>(nested_embed).is_ok());
+        assert!(Delta::from_str_strict(nested_embed).is_err());
+
+        // flat maps in attributes and embeds are accepted
+        let flat = r#"{"ops":[
+            {"insert":"Hello","attributes":{"font":{"family":"Helvetica"}}},
+            {"insert":{"image":"octocat.png"}},
+            {"retain":3,"attributes":{"bold":true}},
+            {"delete":1}
+        ]}"#;
+        assert_eq!(Delta::from_str_strict(flat)?, serde_json::from_str(flat)?);
+
+        assert!(Delta::from_str_strict(r#"{"ops":[{"insert":1.5}]}"#).is_err());
+        assert!(Delta::from_str_strict("not json").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn from_slice_passes() -> Result<()> {
+        let json = r#"{"ops":[
+            {"insert":"Hello ","attributes":{"bold":true}},
+            {"insert":{"image":"octocat.png"}},
+            {"insert":"Wörld 😀\n"}
+        ]}"#;
+        let bytes: &[u8] = json.as_bytes();
+        let delta = Delta::from_slice(bytes)?;
+        assert_eq!(delta, serde_json::from_str::<Delta>(json)?);
+        assert_eq!(delta.len(), 3);
+
+        let err = Delta::from_slice(b"{\"ops\":[{\"insert\":").unwrap_err();
+        assert!(err.to_string().starts_with("Deserialization error:"));
+        assert!(Delta::from_slice(&[0xff, 0xfe]).is_err());
+        Ok(())
+    }
 }
@@ -2,10 +2,15 @@
 
 mod delta {
     mod builder;
+    mod cached_delta;
     mod compose;
+    mod cursor;
     mod diff;
+    mod embed_length;
+    mod embed_merge;
     mod helpers;
     mod invert;
+    mod send_sync;
     mod transform;
     mod transform_position;
 }